@@ -0,0 +1,507 @@
+use std::{
+	env,
+	fs,
+	error::Error,
+	path::{ Path, PathBuf },
+	process::ExitCode
+};
+
+use spritist_core::{
+	SpriteInfo,
+	Palette,
+	format::{ ChannelExpansion, PixelFormat, SpriteFormat },
+	codec::codec,
+	color_match::ColorMetric,
+	dither::DitherMethod,
+	palette::{ original_palette, reversed_palette, translate_colors },
+	palette_file::{ read_palette_file, write_palette_file },
+	palette_generator::{ GeneratorMethod, generate_palette },
+	pixel_format::{ convert_frames, guess_pixel_format, reinterpret_frames },
+	file::{ read_sprite_file, read_sprite_file_with_format, read_sprite_file_salvaged, write_sprite_file },
+	detect::{ score_formats, detect_format },
+	inspect::inspect_file,
+	validate::validate_sprite,
+	import::{
+		get_image,
+		image_to_blk_frames,
+		import_spritesheet_as_frames,
+		import_spritebuilder_spritesheet_as_frames
+	},
+	batch::{ BatchTarget, batch_convert },
+	export::{
+		export_png_frames,
+		export_gif,
+		export_spritesheet_image,
+		combine_frames,
+		combine_frames_spritebuilder
+	}
+};
+
+const USAGE: &str = "Usage: spritist <command> <input> <output> [options]
+       spritist detect <input>
+       spritist inspect <input>
+       spritist convert-palette <input> <output>
+       spritist generate-palette <input> <output> [--method <median-cut|k-means>]
+
+Commands:
+  convert             Convert a sprite to the format of the output file extension
+  export-png          Export each frame as <output stem>-<index>.png
+  export-gif          Export the frames as an animated GIF
+  export-spritesheet  Export the frames as a single PNG or BMP spritesheet
+  import-spritesheet  Cut a PNG or BMP spritesheet into a sprite file
+  png-to-blk          Cut a PNG or BMP image into a BLK background
+  batch               Convert every sprite in the input folder into the output folder
+  detect              Show which formats the input file could be in, best match first
+  convert-palette     Convert a palette to the format of the output file extension (.dta, .pal
+                        for JASC-PAL, .gpl, .act or .png). The input can also be \"original\" or
+                        \"reversed\" for the built-in C1 palettes
+  generate-palette    Make a palette for the colors in a sprite or image, and save it in the
+                        format of the output file extension. Index 0 stays transparent and
+                        the last 10 colors are the ones C1 reserves
+  inspect             Show the headers, frame offsets and sizes, run statistics and any
+                        unreferenced, overlapping or trailing data of a C16, S16, BLK, M16 or N16 file
+
+Options:
+  --pixel-format <555|565>  Pixel format to write 16-bit sprites in
+  --dither <method>         Dither colors that can't be stored exactly when converting to a
+                            16-bit format or a palette: none (default), floyd-steinberg,
+                            atkinson or bayer
+  --color-metric <metric>   How colors are matched to the palette: rgb (default), weighted-rgb
+                            or lab (CIELAB, best for skin tones)
+  --fix-pixel-format        Convert: if the colors of a C16, S16 or BLK file clearly don't match
+                            the pixel format in its header, read it as the other format
+  --palette <file>          Palette file to use for SPR and Photo Album files, in any of the
+                            palette formats convert-palette can read
+  --method <method>         Generate palette: median-cut (default) or k-means (slower, closer
+                            to the art overall)
+  --cols <n>                Number of spritesheet columns
+  --rows <n>                Number of spritesheet rows
+  --spritebuilder           Use a SpriteBuilder-style spritesheet instead of a grid
+  --combined                Export PNG frames combined into one image (BLK tile order)
+  --lines                   Inspect: also list each frame's line offsets
+  --salvage                 Convert: replace frames that can't be read with empty ones
+                            instead of failing, and list the frames that were lost
+  --delay <ms>              GIF frame delay in milliseconds (default 200)
+  --legacy-colors           Export PNG, GIF and spritesheet colors exactly as older versions
+                            did, instead of expanding each channel to the full 0-255 range
+  --from <extension>        Batch: only convert files with this extension (default all sprites)
+  --to <target>             Batch: spr, s16, c16, m16, n16, blk, dta, png (one file per frame),
+                            or stitched-png (one image per file)";
+
+struct Options {
+	input: PathBuf,
+	output: PathBuf,
+	pixel_format: Option<PixelFormat>,
+	dither: DitherMethod,
+	color_metric: ColorMetric,
+	method: GeneratorMethod,
+	fix_pixel_format: bool,
+	palette: Option<PathBuf>,
+	cols: Option<u32>,
+	rows: Option<u32>,
+	spritebuilder: bool,
+	combined: bool,
+	salvage: bool,
+	lines: bool,
+	legacy_colors: bool,
+	delay: u32,
+	from: Option<String>,
+	to: Option<String>
+}
+
+fn main() -> ExitCode {
+	let args: Vec<String> = env::args().skip(1).collect();
+	if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
+		println!("{}", USAGE);
+		return ExitCode::SUCCESS;
+	}
+	match run(&args[0], &args[1..]) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(why) => {
+			eprintln!("Error: {}", why);
+			ExitCode::FAILURE
+		}
+	}
+}
+
+fn run(command: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+	let path_count = if command == "detect" || command == "inspect" { 1 } else { 2 };
+	let options = parse_options(args, path_count)?;
+	match command {
+		"convert" => convert(&options),
+		"export-png" => export_png(&options),
+		"export-gif" => export_gif_file(&options),
+		"export-spritesheet" => export_spritesheet(&options),
+		"import-spritesheet" => import_spritesheet(&options),
+		"png-to-blk" => png_to_blk(&options),
+		"batch" => batch(&options),
+		"detect" => detect(&options),
+		"inspect" => inspect(&options),
+		"convert-palette" => convert_palette(&options),
+		"generate-palette" => generate_palette_file(&options),
+		_ => Err(format!("Unknown command \"{}\".\n\n{}", command, USAGE).into())
+	}
+}
+
+fn parse_options(args: &[String], path_count: usize) -> Result<Options, Box<dyn Error>> {
+	let mut paths: Vec<PathBuf> = Vec::new();
+	let mut options = Options {
+		input: PathBuf::new(),
+		output: PathBuf::new(),
+		pixel_format: None,
+		dither: DitherMethod::None,
+		color_metric: ColorMetric::Rgb,
+		method: GeneratorMethod::MedianCut,
+		fix_pixel_format: false,
+		palette: None,
+		cols: None,
+		rows: None,
+		spritebuilder: false,
+		combined: false,
+		salvage: false,
+		lines: false,
+		legacy_colors: false,
+		delay: 200,
+		from: None,
+		to: None
+	};
+
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--pixel-format" => {
+				options.pixel_format = match next_value(&mut args, arg)?.as_str() {
+					"555" => Some(PixelFormat::Format555),
+					"565" => Some(PixelFormat::Format565),
+					value => return Err(format!("Invalid pixel format \"{}\". Must be 555 or 565.", value).into())
+				};
+			}
+			"--dither" => {
+				let value = next_value(&mut args, arg)?;
+				options.dither = DitherMethod::from_name(&value)
+					.ok_or(format!("Invalid dither method \"{}\". Must be none, floyd-steinberg, atkinson or bayer.", value))?;
+			}
+			"--color-metric" => {
+				let value = next_value(&mut args, arg)?;
+				options.color_metric = ColorMetric::from_name(&value)
+					.ok_or(format!("Invalid color metric \"{}\". Must be rgb, weighted-rgb or lab.", value))?;
+			}
+			"--method" => {
+				let value = next_value(&mut args, arg)?;
+				options.method = GeneratorMethod::from_name(&value)
+					.ok_or(format!("Invalid palette generator method \"{}\". Must be median-cut or k-means.", value))?;
+			}
+			"--palette" => options.palette = Some(PathBuf::from(next_value(&mut args, arg)?)),
+			"--cols" => options.cols = Some(parse_number(&next_value(&mut args, arg)?, arg)?),
+			"--rows" => options.rows = Some(parse_number(&next_value(&mut args, arg)?, arg)?),
+			"--delay" => options.delay = parse_number(&next_value(&mut args, arg)?, arg)?,
+			"--from" => options.from = Some(next_value(&mut args, arg)?),
+			"--to" => options.to = Some(next_value(&mut args, arg)?),
+			"--spritebuilder" => options.spritebuilder = true,
+			"--combined" => options.combined = true,
+			"--lines" => options.lines = true,
+			"--salvage" => options.salvage = true,
+			"--fix-pixel-format" => options.fix_pixel_format = true,
+			"--legacy-colors" => options.legacy_colors = true,
+			_ if arg.starts_with("--") => return Err(format!("Unknown option \"{}\".", arg).into()),
+			_ => paths.push(PathBuf::from(arg))
+		}
+	}
+
+	if paths.len() != path_count {
+		if path_count == 1 {
+			return Err(format!("Expected an input path.\n\n{}", USAGE).into());
+		}
+		return Err(format!("Expected an input and an output path.\n\n{}", USAGE).into());
+	}
+	if path_count == 2 {
+		options.output = paths.pop().unwrap_or_default();
+	}
+	options.input = paths.pop().unwrap_or_default();
+
+	Ok(options)
+}
+
+fn next_value<'a>(args: &mut impl Iterator<Item = &'a String>, option: &str) -> Result<String, Box<dyn Error>> {
+	match args.next() {
+		Some(value) => Ok(value.clone()),
+		None => Err(format!("Missing value for {}.", option).into())
+	}
+}
+
+fn parse_number(value: &str, option: &str) -> Result<u32, Box<dyn Error>> {
+	value.parse().map_err(|_| format!("Invalid value \"{}\" for {}. Must be a whole number.", value, option).into())
+}
+
+fn load_palette(options: &Options) -> Result<Palette, Box<dyn Error>> {
+	let palette = match &options.palette {
+		Some(palette_path) => read_palette_file(palette_path)?,
+		None => original_palette()
+	};
+	Ok(palette.with_color_metric(options.color_metric))
+}
+
+fn write_sprite(file_path: &Path, mut sprite_info: SpriteInfo, options: &Options, palette: &Palette) -> Result<(), Box<dyn Error>> {
+	if let Some(pixel_format) = options.pixel_format {
+		sprite_info.pixel_format = pixel_format;
+	}
+	sprite_info.read_only = false;
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	if let Some(format) = extension.and_then(SpriteFormat::from_extension) {
+		// frames that already fit the format come through dithering unchanged
+		let capabilities = codec(format).capabilities();
+		if options.dither != DitherMethod::None && capabilities.sixteen_bit {
+			let pixel_format = capabilities.fixed_pixel_format.unwrap_or(sprite_info.pixel_format);
+			sprite_info.frames = convert_frames(&sprite_info.frames, pixel_format, options.dither);
+		}
+		if options.dither != DitherMethod::None && capabilities.indexed && sprite_info.frames.iter().any(|frame| frame.color_indexes.is_empty()) {
+			sprite_info.frames = translate_colors(&sprite_info.frames, palette, options.dither)?;
+		}
+		let file_name = file_path.file_name().and_then(|file_name| file_name.to_str());
+		for issue in validate_sprite(&sprite_info, format, palette, file_name) {
+			if issue.is_error() {
+				return Err(format!("Unable to save as {}. {}", format.name(), issue).into());
+			}
+			println!("Warning: {}", issue);
+		}
+	}
+	write_sprite_file(file_path, sprite_info, palette)
+}
+
+fn channel_expansion(options: &Options) -> ChannelExpansion {
+	if options.legacy_colors {
+		ChannelExpansion::Legacy
+	} else {
+		ChannelExpansion::BitReplication
+	}
+}
+
+fn grid_size(options: &Options, frame_count: usize) -> Result<(u32, u32), Box<dyn Error>> {
+	match (options.cols, options.rows) {
+		(Some(cols), Some(rows)) if cols > 0 && rows > 0 => Ok((cols, rows)),
+		(Some(cols), None) if cols > 0 => Ok((cols, (frame_count as u32 + cols - 1) / cols)),
+		(None, Some(rows)) if rows > 0 => Ok(((frame_count as u32 + rows - 1) / rows, rows)),
+		_ => Err("Spritesheets need --cols and/or --rows, or --spritebuilder.".into())
+	}
+}
+
+fn convert(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let (mut sprite_info, format, lost_frames) = if options.salvage {
+		read_sprite_file_salvaged(&options.input, &palette)?
+	} else {
+		let (sprite_info, format) = read_sprite_file_with_format(&options.input, &palette)?;
+		(sprite_info, format, Vec::new())
+	};
+	if options.fix_pixel_format {
+		if let Some(guess) = guess_pixel_format(&sprite_info.frames) {
+			if guess.confident && guess.pixel_format != sprite_info.pixel_format {
+				println!("Reading {} as {} instead of {}", options.input.display(), pixel_format_name(guess.pixel_format), pixel_format_name(sprite_info.pixel_format));
+				sprite_info.frames = reinterpret_frames(&sprite_info.frames, guess.pixel_format);
+				sprite_info.pixel_format = guess.pixel_format;
+			}
+		}
+	}
+	let frame_count = sprite_info.frames.len();
+	write_sprite(&options.output, sprite_info, options, &palette)?;
+	println!("Converted {} ({}) to {}", options.input.display(), format.name(), options.output.display());
+	if !lost_frames.is_empty() {
+		println!("Salvaged {} of {} frame(s). Lost frames were replaced with empty ones:", frame_count - lost_frames.len(), frame_count);
+		for why in lost_frames {
+			println!("  {}", why);
+		}
+	}
+	Ok(())
+}
+
+fn export_png(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let sprite_info = read_sprite_file(&options.input, &palette)?;
+	if options.combined {
+		let cols = options.cols.unwrap_or(sprite_info.cols.into());
+		let rows = options.rows.unwrap_or(sprite_info.rows.into());
+		let image = combine_frames(&sprite_info.frames, cols as usize, rows as usize, false, channel_expansion(options))?;
+		export_spritesheet_image(&image, &options.output)?;
+	} else {
+		export_png_frames(&sprite_info.frames, &options.output, None, channel_expansion(options))?;
+	}
+	println!("Exported {} frame(s) from {}", sprite_info.frames.len(), options.input.display());
+	Ok(())
+}
+
+fn export_gif_file(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let sprite_info = read_sprite_file(&options.input, &palette)?;
+	export_gif(&sprite_info.frames, &options.output, options.delay, channel_expansion(options))?;
+	println!("Exported {} to {}", options.input.display(), options.output.display());
+	Ok(())
+}
+
+fn export_spritesheet(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let sprite_info = read_sprite_file(&options.input, &palette)?;
+	let image = if options.spritebuilder {
+		combine_frames_spritebuilder(&sprite_info.frames)
+	} else {
+		let (cols, rows) = grid_size(options, sprite_info.frames.len())?;
+		combine_frames(&sprite_info.frames, cols as usize, rows as usize, true, channel_expansion(options))?
+	};
+	export_spritesheet_image(&image, &options.output)?;
+	println!("Exported {} to {}", options.input.display(), options.output.display());
+	Ok(())
+}
+
+fn import_spritesheet(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let frames = if options.spritebuilder {
+		import_spritebuilder_spritesheet_as_frames(&options.input)?
+	} else {
+		match (options.cols, options.rows) {
+			(Some(cols), Some(rows)) if cols > 0 && rows > 0 => import_spritesheet_as_frames(&options.input, cols, rows)?,
+			_ => return Err("Importing a spritesheet needs --cols and --rows, or --spritebuilder.".into())
+		}
+	};
+	let frame_count = frames.len();
+	let sprite_info = SpriteInfo {
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	};
+	write_sprite(&options.output, sprite_info, options, &palette)?;
+	println!("Imported {} frame(s) into {}", frame_count, options.output.display());
+	Ok(())
+}
+
+fn png_to_blk(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let image = get_image(&options.input)?;
+	let (frames, cols, rows) = image_to_blk_frames(&image);
+	let sprite_info = SpriteInfo {
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: cols as u16,
+		rows: rows as u16,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	};
+	write_sprite(&options.output, sprite_info, options, &palette)?;
+	println!("Converted {} to {} ({} x {} tiles)", options.input.display(), options.output.display(), cols, rows);
+	Ok(())
+}
+
+fn batch(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = load_palette(options)?;
+	let target_name = options.to.as_deref().ok_or("Batch conversion needs a --to target.")?;
+	let target = BatchTarget::from_name(target_name).ok_or(format!("Invalid batch target \"{}\".", target_name))?;
+	let report = batch_convert(&options.input, options.from.as_deref(), &options.output, &target, &palette, options.pixel_format)?;
+	println!("{}", report.summary());
+	if report.failed.is_empty() {
+		Ok(())
+	} else {
+		Err(format!("{} file(s) failed to convert", report.failed.len()).into())
+	}
+}
+
+fn detect(options: &Options) -> Result<(), Box<dyn Error>> {
+	let bytes = fs::read(&options.input)?;
+	let extension = options.input.extension().and_then(|extension| extension.to_str());
+	let format_scores = score_formats(&bytes, extension);
+	if format_scores.is_empty() {
+		return Err(format!("{} isn't in a format Spritist can read.", options.input.display()).into());
+	}
+	println!("{}", options.input.display());
+	for format_score in format_scores {
+		println!("  {:<20} {}", format_score.format.name(), format_score.score);
+	}
+	let palette = load_palette(options)?;
+	if let Ok(sprite_info) = read_sprite_file(&options.input, &palette) {
+		if let Some(guess) = guess_pixel_format(&sprite_info.frames) {
+			println!("Pixel format: {} in the header, colors look like {}{} (roughness as 555: {:.1}, as 565: {:.1})",
+				pixel_format_name(sprite_info.pixel_format),
+				pixel_format_name(guess.pixel_format),
+				if guess.confident { "" } else { " (not sure)" },
+				guess.roughness_555,
+				guess.roughness_565);
+		}
+	}
+	Ok(())
+}
+
+fn convert_palette(options: &Options) -> Result<(), Box<dyn Error>> {
+	let palette = match options.input.to_str() {
+		Some("original") => original_palette(),
+		Some("reversed") => reversed_palette(),
+		_ => read_palette_file(&options.input)?
+	};
+	write_palette_file(&options.output, &palette)?;
+	println!("Converted {} to {}", options.input.display(), options.output.display());
+	Ok(())
+}
+
+fn generate_palette_file(options: &Options) -> Result<(), Box<dyn Error>> {
+	let sprite_info = read_sprite_file(&options.input, &load_palette(options)?)?;
+	let palette = generate_palette(&sprite_info.frames, options.method)?;
+	write_palette_file(&options.output, &palette)?;
+	println!("Generated a palette for {} in {}", options.input.display(), options.output.display());
+	Ok(())
+}
+
+fn inspect(options: &Options) -> Result<(), Box<dyn Error>> {
+	let bytes = fs::read(&options.input)?;
+	let extension = options.input.extension().and_then(|extension| extension.to_str());
+	// damaged files are what this is for, so trust the extension over detection
+	let report = match extension.and_then(SpriteFormat::from_extension).and_then(|format| inspect_file(&bytes, format)) {
+		Some(report) => report?,
+		None => {
+			let format = detect_format(&bytes, extension)
+				.ok_or_else(|| format!("{} isn't in a format Spritist can read.", options.input.display()))?;
+			inspect_file(&bytes, format)
+				.ok_or_else(|| format!("{} files can't be inspected.", format.name()))??
+		}
+	};
+
+	println!("{}", options.input.display());
+	println!("Format: {}", report.format.name());
+	println!("File size: {} bytes", report.file_size);
+	println!("Flags: {:#010x} (pixel format {})", report.flags, pixel_format_name(report.pixel_format));
+	if let Some((cols, rows)) = report.background_size {
+		println!("Background: {} x {} tiles", cols, rows);
+	}
+	println!("Headers: {} bytes", report.header_size);
+	println!("Frames: {}", report.frames.len());
+	for (i, frame) in report.frames.iter().enumerate() {
+		print!("  {:>4}: {:>4} x {:<4} at {:#010x}, {} of {} bytes",
+			i, frame.width, frame.height, frame.offset, frame.data_size, frame.raw_size);
+		if let Some(runs) = &frame.runs {
+			print!(" ({:.0}%), {} color run(s) of {} px, {} transparent run(s) of {} px",
+				frame.data_size as f64 * 100.0 / frame.raw_size.max(1) as f64,
+				runs.color_runs, runs.color_pixels, runs.transparent_runs, runs.transparent_pixels);
+		}
+		println!();
+		if options.lines && !frame.line_offsets.is_empty() {
+			let line_offsets: Vec<String> = frame.line_offsets.iter().map(|line_offset| format!("{:#x}", line_offset)).collect();
+			println!("        lines: {}", line_offsets.join(" "));
+		}
+	}
+	if report.anomalies.is_empty() {
+		println!("No anomalies");
+	} else {
+		println!("Anomalies:");
+		for anomaly in &report.anomalies {
+			println!("  {}", anomaly);
+		}
+	}
+	Ok(())
+}
+
+fn pixel_format_name(pixel_format: PixelFormat) -> &'static str {
+	match pixel_format {
+		PixelFormat::Format555 => "555",
+		PixelFormat::Format565 => "565"
+	}
+}
@@ -0,0 +1,66 @@
+use criterion::{ criterion_group, criterion_main, Criterion };
+use image::{ Rgba, RgbaImage };
+
+use spritist_core::{
+	Frame,
+	FrameMetadata,
+	PixelFormat,
+	SpriteInfo,
+	format::{ blk, c16 }
+};
+
+fn sprite_info(frames: Vec<Frame>, cols: u16, rows: u16) -> SpriteInfo {
+	SpriteInfo {
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols,
+		rows,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	}
+}
+
+fn frame(image: RgbaImage) -> Frame {
+	Frame {
+		image,
+		color_indexes: Vec::new(),
+		metadata: FrameMetadata::default(),
+		native_pixels: None
+	}
+}
+
+// a 58 x 16 tile background, about the size of the biggest rooms in the games
+fn background() -> Vec<u8> {
+	let frames = (0..58 * 16).map(|i| {
+		frame(RgbaImage::from_fn(128, 128, |x, y| Rgba([(x * 2) as u8, (y * 2) as u8, i as u8, 255])))
+	}).collect();
+	blk::encode(sprite_info(frames, 58, 16)).unwrap().to_vec()
+}
+
+// 300 frames of a body part, with a transparent border around each one
+fn body_part() -> Vec<u8> {
+	let frames = (0..300).map(|i| {
+		frame(RgbaImage::from_fn(64, 80, |x, y| {
+			if (x as i32 - 32).pow(2) + (y as i32 - 40).pow(2) < 900 {
+				Rgba([(x * 4) as u8, (y * 3) as u8, i as u8, 255])
+			} else {
+				Rgba([0, 0, 0, 0])
+			}
+		}))
+	}).collect();
+	c16::encode(sprite_info(frames, 0, 0)).unwrap().to_vec()
+}
+
+fn decode(c: &mut Criterion) {
+	let background = background();
+	let body_part = body_part();
+	let mut group = c.benchmark_group("decode");
+	group.sample_size(10);
+	group.bench_function("blk 58x16 tiles", |b| b.iter(|| blk::decode(&background).unwrap()));
+	group.bench_function("c16 300 frames", |b| b.iter(|| c16::decode(&body_part).unwrap()));
+	group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spritist_core::format::blk;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = blk::decode(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spritist_core::format::c16;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = c16::decode(data);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spritist_core::format::dta;
+
+fuzz_target!(|data: &[u8]| {
+	let _ = dta::decode(data);
+});
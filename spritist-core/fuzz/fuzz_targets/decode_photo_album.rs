@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spritist_core::{ format::photo_album, palette::original_palette };
+
+fuzz_target!(|data: &[u8]| {
+	let _ = photo_album::decode(data, &original_palette());
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spritist_core::{ format::spr, palette::original_palette };
+
+fuzz_target!(|data: &[u8]| {
+	let palette = original_palette();
+	let _ = spr::decode(data, &palette);
+	let _ = spr::decode_single_width(data, &palette);
+	let _ = spr::decode_double_width(data, &palette);
+	let _ = spr::decode_multi_sprite(data, &palette);
+	let _ = spr::decode_prototype(data, &palette);
+});
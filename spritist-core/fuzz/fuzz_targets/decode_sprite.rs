@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use spritist_core::{ file::decode_sprite, palette::original_palette };
+
+// goes through format detection, so this also covers `detect::score_formats`
+fuzz_target!(|data: &[u8]| {
+	let _ = decode_sprite(data, None, &original_palette());
+});
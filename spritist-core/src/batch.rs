@@ -0,0 +1,145 @@
+use std::{
+	fs,
+	error::Error,
+	path::{ Path, PathBuf }
+};
+
+use crate::{
+	palette::Palette,
+	codec::codec_for_extension,
+	validate::validate_sprite,
+	format::{ ChannelExpansion, PixelFormat },
+	file::{ read_sprite_file, write_sprite_file },
+	export::{ export_png_frames, export_spritesheet_image, combine_frames }
+};
+
+/// What each file in a batch conversion gets turned into.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchTarget {
+	/// Another sprite format, given by its file extension.
+	Sprite(String),
+	/// One PNG file per frame.
+	PngFrames,
+	/// A single PNG with all frames stitched together, in BLK tile order.
+	StitchedPng
+}
+
+impl BatchTarget {
+	pub fn from_name(name: &str) -> Option<BatchTarget> {
+		match name.to_lowercase().as_str() {
+			"png" => Some(BatchTarget::PngFrames),
+			"stitched-png" => Some(BatchTarget::StitchedPng),
+			extension => codec_for_extension(extension)
+				.filter(|codec| !codec.capabilities().read_only)
+				.map(|codec| BatchTarget::Sprite(codec.extensions()[0].to_string()))
+		}
+	}
+}
+
+/// The outcome of a batch conversion.
+#[derive(Clone, Debug, Default)]
+pub struct BatchReport {
+	/// Files that were converted successfully.
+	pub converted: Vec<PathBuf>,
+	/// Files that couldn't be converted, with the reason why.
+	pub failed: Vec<(PathBuf, String)>
+}
+
+impl BatchReport {
+	pub fn summary(&self) -> String {
+		let mut summary = format!("Converted {} file(s), {} failed.", self.converted.len(), self.failed.len());
+		for (file_path, why) in &self.failed {
+			let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+			summary.push_str(&format!("\n{}: {}", file_name, why));
+		}
+		summary
+	}
+}
+
+/// Converts every sprite in `input_dir` (not including subfolders) and writes
+/// the results to `output_dir`. If `source_extension` is given, only files with
+/// that extension are converted, otherwise files in every format that can be
+/// saved are. Files already in the target format are skipped. If
+/// `pixel_format` is given, 16-bit output files are written in that format
+/// instead of the pixel format of the original file.
+pub fn batch_convert(input_dir: &Path, source_extension: Option<&str>, output_dir: &Path, target: &BatchTarget, palette: &Palette, pixel_format: Option<PixelFormat>) -> Result<BatchReport, Box<dyn Error>> {
+	let mut file_paths: Vec<PathBuf> = Vec::new();
+	for entry in fs::read_dir(input_dir)? {
+		let file_path = entry?.path();
+		if file_path.is_file() {
+			let extension = match file_path.extension() {
+				Some(extension) => extension.to_string_lossy().to_lowercase(),
+				None => continue
+			};
+			let is_source = match source_extension {
+				Some(source_extension) => extension == source_extension.to_lowercase(),
+				// any sprite format that can be saved, so not images
+				None => codec_for_extension(&extension).is_some_and(|codec| !codec.capabilities().read_only)
+			};
+			let is_target = match target {
+				BatchTarget::Sprite(target_extension) => extension == *target_extension,
+				_ => false
+			};
+			if is_source && !is_target {
+				file_paths.push(file_path);
+			}
+		}
+	}
+	file_paths.sort();
+
+	fs::create_dir_all(output_dir)?;
+
+	let mut report = BatchReport::default();
+	let mut used_names: Vec<String> = Vec::new();
+	for file_path in file_paths {
+		// files like "body.c16" and "body.s16" would overwrite each other's output
+		let name = file_path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+		if used_names.contains(&name) {
+			report.failed.push((file_path, "Another file with the same name has already been converted".to_string()));
+			continue;
+		}
+		used_names.push(name);
+		match convert_file(&file_path, output_dir, target, palette, pixel_format) {
+			Ok(()) => report.converted.push(file_path),
+			Err(why) => report.failed.push((file_path, why.to_string()))
+		}
+	}
+	Ok(report)
+}
+
+fn convert_file(file_path: &Path, output_dir: &Path, target: &BatchTarget, palette: &Palette, pixel_format: Option<PixelFormat>) -> Result<(), Box<dyn Error>> {
+	let file_stem = file_path.file_stem().ok_or("Invalid file name")?.to_string_lossy();
+	let mut sprite_info = read_sprite_file(file_path, palette)?;
+	if sprite_info.frames.is_empty() {
+		return Err("File has no frames".into());
+	}
+	match target {
+		BatchTarget::Sprite(extension) => {
+			if let Some(pixel_format) = pixel_format {
+				sprite_info.pixel_format = pixel_format;
+			}
+			let output_path = output_dir.join(format!("{}.{}", file_stem, extension));
+			if let Some(codec) = codec_for_extension(extension) {
+				let file_name = output_path.file_name().and_then(|file_name| file_name.to_str());
+				if let Some(issue) = validate_sprite(&sprite_info, codec.format(), palette, file_name).into_iter().find(|issue| issue.is_error()) {
+					return Err(issue.to_string().into());
+				}
+			}
+			write_sprite_file(&output_path, sprite_info, palette)
+		}
+		BatchTarget::PngFrames => {
+			let output_path = output_dir.join(format!("{}.png", file_stem));
+			export_png_frames(&sprite_info.frames, &output_path, None, ChannelExpansion::default())
+		}
+		BatchTarget::StitchedPng => {
+			let (cols, rows) = if sprite_info.cols > 0 && sprite_info.rows > 0 {
+				(sprite_info.cols as usize, sprite_info.rows as usize)
+			} else {
+				(sprite_info.frames.len(), 1)
+			};
+			let image = combine_frames(&sprite_info.frames, cols, rows, false, ChannelExpansion::default())?;
+			let output_path = output_dir.join(format!("{}.png", file_stem));
+			export_spritesheet_image(&image, &output_path)
+		}
+	}
+}
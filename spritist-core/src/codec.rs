@@ -0,0 +1,218 @@
+use std::error::Error;
+
+use bytes::Bytes;
+
+use crate::{
+	error::SpriteError,
+	inspect::FileReport,
+	validate::ValidationIssue,
+	sprite::SpriteInfo,
+	palette::Palette,
+	format::{
+		PixelFormat,
+		SpriteFormat,
+		png,
+		gif,
+		bmp,
+		c16,
+		s16,
+		blk,
+		m16,
+		n16,
+		dta,
+		spr,
+		photo_album
+	}
+};
+
+/// What a format can store and what Spritist can do with it.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+	/// Pixels are palette indexes (SPR, Photo Album), so reading and writing
+	/// the format needs a palette.
+	pub indexed: bool,
+	/// Pixels are 16-bit colors.
+	pub sixteen_bit: bool,
+	/// The colors a 16-bit format always stores, whatever the sprite's
+	/// `pixel_format` says. M16 and N16 keep 5 bits of every channel, the same
+	/// colors as `Format555` in a different bit layout. None for formats that
+	/// store the sprite's `pixel_format`.
+	pub fixed_pixel_format: Option<PixelFormat>,
+	/// The format can be opened but not saved. Use Export instead.
+	pub read_only: bool,
+	/// A plain image format, which can be imported as frames or backgrounds.
+	pub image: bool
+}
+
+/// Everything Spritist knows about one file format. Every format has a codec
+/// in `codecs`, which file detection, opening, saving and the file dialogs
+/// all go through.
+pub trait SpriteCodec: Sync {
+	fn format(&self) -> SpriteFormat;
+
+	/// A human-readable name, including the SPR layout.
+	fn name(&self) -> &'static str;
+
+	/// File extensions for the format in lowercase, the usual one first.
+	fn extensions(&self) -> &'static [&'static str];
+
+	fn capabilities(&self) -> Capabilities;
+
+	/// How well the file's headers fit the format, out of 100, or None if the
+	/// contents rule it out. See `detect::score_formats`.
+	fn detect(&self, contents: &[u8]) -> Option<u32>;
+
+	/// Decodes a file. The palette is only used by indexed formats.
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>>;
+
+	/// Decodes every frame it can, replacing the rest with placeholders, or
+	/// returns None if the format doesn't support salvaging.
+	fn salvage(&self, _contents: &[u8], _palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		None
+	}
+
+	/// Reports the layout of a file, or returns None if the format can't be
+	/// inspected. See `inspect::inspect_file`.
+	fn inspect(&self, _contents: &[u8]) -> Option<Result<FileReport, SpriteError>> {
+		None
+	}
+
+	/// Checks a sprite against the format's rules before it's encoded. See
+	/// `validate::validate_sprite`.
+	fn validate(&self, _sprite_info: &SpriteInfo, _palette: &Palette) -> Vec<ValidationIssue> {
+		Vec::new()
+	}
+
+	/// Encodes a sprite. The palette is only used by indexed formats.
+	fn encode(&self, _sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Err(format!("Unable to save as {}. Use Export instead.", self.name()).into())
+	}
+}
+
+// detection breaks ties in this order, and the first codec for an extension
+// is the one it normally means
+static CODECS: [&dyn SpriteCodec; 15] = [
+	&png::PngCodec,
+	&gif::GifCodec,
+	&bmp::BmpCodec,
+	&c16::C16Codec,
+	&s16::S16Codec,
+	&blk::BlkCodec,
+	&m16::M16Codec,
+	&n16::N16Codec,
+	&dta::DtaCodec,
+	&spr::SprCodec,
+	&spr::SprSingleWidthCodec,
+	&spr::SprDoubleWidthCodec,
+	&spr::SprMultiSpriteCodec,
+	&spr::SprPrototypeCodec,
+	&photo_album::PhotoAlbumCodec
+];
+
+/// Every format Spritist can open.
+pub fn codecs() -> &'static [&'static dyn SpriteCodec] {
+	&CODECS
+}
+
+pub fn codec(format: SpriteFormat) -> &'static dyn SpriteCodec {
+	*CODECS.iter()
+		.find(|codec| codec.format() == format)
+		.expect("every sprite format has a codec")
+}
+
+/// The codec a file extension normally means, ignoring case. SPR files map to
+/// the standard layout.
+pub fn codec_for_extension(extension: &str) -> Option<&'static dyn SpriteCodec> {
+	let extension = extension.to_lowercase();
+	CODECS.iter()
+		.find(|codec| codec.extensions().contains(&extension.as_str()))
+		.copied()
+}
+
+/// The extensions of the codecs that pass `filter`, without duplicates.
+pub fn extensions(filter: impl Fn(&dyn SpriteCodec) -> bool) -> Vec<&'static str> {
+	let mut extensions: Vec<&'static str> = Vec::new();
+	for codec in CODECS.iter().filter(|codec| filter(**codec)) {
+		for extension in codec.extensions() {
+			if !extensions.contains(extension) {
+				extensions.push(extension);
+			}
+		}
+	}
+	extensions
+}
+
+/// Extensions for a file dialog filter. Filters are case-sensitive on some
+/// platforms, so each one is listed in lowercase, uppercase and title case.
+pub fn dialog_extensions(filter: impl Fn(&dyn SpriteCodec) -> bool) -> Vec<String> {
+	let mut dialog_extensions: Vec<String> = Vec::new();
+	for extension in extensions(filter) {
+		let title_case = extension.split(' ')
+			.map(|word| {
+				let mut chars = word.chars();
+				match chars.next() {
+					Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+					None => String::new()
+				}
+			})
+			.collect::<Vec<String>>()
+			.join(" ");
+		for variant in [extension.to_string(), extension.to_uppercase(), title_case] {
+			if !dialog_extensions.contains(&variant) {
+				dialog_extensions.push(variant);
+			}
+		}
+	}
+	dialog_extensions
+}
+
+/// A list of extensions for error messages, like `".spr", ".s16"`.
+pub fn extension_list(filter: impl Fn(&dyn SpriteCodec) -> bool) -> String {
+	extensions(filter).iter()
+		.map(|extension| format!("\".{}\"", extension))
+		.collect::<Vec<String>>()
+		.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{ Rgba, RgbaImage };
+
+	use super::*;
+	use crate::{
+		dither::DitherMethod,
+		palette::original_palette,
+		pixel_format::convert_frame,
+		sprite::{ Frame, FrameMetadata }
+	};
+
+	// converting to the colors a 16-bit format says it stores has to give
+	// exactly the colors that come back out of the file
+	#[test]
+	fn sixteen_bit_codecs_store_their_pixel_format() {
+		let palette = original_palette();
+		let frame = Frame {
+			image: RgbaImage::from_fn(128, 128, |x, y| Rgba([x as u8 * 2, y as u8 * 2, 255 - x as u8, 255])),
+			color_indexes: Vec::new(),
+			metadata: FrameMetadata::default(),
+			native_pixels: None
+		};
+		let sixteen_bit_codecs = codecs().iter().filter(|codec| codec.capabilities().sixteen_bit);
+		for codec in sixteen_bit_codecs {
+			let pixel_format = codec.capabilities().fixed_pixel_format.unwrap_or(PixelFormat::Format565);
+			let converted = convert_frame(&frame, pixel_format, DitherMethod::None);
+			let sprite_info = SpriteInfo {
+				frames: vec![converted.clone()],
+				pixel_format: PixelFormat::Format565,
+				cols: 1,
+				rows: 1,
+				read_only: false,
+				spr_variant: None,
+				dta_variant: None
+			};
+			let contents = codec.encode(sprite_info, &palette).unwrap();
+			let decoded = codec.decode(&contents, &palette).unwrap();
+			assert!(decoded.frames[0].image == converted.image, "{}", codec.name());
+		}
+	}
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use rayon::prelude::*;
+
+/// How the distance between two colors is measured when finding the closest
+/// palette color.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ColorMetric {
+	/// Straight-line distance between the red, green and blue values.
+	#[default]
+	Rgb,
+	/// RGB distance weighted by how sensitive eyes are to each channel, with
+	/// red and blue weighted by how red the colors are ("redmean").
+	WeightedRgb,
+	/// Distance in CIELAB space (CIE76 ΔE), which is close to how different
+	/// the colors look, so it does best with skin tones and dark colors.
+	Lab
+}
+
+impl ColorMetric {
+	pub const ALL: [ColorMetric; 3] = [
+		ColorMetric::Rgb,
+		ColorMetric::WeightedRgb,
+		ColorMetric::Lab
+	];
+
+	/// The name used on the command line, in the config file and by the UI.
+	pub fn name(&self) -> &'static str {
+		match self {
+			ColorMetric::Rgb => "rgb",
+			ColorMetric::WeightedRgb => "weighted-rgb",
+			ColorMetric::Lab => "lab"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<ColorMetric> {
+		ColorMetric::ALL.iter()
+			.find(|metric| metric.name() == name.to_lowercase())
+			.copied()
+	}
+
+	// where a color sits in the space the metric measures in
+	fn point(&self, (r, g, b): (u8, u8, u8)) -> [f32; 3] {
+		match self {
+			ColorMetric::Rgb | ColorMetric::WeightedRgb => [r as f32, g as f32, b as f32],
+			ColorMetric::Lab => rgb_to_lab(r, g, b)
+		}
+	}
+
+	// squared, since only the order matters
+	fn distance(&self, a: &[f32; 3], b: &[f32; 3]) -> f32 {
+		let (d0, d1, d2) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+		match self {
+			ColorMetric::Rgb | ColorMetric::Lab => d0 * d0 + d1 * d1 + d2 * d2,
+			ColorMetric::WeightedRgb => {
+				let red_mean = (a[0] + b[0]) / 2.0;
+				(2.0 + red_mean / 256.0) * d0 * d0 + 4.0 * d1 * d1 + (2.0 + (255.0 - red_mean) / 256.0) * d2 * d2
+			}
+		}
+	}
+}
+
+fn srgb_to_linear(channel: u8) -> f32 {
+	let channel = channel as f32 / 255.0;
+	if channel <= 0.04045 {
+		channel / 12.92
+	} else {
+		((channel + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+// sRGB to CIELAB, with a D65 white point
+fn rgb_to_lab(r: u8, g: u8, b: u8) -> [f32; 3] {
+	let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+	let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+	let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+	let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+	let f = |t: f32| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+	let (fx, fy, fz) = (f(x), f(y), f(z));
+	[116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// The closest palette color to every 16-bit (565) color, so matching a pixel
+/// is one lookup instead of a search through the palette. Colors that are
+/// exactly in the palette always get their own index.
+pub(crate) struct LookupTable {
+	pub(crate) colors: [(u8, u8, u8); 256],
+	pub(crate) metric: ColorMetric,
+	exact_colors: HashMap<(u8, u8, u8), u8>,
+	color_indexes: Vec<u8>
+}
+
+impl LookupTable {
+	pub(crate) fn new(colors: &[(u8, u8, u8); 256], metric: ColorMetric) -> LookupTable {
+		let mut exact_colors: HashMap<(u8, u8, u8), u8> = HashMap::new();
+		for (i, color) in colors.iter().enumerate() {
+			exact_colors.entry(*color).or_insert(i as u8);
+		}
+		let palette_points: Vec<[f32; 3]> = colors.iter().map(|color| metric.point(*color)).collect();
+		let color_indexes = (0..=u16::MAX).into_par_iter()
+			.map(|key| {
+				// the middle of the range of colors that share the key
+				let r = ((key >> 11) as u8) << 3 | 4;
+				let g = ((key >> 5) as u8 & 0x3f) << 2 | 2;
+				let b = (key as u8 & 0x1f) << 3 | 4;
+				let point = metric.point((r, g, b));
+				let mut best_fit_index = 0;
+				let mut best_fit_dist = f32::MAX;
+				for (i, palette_point) in palette_points.iter().enumerate() {
+					let dist = metric.distance(&point, palette_point);
+					if dist < best_fit_dist {
+						best_fit_index = i as u8;
+						best_fit_dist = dist;
+					}
+				}
+				best_fit_index
+			})
+			.collect();
+		LookupTable { colors: *colors, metric, exact_colors, color_indexes }
+	}
+
+	pub(crate) fn find(&self, color: (u8, u8, u8)) -> u8 {
+		if let Some(color_index) = self.exact_colors.get(&color) {
+			return *color_index;
+		}
+		let (r, g, b) = color;
+		let key = (r as usize >> 3) << 11 | (g as usize >> 2) << 5 | b as usize >> 3;
+		self.color_indexes[key]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::palette::original_palette;
+
+	fn closest_color_index(colors: &[(u8, u8, u8); 256], metric: ColorMetric, color: (u8, u8, u8)) -> u8 {
+		let point = metric.point(color);
+		let mut best_fit_index = 0;
+		let mut best_fit_dist = f32::MAX;
+		for (i, palette_color) in colors.iter().enumerate() {
+			let dist = metric.distance(&point, &metric.point(*palette_color));
+			if dist < best_fit_dist {
+				best_fit_index = i as u8;
+				best_fit_dist = dist;
+			}
+		}
+		best_fit_index
+	}
+
+	#[test]
+	fn lookup_tables_match_a_full_search() {
+		let colors = original_palette().colors;
+		for metric in ColorMetric::ALL {
+			let lookup_table = LookupTable::new(&colors, metric);
+			// colors in the middle of their 565 range, which the table is built from
+			for key in (0..=u16::MAX).step_by(97) {
+				let color = (((key >> 11) as u8) << 3 | 4, ((key >> 5) as u8 & 0x3f) << 2 | 2, (key as u8 & 0x1f) << 3 | 4);
+				assert_eq!(lookup_table.find(color), closest_color_index(&colors, metric, color), "{:?} {:?}", metric, color);
+			}
+			// palette colors always get their first index, even if it's 0
+			for (i, color) in colors.iter().enumerate() {
+				assert_eq!(colors[lookup_table.find(*color) as usize], *color, "{:?} {}", metric, i);
+			}
+			assert_eq!(lookup_table.find((0, 0, 0)), 0);
+		}
+	}
+
+	#[test]
+	fn lab_matches_how_colors_look() {
+		let mut colors = [(0, 0, 255); 256];
+		colors[1] = (150, 0, 0);
+		colors[2] = (255, 60, 60);
+		// a strong red is nearer dark red in RGB, but looks closer to light red
+		assert_eq!(LookupTable::new(&colors, ColorMetric::Rgb).find((204, 2, 4)), 1);
+		assert_eq!(LookupTable::new(&colors, ColorMetric::Lab).find((204, 2, 4)), 2);
+		assert_eq!(rgb_to_lab(255, 255, 255).map(|channel| channel.round()), [100.0, 0.0, 0.0]);
+	}
+}
@@ -0,0 +1,438 @@
+use std::cmp::Reverse;
+
+use bytes::Buf;
+
+use crate::{
+	codec::codecs,
+	format::SpriteFormat
+};
+
+// a header that accounts for every byte in the file
+pub(crate) const EXACT_MATCH: u32 = 90;
+// a header that fits in the file but leaves gaps or trailing data
+const LOOSE_MATCH: u32 = 50;
+// bonus for the format matching the file extension, used to break ties
+const EXTENSION_BONUS: u32 = 10;
+// score given to the extension's own format when the structure doesn't fit,
+// so files that only the more forgiving decoders can read still open
+const EXTENSION_FALLBACK: u32 = 5;
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// How likely it is that a file is in a given format, out of 100.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FormatScore {
+	pub format: SpriteFormat,
+	pub score: u32
+}
+
+/// Scores every format the file could be in by checking its headers against
+/// the file contents, best match first. Formats the contents rule out are
+/// left out, except for the format matching `extension` (if any), which is
+/// always included so the decoders get a chance to read it.
+pub fn score_formats(contents: &[u8], extension: Option<&str>) -> Vec<FormatScore> {
+	let extension = extension.map(|extension| extension.to_lowercase());
+
+	let mut scores: Vec<FormatScore> = Vec::new();
+	for codec in codecs() {
+		let format = codec.format();
+		// every SPR layout matches the ".spr" extension
+		let matches_extension = extension.as_ref().is_some_and(|extension| codec.extensions().contains(&extension.as_str()));
+		let score = match codec.detect(contents) {
+			Some(score) if matches_extension => Some(score + EXTENSION_BONUS),
+			Some(score) => Some(score),
+			None if matches_extension => Some(EXTENSION_FALLBACK),
+			None => None
+		};
+		if let Some(score) = score {
+			scores.push(FormatScore { format, score });
+		}
+	}
+
+	// stable sort, so ties keep the order of `codecs`
+	scores.sort_by_key(|format_score| Reverse(format_score.score));
+	scores
+}
+
+/// The most likely format for a file, if any format fits.
+pub fn detect_format(contents: &[u8], extension: Option<&str>) -> Option<SpriteFormat> {
+	score_formats(contents, extension).first().map(|format_score| format_score.format)
+}
+
+fn exact_or_loose(is_exact: bool) -> u32 {
+	if is_exact { EXACT_MATCH } else { LOOSE_MATCH }
+}
+
+pub(crate) fn score_png(contents: &[u8]) -> Option<u32> {
+	if contents.starts_with(PNG_MAGIC) { Some(100) } else { None }
+}
+
+pub(crate) fn score_gif(contents: &[u8]) -> Option<u32> {
+	if contents.starts_with(b"GIF87a") || contents.starts_with(b"GIF89a") { Some(100) } else { None }
+}
+
+pub(crate) fn score_bmp(contents: &[u8]) -> Option<u32> {
+	if contents.len() < 6 || !contents.starts_with(b"BM") { return None; }
+	let mut buffer = &contents[2..];
+	let file_size = buffer.get_u32_le() as usize;
+	Some(if file_size == contents.len() { 100 } else { 60 })
+}
+
+// checks a list of uncompressed images that should follow each other with no
+// gaps, starting straight after the headers
+fn score_image_chain(images: &[(usize, usize)], data_start: usize, file_size: usize) -> Option<u32> {
+	let mut is_exact = true;
+	let mut expected_offset = data_start;
+	for (offset, size) in images {
+		if offset + size > file_size { return None; }
+		is_exact &= *offset == expected_offset;
+		expected_offset = offset + size;
+	}
+	Some(exact_or_loose(is_exact && expected_offset == file_size))
+}
+
+pub(crate) fn score_s16(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 6 { return None; }
+	let flags = buffer.get_u32_le();
+	let image_count = buffer.get_u16_le() as usize;
+	if flags > 1 || buffer.remaining() < image_count * 8 { return None; }
+
+	let mut images = Vec::new();
+	for _ in 0..image_count {
+		let offset = buffer.get_u32_le() as usize;
+		let width = buffer.get_u16_le() as usize;
+		let height = buffer.get_u16_le() as usize;
+		images.push((offset, width * height * 2));
+	}
+	score_image_chain(&images, 6 + (image_count * 8), contents.len())
+}
+
+pub(crate) fn score_m16(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 6 { return None; }
+	let flags = buffer.get_u32();
+	let image_count = buffer.get_u16() as usize;
+	if flags > 1 || buffer.remaining() < image_count * 8 { return None; }
+
+	let mut images = Vec::new();
+	for _ in 0..image_count {
+		let offset = buffer.get_u32() as usize;
+		let width = buffer.get_u16() as usize;
+		let height = buffer.get_u16() as usize;
+		images.push((offset, width * height * 2));
+	}
+	score_image_chain(&images, 6 + (image_count * 8), contents.len())
+}
+
+pub(crate) fn score_blk(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 10 { return None; }
+	let flags = buffer.get_u32_le();
+	let cols = buffer.get_u16_le() as usize;
+	let rows = buffer.get_u16_le() as usize;
+	let image_count = buffer.get_u16_le() as usize;
+	if flags > 1 || image_count != cols * rows || buffer.remaining() < image_count * 8 { return None; }
+
+	let mut images = Vec::new();
+	for _ in 0..image_count {
+		let offset = buffer.get_u32_le() as usize + 4;
+		let width = buffer.get_u16_le();
+		let height = buffer.get_u16_le();
+		if width != 128 || height != 128 { return None; }
+		images.push((offset, 128 * 128 * 2));
+	}
+	score_image_chain(&images, 10 + (image_count * 8), contents.len())
+}
+
+fn get_u16(buffer: &mut &[u8], big_endian: bool) -> u16 {
+	if big_endian { buffer.get_u16() } else { buffer.get_u16_le() }
+}
+
+fn get_u32(buffer: &mut &[u8], big_endian: bool) -> u32 {
+	if big_endian { buffer.get_u32() } else { buffer.get_u32_le() }
+}
+
+// walks the runs of one C16 or N16 line, returning true if they add up to the
+// line width and end with an end-of-line marker. Each run header read uses up
+// one of `budget`, and the line stops fitting once it runs out
+fn check_run_line(contents: &[u8], line_offset: usize, width: usize, big_endian: bool, budget: &mut usize) -> Option<bool> {
+	let mut buffer = contents.get(line_offset..)?;
+	let mut x = 0;
+	while x < width {
+		if *budget == 0 { return Some(false); }
+		*budget -= 1;
+		if buffer.remaining() < 2 { return None; }
+		let run_header = get_u16(&mut buffer, big_endian) as usize;
+		let run_length = run_header >> 1;
+		if run_length == 0 { return Some(false); }
+		if run_header & 1 == 1 {
+			if buffer.remaining() < run_length * 2 { return None; }
+			buffer.advance(run_length * 2);
+		}
+		x += run_length;
+	}
+	Some(x == width && buffer.remaining() >= 2 && get_u16(&mut buffer, big_endian) == 0)
+}
+
+pub(crate) fn score_c16(contents: &[u8]) -> Option<u32> {
+	score_run_images(contents, false)
+}
+
+// N16 is C16 with big-endian words
+pub(crate) fn score_n16(contents: &[u8]) -> Option<u32> {
+	score_run_images(contents, true)
+}
+
+fn score_run_images(contents: &[u8], big_endian: bool) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 6 { return None; }
+	let flags = get_u32(&mut buffer, big_endian);
+	let image_count = get_u16(&mut buffer, big_endian) as usize;
+	if big_endian {
+		if flags > 3 { return None; }
+	} else if flags != 2 && flags != 3 {
+		return None;
+	}
+
+	let mut images = Vec::new();
+	for _ in 0..image_count {
+		if buffer.remaining() < 8 { return None; }
+		let first_line_offset = get_u32(&mut buffer, big_endian) as usize;
+		let width = get_u16(&mut buffer, big_endian) as usize;
+		let height = get_u16(&mut buffer, big_endian) as usize;
+		if buffer.remaining() < height.saturating_sub(1) * 4 { return None; }
+		// like the decoder, frames 0 px tall have no lines and ignore the offset
+		let mut line_offsets = Vec::new();
+		if height > 0 {
+			line_offsets.push(first_line_offset);
+		}
+		for _ in 1..height {
+			line_offsets.push(get_u32(&mut buffer, big_endian) as usize);
+		}
+		images.push((width, line_offsets));
+	}
+	let headers_end = contents.len() - buffer.remaining();
+
+	// a real file has a run header in at most every other byte, so any more
+	// means lines share their data. Crafted files can point thousands of long
+	// lines at the same runs, which would take ages to walk one by one
+	let mut budget = contents.len() / 2;
+	let mut is_exact = true;
+	let mut line_count = 0;
+	for (i, (width, line_offsets)) in images.iter().enumerate() {
+		for line_offset in line_offsets {
+			// frames 0 px tall before the first line only have an end of image marker
+			if line_count == 0 {
+				is_exact &= *line_offset == headers_end + i * 2;
+			}
+			line_count += 1;
+			if budget == 0 { return Some(LOOSE_MATCH); }
+			is_exact &= check_run_line(contents, *line_offset, *width, big_endian, &mut budget)?;
+		}
+	}
+	// a header with no image data to check could be almost anything
+	Some(exact_or_loose(is_exact && line_count > 0))
+}
+
+pub(crate) fn score_dta(contents: &[u8]) -> Option<u32> {
+	let (char_width, char_height, char_count) = match contents.len() {
+		4096 => (4, 8, 128),
+		9472 => (6, 12, 128),
+		17152 => (11, 12, 128),
+		18944 => (6, 12, 256),
+		_ => return None
+	};
+	let char_data_size = char_width * char_height * char_count;
+
+	// charset pixels only use the first 4 colors
+	let mut is_exact = contents[..char_data_size].iter().all(|color_index| *color_index < 4);
+	if char_height == 12 {
+		let mut buffer = &contents[char_data_size..];
+		while buffer.remaining() >= 2 {
+			is_exact &= buffer.get_u16_le() as usize <= char_width;
+		}
+	}
+	Some(if is_exact { EXACT_MATCH } else { 30 })
+}
+
+pub(crate) fn score_spr(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 2 { return None; }
+	let image_count = buffer.get_u16_le() as usize;
+	if buffer.remaining() < image_count * 8 { return None; }
+
+	let mut images = Vec::new();
+	for _ in 0..image_count {
+		let offset = buffer.get_u32_le() as usize;
+		let width = buffer.get_u16_le() as usize;
+		let height = buffer.get_u16_le() as usize;
+		images.push((offset, width * height));
+	}
+	score_image_chain(&images, 2 + (image_count * 8), contents.len())
+}
+
+pub(crate) fn score_spr_prototype(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 2 { return None; }
+	let image_count = buffer.get_u16_le() as usize;
+	if buffer.remaining() < image_count * 4 { return None; }
+
+	let mut images = Vec::new();
+	for _ in 0..image_count {
+		let offset = buffer.get_u16_le() as usize;
+		let width = buffer.get_u8() as usize;
+		let height = buffer.get_u8() as usize;
+		images.push((offset, width * height));
+	}
+	score_image_chain(&images, 2 + (image_count * 4), contents.len())
+}
+
+pub(crate) fn score_spr_single_width(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 6 { return None; }
+	let image_count = buffer.get_u16_le();
+	buffer.advance(4);
+	for _ in 0..image_count {
+		if buffer.remaining() < 4 { return None; }
+		let width = buffer.get_u16_le() as usize;
+		let height = buffer.get_u16_le() as usize;
+		if buffer.remaining() < width * height { return None; }
+		buffer.advance(width * height);
+	}
+	Some(exact_or_loose(buffer.remaining() == 0))
+}
+
+// returns the number of bytes used by a double-width sprite
+fn walk_spr_double_width(contents: &[u8]) -> Option<usize> {
+	let mut buffer = contents;
+	if buffer.remaining() < 6 { return None; }
+	let image_count = buffer.get_u16_le();
+	buffer.advance(4);
+	for _ in 0..image_count {
+		if buffer.remaining() < 10 { return None; }
+		let padded_width = buffer.get_u32_le() as usize;
+		let height = buffer.get_u32_le() as usize;
+		let width = buffer.get_u16_le() as usize;
+		if padded_width < width || height > 65535 { return None; }
+		let size = padded_width.checked_mul(height)?;
+		if buffer.remaining() < size { return None; }
+		buffer.advance(size);
+	}
+	Some(contents.len() - buffer.remaining())
+}
+
+pub(crate) fn score_spr_double_width(contents: &[u8]) -> Option<u32> {
+	let size = walk_spr_double_width(contents)?;
+	Some(exact_or_loose(size == contents.len()))
+}
+
+pub(crate) fn score_spr_multi_sprite(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 6 { return None; }
+	let sprite_count = buffer.get_u16_le();
+	buffer.advance(4);
+	for _ in 0..sprite_count {
+		let size = walk_spr_double_width(buffer)?;
+		buffer.advance(size);
+	}
+	Some(exact_or_loose(buffer.remaining() == 0))
+}
+
+fn skip_photo_album_string(buffer: &mut &[u8], length_byte: u8) -> Option<()> {
+	let mut length = length_byte as usize;
+	if length == 255 {
+		if buffer.remaining() < 2 { return None; }
+		length = buffer.get_u16_le() as usize;
+	}
+	if buffer.remaining() < length { return None; }
+	buffer.advance(length);
+	Some(())
+}
+
+pub(crate) fn score_photo_album(contents: &[u8]) -> Option<u32> {
+	let mut buffer = contents;
+	if buffer.remaining() < 2 { return None; }
+	let image_count = buffer.get_u16_le();
+	for _ in 0..image_count {
+		if buffer.remaining() < 1 { return None; }
+		let timestamp_length = buffer.get_u8();
+		skip_photo_album_string(&mut buffer, timestamp_length)?;
+
+		if buffer.remaining() < 12 { return None; }
+		let width = buffer.get_u32_le() as usize;
+		let height = buffer.get_u32_le() as usize;
+		buffer.advance(4);
+		let size = width.checked_mul(height)?;
+		if size == 0 { continue; }
+		if buffer.remaining() < size { return None; }
+		// the last pixel byte doubles as the length of the comment
+		let comment_length = buffer[size - 1];
+		buffer.advance(size);
+		skip_photo_album_string(&mut buffer, comment_length)?;
+	}
+	Some(exact_or_loose(buffer.remaining() == 0))
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::{ BufMut, BytesMut };
+	use image::{ Rgba, RgbaImage };
+
+	use super::*;
+	use crate::{ Frame, FrameMetadata, PixelFormat, SpriteInfo, format::c16 };
+
+	#[test]
+	fn shared_run_lines_stay_within_budget() {
+		// 20000 lines of 65535 one-pixel transparent runs, all at the same offset
+		let (width, height) = (u16::MAX, 20_000_u16);
+		let mut contents = BytesMut::new();
+		contents.put_u32_le(3);
+		contents.put_u16_le(1);
+		let line_offset = 6 + 8 + (height as u32 - 1) * 4;
+		contents.put_u32_le(line_offset);
+		contents.put_u16_le(width);
+		contents.put_u16_le(height);
+		for _ in 1..height {
+			contents.put_u32_le(line_offset);
+		}
+		for _ in 0..width {
+			contents.put_u16_le(2);
+		}
+		contents.put_u16_le(0);
+		contents.put_u16_le(0);
+
+		assert_eq!(score_c16(&contents), Some(LOOSE_MATCH));
+	}
+
+	#[test]
+	fn c16_files_without_image_data_are_loose_matches() {
+		assert_eq!(score_c16(&[3, 0, 0, 0, 0, 0]), Some(LOOSE_MATCH));
+		// one frame 0 px tall, which the decoder reads as an empty image
+		let contents = [3, 0, 0, 0, 1, 0, 0, 0, 0, 0, 4, 0, 0, 0];
+		assert!(c16::decode(&contents).is_ok());
+		assert_eq!(score_c16(&contents), Some(LOOSE_MATCH));
+	}
+
+	#[test]
+	fn c16_frames_0_px_tall_are_allowed() {
+		let frames = [(4, 0), (1, 1), (2, 0)].map(|(width, height)| Frame {
+			image: RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255])),
+			color_indexes: Vec::new(),
+			metadata: FrameMetadata::default(),
+			native_pixels: None
+		});
+		let sprite_info = SpriteInfo {
+			frames: frames.to_vec(),
+			pixel_format: PixelFormat::Format565,
+			cols: 0,
+			rows: 0,
+			read_only: false,
+			spr_variant: None,
+			dta_variant: None
+		};
+		let contents = c16::encode(sprite_info).unwrap();
+		assert_eq!(c16::decode(&contents).unwrap().frames.len(), 3);
+		assert_eq!(score_c16(&contents), Some(EXACT_MATCH));
+	}
+}
@@ -0,0 +1,177 @@
+use image::RgbaImage;
+
+/// How colors that can't be stored exactly are approximated when converting
+/// to a palette or a 16-bit pixel format.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum DitherMethod {
+	/// Every pixel gets the closest color, so smooth gradients band.
+	#[default]
+	None,
+	/// Spreads all of each pixel's error over the pixels right of and below it.
+	FloydSteinberg,
+	/// Spreads 3/4 of the error further afield, which keeps flat areas cleaner
+	/// and more contrast, at the cost of some detail in the darkest and lightest
+	/// parts.
+	Atkinson,
+	/// Ordered dithering with an 8 x 8 Bayer matrix. Each pixel only depends on
+	/// its own color and position, so frames of an animation don't flicker.
+	Bayer
+}
+
+impl DitherMethod {
+	pub const ALL: [DitherMethod; 4] = [
+		DitherMethod::None,
+		DitherMethod::FloydSteinberg,
+		DitherMethod::Atkinson,
+		DitherMethod::Bayer
+	];
+
+	/// The name used on the command line, in the config file and by the UI.
+	pub fn name(&self) -> &'static str {
+		match self {
+			DitherMethod::None => "none",
+			DitherMethod::FloydSteinberg => "floyd-steinberg",
+			DitherMethod::Atkinson => "atkinson",
+			DitherMethod::Bayer => "bayer"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<DitherMethod> {
+		DitherMethod::ALL.iter()
+			.find(|method| method.name() == name.to_lowercase())
+			.copied()
+	}
+}
+
+// where the error goes, as (right, down, sixteenths of the error). Errors are
+// added up in sixteenths too, since a few levels of error split 6 ways would
+// otherwise round away to nothing
+const FLOYD_STEINBERG: [(isize, usize, i32); 4] = [(1, 0, 7), (-1, 1, 3), (0, 1, 5), (1, 1, 1)];
+const ATKINSON: [(isize, usize, i32); 6] = [(1, 0, 2), (2, 0, 2), (-1, 1, 2), (0, 1, 2), (1, 1, 2), (0, 2, 2)];
+
+const BAYER: [[i32; 8]; 8] = [
+	[0, 32, 8, 40, 2, 34, 10, 42],
+	[48, 16, 56, 24, 50, 18, 58, 26],
+	[12, 44, 4, 36, 14, 46, 6, 38],
+	[60, 28, 52, 20, 62, 30, 54, 22],
+	[3, 35, 11, 43, 1, 33, 9, 41],
+	[51, 19, 59, 27, 49, 17, 57, 25],
+	[15, 47, 7, 39, 13, 45, 5, 37],
+	[63, 31, 55, 23, 61, 29, 53, 21]
+];
+
+/// Maps every pixel of an image to a value (a color index or an encoded
+/// pixel) with `quantize`, which also returns the color the value stands for.
+/// Returns the values in row-major order. Transparent pixels get `transparent`
+/// and are left out of the dithering, so no error leaks in or out across the
+/// edges of a sprite. `bayer_spread` is how far ordered dithering can push
+/// each channel, which should be about the gap between the colors that can be
+/// stored.
+pub(crate) fn dither_image<T: Copy>(image: &RgbaImage, method: DitherMethod, bayer_spread: [i32; 3], transparent: T, mut quantize: impl FnMut([u8; 3]) -> (T, [u8; 3])) -> Vec<T> {
+	let width = image.width() as usize;
+	let height = image.height() as usize;
+	let kernel: &[(isize, usize, i32)] = match method {
+		DitherMethod::FloydSteinberg => &FLOYD_STEINBERG,
+		DitherMethod::Atkinson => &ATKINSON,
+		DitherMethod::None | DitherMethod::Bayer => &[]
+	};
+	let mut errors: Vec<[i32; 3]> = if kernel.is_empty() { Vec::new() } else { vec![[0; 3]; width * height] };
+	let is_opaque = |i: usize| image.as_raw()[i * 4 + 3] != 0;
+
+	let mut values: Vec<T> = Vec::with_capacity(width * height);
+	for (i, pixel) in image.pixels().enumerate() {
+		if pixel[3] == 0 {
+			values.push(transparent);
+			continue;
+		}
+		let (x, y) = (i % width, i / width);
+		let mut wanted = [0_u8; 3];
+		for channel in 0..3 {
+			let offset = match method {
+				DitherMethod::None => 0,
+				// from just under -1/2 to just under 1/2 of the spread
+				DitherMethod::Bayer => (BAYER[y % 8][x % 8] * 2 - 63) * bayer_spread[channel] / 128,
+				DitherMethod::FloydSteinberg | DitherMethod::Atkinson => errors[i][channel] / 16
+			};
+			wanted[channel] = (pixel[channel] as i32 + offset).clamp(0, 255) as u8;
+		}
+		let (value, color) = quantize(wanted);
+		values.push(value);
+
+		for (right, down, weight) in kernel {
+			let (neighbor_x, neighbor_y) = (x as isize + right, y + down);
+			if neighbor_x < 0 || neighbor_x as usize >= width || neighbor_y >= height {
+				continue;
+			}
+			let neighbor = neighbor_y * width + neighbor_x as usize;
+			if !is_opaque(neighbor) {
+				continue;
+			}
+			for channel in 0..3 {
+				errors[neighbor][channel] += (wanted[channel] as i32 - color[channel] as i32) * weight;
+			}
+		}
+	}
+	values
+}
+
+#[cfg(test)]
+mod tests {
+	use image::Rgba;
+
+	use super::*;
+
+	// dithers to black (0) and white (1), with transparent pixels as 2
+	fn dither_to_black_and_white(image: &RgbaImage, method: DitherMethod) -> Vec<u8> {
+		dither_image(image, method, [255; 3], 2, |[r, _, _]| {
+			if r >= 128 { (1, [255; 3]) } else { (0, [0; 3]) }
+		})
+	}
+
+	fn white_pixels(values: &[u8]) -> usize {
+		values.iter().filter(|value| **value == 1).count()
+	}
+
+	#[test]
+	fn dithering_keeps_the_average_color() {
+		// 100 / 255 of the pixels should end up white
+		let grey = RgbaImage::from_pixel(16, 16, Rgba([100, 100, 100, 255]));
+		assert_eq!(white_pixels(&dither_to_black_and_white(&grey, DitherMethod::None)), 0);
+		let floyd_steinberg = white_pixels(&dither_to_black_and_white(&grey, DitherMethod::FloydSteinberg));
+		assert!((95..=106).contains(&floyd_steinberg), "{}", floyd_steinberg);
+		let bayer = white_pixels(&dither_to_black_and_white(&grey, DitherMethod::Bayer));
+		assert!((90..=110).contains(&bayer), "{}", bayer);
+		// Atkinson drops a quarter of the error, so it comes out darker
+		let atkinson = white_pixels(&dither_to_black_and_white(&grey, DitherMethod::Atkinson));
+		assert!(atkinson > 0 && atkinson < floyd_steinberg, "{}", atkinson);
+	}
+
+	#[test]
+	fn ordered_dithering_only_depends_on_position() {
+		let mut grey = RgbaImage::from_pixel(16, 16, Rgba([100, 100, 100, 255]));
+		let before = dither_to_black_and_white(&grey, DitherMethod::Bayer);
+		grey.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+		let after = dither_to_black_and_white(&grey, DitherMethod::Bayer);
+		assert_eq!((before[0], after[0]), (0, 1));
+		assert_eq!(before[1..], after[1..]);
+	}
+
+	#[test]
+	fn error_does_not_spread_across_transparent_pixels() {
+		// a column of grey that leaves lots of error, beside a transparent column
+		let with_neighbor = RgbaImage::from_fn(3, 8, |x, _| match x {
+			0 => Rgba([127, 127, 127, 255]),
+			1 => Rgba([0, 0, 0, 0]),
+			_ => Rgba([100, 100, 100, 255])
+		});
+		let mut alone = with_neighbor.clone();
+		for y in 0..8 {
+			alone.put_pixel(0, y, Rgba([0, 0, 0, 0]));
+		}
+		let with_neighbor = dither_to_black_and_white(&with_neighbor, DitherMethod::FloydSteinberg);
+		let alone = dither_to_black_and_white(&alone, DitherMethod::FloydSteinberg);
+		assert!(with_neighbor.chunks(3).all(|row| row[1] == 2));
+		let last_column = |values: &[u8]| values.chunks(3).map(|row| row[2]).collect::<Vec<u8>>();
+		assert_eq!(last_column(&with_neighbor), last_column(&alone));
+	}
+}
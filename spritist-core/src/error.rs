@@ -0,0 +1,101 @@
+use std::{ error::Error, fmt };
+
+/// What went wrong while reading or writing a sprite file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteErrorKind {
+	/// The file ends before the end of the file header.
+	TruncatedFileHeader,
+	/// The file ends in the middle of an image header.
+	TruncatedImageHeader,
+	/// The file ends in the middle of an image's pixel data.
+	TruncatedImageData,
+	/// An image or line offset points outside the file.
+	BadLineOffset(u32),
+	/// A compressed run doesn't fit in the rest of its line.
+	BadRunLength(u16),
+	/// A frame is a size the format doesn't allow.
+	DimensionMismatch { width: u32, height: u32, expected: String },
+	/// The file header has flags the format doesn't support.
+	UnsupportedFlags(u32),
+	/// The file isn't one of the sizes the format allows.
+	InvalidFileSize(usize),
+	/// Decoding would need more pixels than Spritist allows.
+	ImageTooLarge { pixels: u64, limit: u64 },
+	/// Any other problem, described in plain text.
+	Other(String)
+}
+
+/// An error from one of the sprite codecs, with the frame and byte offset
+/// where it happened when they're known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteError {
+	pub kind: SpriteErrorKind,
+	pub frame: Option<usize>,
+	pub offset: Option<usize>
+}
+
+impl SpriteError {
+	pub fn new(kind: SpriteErrorKind) -> SpriteError {
+		SpriteError { kind, frame: None, offset: None }
+	}
+
+	pub fn at_frame(mut self, frame: usize) -> SpriteError {
+		self.frame = Some(frame);
+		self
+	}
+
+	pub fn at_offset(mut self, offset: usize) -> SpriteError {
+		self.offset = Some(offset);
+		self
+	}
+
+	/// What went wrong, without the location.
+	pub fn description(&self) -> String {
+		match &self.kind {
+			SpriteErrorKind::TruncatedFileHeader => "Invalid data. File ends in the middle of file header.".to_string(),
+			SpriteErrorKind::TruncatedImageHeader => "Invalid data. File ends in the middle of an image header.".to_string(),
+			SpriteErrorKind::TruncatedImageData => "Invalid data. File ends in the middle of an image.".to_string(),
+			SpriteErrorKind::BadLineOffset(line_offset) => format!("Invalid data. Image data offset {} is outside the file.", line_offset),
+			SpriteErrorKind::BadRunLength(run_length) => format!("Invalid data. A run of {} pixels goes past the end of the line.", run_length),
+			SpriteErrorKind::DimensionMismatch { width, height, expected } => format!("Frame is {} x {} px, but {}.", width, height, expected),
+			SpriteErrorKind::UnsupportedFlags(flags) => format!("Unsupported file flags ({:#x}).", flags),
+			SpriteErrorKind::InvalidFileSize(size) => format!("Invalid file size ({} bytes).", size),
+			SpriteErrorKind::ImageTooLarge { pixels, limit } => format!("Image is too large to open ({} pixels, the limit is {}).", pixels, limit),
+			SpriteErrorKind::Other(message) => message.clone()
+		}
+	}
+
+	/// Where it went wrong, e.g. "frame 3, byte 1024 (0x400)", if known.
+	pub fn location(&self) -> Option<String> {
+		let frame = self.frame.map(|frame| format!("frame {}", frame));
+		let offset = self.offset.map(|offset| format!("byte {} ({:#x})", offset, offset));
+		match (frame, offset) {
+			(Some(frame), Some(offset)) => Some(format!("{}, {}", frame, offset)),
+			(Some(location), None) | (None, Some(location)) => Some(location),
+			(None, None) => None
+		}
+	}
+}
+
+impl fmt::Display for SpriteError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.location() {
+			Some(location) => write!(f, "{} ({})", self.description(), location),
+			None => write!(f, "{}", self.description())
+		}
+	}
+}
+
+impl Error for SpriteError {}
+
+impl From<String> for SpriteError {
+	fn from(message: String) -> SpriteError {
+		SpriteError::new(SpriteErrorKind::Other(message))
+	}
+}
+
+impl From<&str> for SpriteError {
+	fn from(message: &str) -> SpriteError {
+		SpriteError::new(SpriteErrorKind::Other(message.to_string()))
+	}
+}
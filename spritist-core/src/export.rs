@@ -0,0 +1,226 @@
+use std::{
+	fs::File,
+	error::Error,
+	path::{ Path, PathBuf }
+};
+
+use rand::random;
+
+use image::{
+	Delay,
+	Rgba,
+	RgbaImage,
+	GenericImage,
+	Frame as GifFrame,
+	codecs::gif::{ GifEncoder, Repeat }
+};
+
+use crate::{
+	sprite::Frame,
+	format::png::encode as encode_png,
+	format::bmp::encode as encode_bmp,
+	format::{ ChannelExpansion, PixelFormat, encode_pixel, parse_pixel }
+};
+
+/// Writes each frame to its own PNG file, named `<file stem>-<frame index>.png`
+/// and placed next to `file_path`. If `frame_indexes` is given, only those
+/// frames are written.
+pub fn export_png_frames(frames: &[Frame], file_path: &Path, frame_indexes: Option<&[usize]>, expansion: ChannelExpansion) -> Result<(), Box<dyn Error>> {
+	let base_dir = file_path.parent().ok_or("Invalid file path")?;
+	let file_stem = file_path.file_stem().ok_or("Invalid file name")?;
+	for (i, frame) in frames.iter().enumerate() {
+		let export_frame = match frame_indexes {
+			Some(frame_indexes) => frame_indexes.contains(&i),
+			None => true
+		};
+		if export_frame {
+			let file_path = base_dir.join(format!("{}-{}.png", file_stem.to_string_lossy(), i));
+			encode_png(&frame.expanded_image(expansion), file_path)?;
+		}
+	}
+	Ok(())
+}
+
+/// Writes the frames as an infinitely looping animated GIF.
+pub fn export_gif(frames: &[Frame], file_path: &Path, frame_delay: u32, expansion: ChannelExpansion) -> Result<(), Box<dyn Error>> {
+	let delay = Delay::from_numer_denom_ms(frame_delay, 1);
+	let mut gif_frames: Vec<GifFrame> = Vec::new();
+	for frame in frames {
+		let gif_frame = GifFrame::from_parts(frame.expanded_image(expansion).into_owned(), 0, 0, delay);
+		gif_frames.push(gif_frame);
+	}
+
+	let file = File::create(file_path)?;
+	let mut gif_encoder = GifEncoder::new(file);
+	gif_encoder.set_repeat(Repeat::Infinite)?;
+	gif_encoder.encode_frames(gif_frames)?;
+	Ok(())
+}
+
+/// Writes a spritesheet image as a BMP if the path ends in `.bmp`, or as a PNG
+/// otherwise.
+pub fn export_spritesheet_image(image: &RgbaImage, file_path: &Path) -> Result<(), Box<dyn Error>> {
+	if file_path.to_string_lossy().to_lowercase().ends_with(".bmp") {
+		encode_bmp(image, PathBuf::from(file_path))
+	} else {
+		encode_png(image, PathBuf::from(file_path))
+	}
+}
+
+/// Lays the frames out on a grid of `cols` x `rows` tiles, each the size of the
+/// largest frame. Frames fill the grid row by row if `by_rows` is true, or
+/// column by column (the BLK tile order) if not.
+pub fn combine_frames(frames: &[Frame], cols: usize, rows: usize, by_rows: bool, expansion: ChannelExpansion) -> Result<RgbaImage, Box<dyn Error>> {
+	if cols == 0 || rows == 0 {
+		return Err("Spritesheets need at least one column and one row.".into());
+	}
+
+	let mut tile_width = 0;
+	let mut tile_height = 0;
+	for frame in frames {
+		if frame.image.width() > tile_width { tile_width = frame.image.width(); }
+		if frame.image.height() > tile_height { tile_height = frame.image.height(); }
+	}
+
+	let image_width = tile_width * cols as u32;
+	let image_height = tile_height * rows as u32;
+	let mut output_image = RgbaImage::new(image_width, image_height);
+
+	for (i, frame) in frames.iter().enumerate() {
+		let tile_x = if by_rows { i % cols } else { i / rows };
+		let tile_y = if by_rows { i / cols } else { i % rows };
+
+		let image = frame.expanded_image(expansion);
+		for y in 0..image.height() {
+			for x in 0..image.width() {
+				let pixel = *image.get_pixel(x, y);
+				let image_x = (tile_x as u32 * tile_width) + x;
+				let image_y = (tile_y as u32 * tile_height) + y;
+				if image_x < output_image.width() && image_y < output_image.height() {
+					output_image.put_pixel(image_x, image_y, pixel);
+				}
+			}
+		}
+	}
+
+	Ok(output_image)
+}
+
+/// Packs the frames into a SpriteBuilder-style spritesheet: frames separated by
+/// a margin of a divider color that isn't used anywhere in the frames.
+pub fn combine_frames_spritebuilder(frames: &[Frame]) -> RgbaImage {
+	let margin = 5;
+
+	// get all used colors
+	let mut used_colors = Vec::new();
+	for frame in frames.iter() {
+		for pixel in frame.image.pixels() {
+			let color = encode_pixel(pixel, PixelFormat::Format555);
+			if !used_colors.contains(&color) {
+				used_colors.push(color);
+			}
+		}
+	}
+
+	// find unused color for divider color
+	let mut divider_color = Rgba::<u8>([0, 255, 255, 255]);
+	for _try in 0..10000 {
+		let color = random::<u16>();
+		if !used_colors.contains(&color) {
+			divider_color = parse_pixel(color, PixelFormat::Format555);
+			break;
+		}
+	}
+
+	// get spritesheet width
+	let mut max_width = 640;
+	for frame in frames.iter() {
+		if frame.image.width() > max_width {
+			max_width = frame.image.width();
+		}
+	}
+	max_width += margin * 2;
+
+	// get spritesheet height
+	let mut spritesheet_height = margin;
+	let mut row_height = 0;
+	let mut max_width_used = 0;
+	let mut width_used = margin;
+	for frame in frames.iter() {
+		if width_used + frame.image.width() + margin > max_width {
+			if width_used > max_width_used {
+				max_width_used = width_used;
+			}
+			width_used = margin;
+			spritesheet_height += row_height + margin;
+		}
+		width_used += frame.image.width() + margin;
+		if frame.image.height() > row_height {
+			row_height = frame.image.height();
+		}
+	}
+	if width_used > max_width_used {
+		max_width_used = width_used;
+	}
+	spritesheet_height += row_height + margin;
+	let spritesheet_width = max_width_used;
+
+	// create spritesheet image
+	let mut spritesheet_image = RgbaImage::new(spritesheet_width, spritesheet_height);
+
+	// fill spritesheet with divider color
+	for pixel in spritesheet_image.pixels_mut() {
+		*pixel = divider_color;
+	}
+
+	// draw sprites
+	let mut x = margin;
+	let mut y = margin;
+	let mut row_height = 0;
+	for frame in frames.iter() {
+		if x + frame.image.width() + margin > spritesheet_width {
+			x = margin;
+			y += row_height + margin;
+		}
+		let _ = spritesheet_image.copy_from(&frame.image, x, y);
+		x += frame.image.width() + margin;
+		if frame.image.height() > row_height {
+			row_height = frame.image.height();
+		}
+	}
+
+	spritesheet_image
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sprite::FrameMetadata;
+
+	fn frame(color: [u8; 4]) -> Frame {
+		Frame {
+			image: RgbaImage::from_pixel(2, 2, Rgba(color)),
+			color_indexes: Vec::new(),
+			metadata: FrameMetadata::default(),
+			native_pixels: None
+		}
+	}
+
+	#[test]
+	fn combine_frames_fills_rows_or_columns() {
+		let (red, green, blue) = ([255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]);
+		let frames = [frame(red), frame(green), frame(blue)];
+		let by_rows = combine_frames(&frames, 2, 2, true, ChannelExpansion::BitReplication).unwrap();
+		assert_eq!(by_rows.dimensions(), (4, 4));
+		assert_eq!([by_rows.get_pixel(2, 0).0, by_rows.get_pixel(0, 2).0, by_rows.get_pixel(2, 2).0], [green, blue, [0, 0, 0, 0]]);
+		let by_cols = combine_frames(&frames, 2, 2, false, ChannelExpansion::BitReplication).unwrap();
+		assert_eq!([by_cols.get_pixel(0, 2).0, by_cols.get_pixel(2, 0).0], [green, blue]);
+	}
+
+	#[test]
+	fn combine_frames_needs_a_column_and_a_row() {
+		let frames = [frame([255, 0, 0, 255])];
+		assert!(combine_frames(&frames, 0, 2, true, ChannelExpansion::BitReplication).is_err());
+		assert!(combine_frames(&frames, 2, 0, false, ChannelExpansion::BitReplication).is_err());
+	}
+}
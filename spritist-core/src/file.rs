@@ -0,0 +1,135 @@
+use std::{
+	fs,
+	error::Error,
+	path::Path
+};
+
+use bytes::Bytes;
+
+use crate::{
+	error::SpriteError,
+	sprite::SpriteInfo,
+	palette::Palette,
+	codec::{ codec, codec_for_extension, extension_list },
+	detect::{ EXACT_MATCH, FormatScore, score_formats },
+	format::SpriteFormat
+};
+
+/// Reads a sprite or image file. The format is detected from the file contents,
+/// using the file extension to break ties, and returned along with the sprite.
+/// The palette is only used by the indexed formats (SPR and Photo Album).
+pub fn read_sprite_file_with_format(file_path: &Path, palette: &Palette) -> Result<(SpriteInfo, SpriteFormat), Box<dyn Error>> {
+	let bytes = fs::read(file_path)?;
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	decode_sprite(&bytes, extension, palette)
+}
+
+/// Reads a sprite or image file, detecting its format from the file contents.
+pub fn read_sprite_file(file_path: &Path, palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+	Ok(read_sprite_file_with_format(file_path, palette)?.0)
+}
+
+// formats other than the one the file extension is for have to match the file exactly,
+// including the other SPR layouts for ".spr" files
+fn is_trusted(format_score: &FormatScore, extension_format: Option<SpriteFormat>) -> bool {
+	extension_format.is_none() || format_score.score >= EXACT_MATCH || Some(format_score.format) == extension_format
+}
+
+/// Decodes a file in whichever format scores best in `detect::score_formats`,
+/// falling back to the next best format if decoding fails. Formats other than
+/// the one the file extension is for are only tried if their headers match
+/// the file exactly, so damaged files report what's wrong with them instead of
+/// opening as garbage in another format.
+pub fn decode_sprite(contents: &[u8], extension: Option<&str>, palette: &Palette) -> Result<(SpriteInfo, SpriteFormat), Box<dyn Error>> {
+	let extension_format = extension.and_then(SpriteFormat::from_extension);
+	let mut first_error: Option<Box<dyn Error>> = None;
+	for format_score in score_formats(contents, extension) {
+		if !is_trusted(&format_score, extension_format) {
+			continue;
+		}
+		match decode_sprite_as(contents, format_score.format, palette) {
+			Ok(sprite_info) => return Ok((sprite_info, format_score.format)),
+			Err(why) => {
+				if first_error.is_none() {
+					first_error = Some(why);
+				}
+			}
+		}
+	}
+	Err(first_error.unwrap_or_else(|| "Unable to open file. It isn't in a format Spritist can read (SPR, S16, C16, BLK, etc.)".into()))
+}
+
+/// Reads a sprite file like `read_sprite_file_with_format`, but recovers what
+/// it can from damaged files instead of failing. See `salvage_sprite`.
+pub fn read_sprite_file_salvaged(file_path: &Path, palette: &Palette) -> Result<(SpriteInfo, SpriteFormat, Vec<SpriteError>), Box<dyn Error>> {
+	let bytes = fs::read(file_path)?;
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	salvage_sprite(&bytes, extension, palette)
+}
+
+/// Decodes a file like `decode_sprite`, but if no format can decode it, decodes
+/// every frame it can in the most likely format and puts transparent
+/// placeholders in place of the rest. Also returns the errors for the frames
+/// that were lost, which is empty if the whole file could be decoded.
+pub fn salvage_sprite(contents: &[u8], extension: Option<&str>, palette: &Palette) -> Result<(SpriteInfo, SpriteFormat, Vec<SpriteError>), Box<dyn Error>> {
+	let first_error = match decode_sprite(contents, extension, palette) {
+		Ok((sprite_info, format)) => return Ok((sprite_info, format, Vec::new())),
+		Err(why) => why
+	};
+	let extension_format = extension.and_then(SpriteFormat::from_extension);
+	for format_score in score_formats(contents, extension) {
+		if !is_trusted(&format_score, extension_format) {
+			continue;
+		}
+		if let Some(Ok((sprite_info, lost_frames))) = salvage_sprite_as(contents, format_score.format, palette) {
+			return Ok((sprite_info, format_score.format, lost_frames));
+		}
+	}
+	Err(first_error)
+}
+
+/// Salvages a file as a specific format, or returns `None` if the format
+/// doesn't support salvaging.
+pub fn salvage_sprite_as(contents: &[u8], format: SpriteFormat, palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+	codec(format).salvage(contents, palette)
+}
+
+/// Decodes a file as a specific format.
+pub fn decode_sprite_as(contents: &[u8], format: SpriteFormat, palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+	codec(format).decode(contents, palette)
+}
+
+/// Encodes a sprite in the given format. `SpriteFormat::Spr` writes the layout
+/// stored in `sprite_info.spr_variant`, and the other SPR formats their own.
+pub fn encode_sprite(sprite_info: SpriteInfo, format: SpriteFormat, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+	codec(format).encode(sprite_info, palette)
+}
+
+fn extension_error() -> String {
+	format!("File does not have a valid file extension ({})", extension_list(|codec| !codec.capabilities().read_only))
+}
+
+/// Encodes a sprite in the format that matches the given file extension,
+/// ignoring case.
+pub fn encode_sprite_file(sprite_info: SpriteInfo, extension: &str, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+	let codec = codec_for_extension(extension).ok_or_else(extension_error)?;
+	codec.encode(sprite_info, palette)
+}
+
+/// Encodes a sprite in the format that matches the file extension of
+/// `file_path` and writes it to disk.
+pub fn write_sprite_file(file_path: &Path, sprite_info: SpriteInfo, palette: &Palette) -> Result<(), Box<dyn Error>> {
+	let extension = file_path.extension().ok_or_else(extension_error)?;
+	let extension_str = extension.to_str().ok_or_else(extension_error)?;
+	let data = encode_sprite_file(sprite_info, extension_str, palette)?;
+	fs::write(file_path, &data)?;
+	Ok(())
+}
+
+/// Encodes a sprite in the given format and writes it to disk, whatever the
+/// file extension is.
+pub fn write_sprite_file_as(file_path: &Path, sprite_info: SpriteInfo, format: SpriteFormat, palette: &Palette) -> Result<(), Box<dyn Error>> {
+	let data = encode_sprite(sprite_info, format, palette)?;
+	fs::write(file_path, &data)?;
+	Ok(())
+}
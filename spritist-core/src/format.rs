@@ -0,0 +1,386 @@
+use bytes::Buf;
+use image::{ GenericImage, Rgba, RgbaImage };
+use rayon::prelude::*;
+
+use crate::{
+	codec::{ codec, codec_for_extension },
+	error::{ SpriteError, SpriteErrorKind },
+	sprite::{ Frame, FrameMetadata, SpriteInfo }
+};
+
+pub mod png;
+pub mod gif;
+pub mod bmp;
+pub mod spr;
+pub mod s16;
+pub mod m16;
+pub mod n16;
+pub mod c16;
+pub mod blk;
+pub mod dta;
+pub mod photo_album;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PixelFormat {
+	Format555,
+	Format565
+}
+
+/// Every file format Spritist can open, with each SPR layout listed separately.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpriteFormat {
+	Spr,
+	SprSingleWidth,
+	SprDoubleWidth,
+	SprMultiSprite,
+	SprPrototype,
+	S16,
+	C16,
+	M16,
+	N16,
+	Blk,
+	Dta,
+	PhotoAlbum,
+	Png,
+	Gif,
+	Bmp
+}
+
+impl SpriteFormat {
+	/// A human-readable name, including the SPR layout.
+	pub fn name(&self) -> &'static str {
+		codec(*self).name()
+	}
+
+	/// The usual file extension for the format, in lowercase.
+	pub fn extension(&self) -> &'static str {
+		codec(*self).extensions()[0]
+	}
+
+	/// The format a file extension normally means, ignoring case. SPR files
+	/// map to the standard layout.
+	pub fn from_extension(extension: &str) -> Option<SpriteFormat> {
+		codec_for_extension(extension).map(|codec| codec.format())
+	}
+
+	pub fn is_spr(&self) -> bool {
+		self.extension() == "spr"
+	}
+}
+
+/// The most pixels a single frame can have.
+pub const MAX_FRAME_PIXELS: u64 = 8192 * 8192;
+/// The most pixels a whole file can decode to. Several image headers can point
+/// at the same data, so this can't be enforced by the file size alone.
+pub const MAX_TOTAL_PIXELS: u64 = 128 * 1024 * 1024;
+
+/// Counts the pixels a decoder allocates, so that header values can't make it
+/// allocate more than `MAX_FRAME_PIXELS` per frame or `MAX_TOTAL_PIXELS` in total.
+#[derive(Default)]
+pub struct PixelBudget {
+	total_pixels: u64
+}
+
+impl PixelBudget {
+	/// Call before allocating a frame of `width` x `height` pixels.
+	pub fn allocate(&mut self, width: u64, height: u64, frame: usize) -> Result<(), SpriteError> {
+		let pixels = width.saturating_mul(height);
+		if pixels > MAX_FRAME_PIXELS {
+			return Err(SpriteError::new(SpriteErrorKind::ImageTooLarge { pixels, limit: MAX_FRAME_PIXELS }).at_frame(frame));
+		}
+		self.total_pixels += pixels;
+		if self.total_pixels > MAX_TOTAL_PIXELS {
+			return Err(SpriteError::new(SpriteErrorKind::ImageTooLarge { pixels: self.total_pixels, limit: MAX_TOTAL_PIXELS }).at_frame(frame));
+		}
+		Ok(())
+	}
+}
+
+/// Fails if `buffer` has fewer than `size` bytes left, for checking that a
+/// frame's data is all there before allocating anything for it.
+pub fn check_remaining(buffer: &[u8], size: u64, file_size: usize, frame: usize) -> Result<(), SpriteError> {
+	if (buffer.remaining() as u64) < size {
+		return Err(image_error(frame, file_size));
+	}
+	Ok(())
+}
+
+/// A transparent frame to stand in for one that couldn't be decoded when
+/// salvaging a file. It's the size the frame should have been if that's known
+/// and fits in `budget`, otherwise 1 x 1 px.
+pub fn placeholder_frame(size: Option<(u32, u32)>, budget: &mut PixelBudget, frame: usize) -> Frame {
+	let (width, height) = match size {
+		Some((width, height)) if budget.allocate(width.into(), height.into(), frame).is_ok() => (width, height),
+		_ => (1, 1)
+	};
+	Frame {
+		image: RgbaImage::new(width, height),
+		color_indexes: Vec::new(),
+		metadata: FrameMetadata::default(),
+		native_pixels: None
+	}
+}
+
+/// Turns the result of a codec's `salvage` function into the result of a
+/// normal decode, which fails on the first frame that couldn't be decoded.
+pub fn without_lost_frames(salvaged: (SpriteInfo, Vec<SpriteError>)) -> Result<SpriteInfo, SpriteError> {
+	let (sprite_info, lost_frames) = salvaged;
+	match lost_frames.into_iter().next() {
+		Some(why) => Err(why),
+		None => Ok(sprite_info)
+	}
+}
+
+/// Decodes frames in parallel from their headers, replacing the ones that
+/// can't be decoded with placeholders like `placeholder_frame`. The frames are
+/// counted against the pixel budget in file order before any are decoded, so
+/// the frames and errors come out the same as decoding them one at a time.
+/// `default_size` is the placeholder size for frames whose header is broken.
+pub fn decode_frames<H, S, F>(image_headers: &[Result<H, SpriteError>], frame_size: S, default_size: Option<(u32, u32)>, decode_frame: F) -> (Vec<Frame>, Vec<SpriteError>)
+where
+	H: Sync,
+	S: Fn(&H) -> (u32, u32),
+	F: Fn(&H, usize) -> Result<Frame, SpriteError> + Sync
+{
+	let mut budget = PixelBudget::default();
+	let budgeted_headers: Vec<Result<&H, SpriteError>> = image_headers.iter().enumerate().map(|(i, image_header)| {
+		let image_header = image_header.as_ref().map_err(|why| why.clone())?;
+		let (width, height) = frame_size(image_header);
+		budget.allocate(width.into(), height.into(), i)?;
+		Ok(image_header)
+	}).collect();
+	let decoded_frames: Vec<Result<Frame, SpriteError>> = budgeted_headers.par_iter().enumerate().map(|(i, image_header)| {
+		match image_header {
+			Ok(image_header) => decode_frame(image_header, i),
+			Err(why) => Err(why.clone())
+		}
+	}).collect();
+
+	let mut frames: Vec<Frame> = Vec::with_capacity(decoded_frames.len());
+	let mut lost_frames: Vec<SpriteError> = Vec::new();
+	for (i, (frame, image_header)) in decoded_frames.into_iter().zip(image_headers).enumerate() {
+		match frame {
+			Ok(frame) => frames.push(frame),
+			Err(why) => {
+				let size = image_header.as_ref().ok().map(&frame_size).or(default_size);
+				frames.push(placeholder_frame(size, &mut budget, i));
+				lost_frames.push(why);
+			}
+		}
+	}
+	(frames, lost_frames)
+}
+
+pub fn file_header_error(offset: usize) -> SpriteError {
+	SpriteError::new(SpriteErrorKind::TruncatedFileHeader).at_offset(offset)
+}
+
+pub fn image_header_error(frame: usize, offset: usize) -> SpriteError {
+	SpriteError::new(SpriteErrorKind::TruncatedImageHeader).at_frame(frame).at_offset(offset)
+}
+
+pub fn image_error(frame: usize, offset: usize) -> SpriteError {
+	SpriteError::new(SpriteErrorKind::TruncatedImageData).at_frame(frame).at_offset(offset)
+}
+
+/// The position in the file of the next byte `buffer` will read, for buffers
+/// that were copied from the start of the file.
+pub fn buffer_offset(file_size: usize, buffer: &[u8]) -> usize {
+	file_size - buffer.remaining()
+}
+
+/// A buffer that reads the file from `offset` onwards. It borrows the file
+/// instead of copying it, so it's cheap to make one per frame or line.
+pub fn buffer_at(contents: &[u8], offset: u32, frame: usize) -> Result<&[u8], SpriteError> {
+	if offset as usize > contents.len() {
+		return Err(SpriteError::new(SpriteErrorKind::BadLineOffset(offset)).at_frame(frame));
+	}
+	Ok(&contents[offset as usize..])
+}
+
+/// The native pixels a frame was decoded from, if they're in `pixel_format` and
+/// the frame's image hasn't been changed since. Encoders write these as they
+/// are instead of re-encoding the image, so untouched frames save losslessly.
+pub fn unedited_native_pixels(frame: &Frame, pixel_format: PixelFormat) -> Option<&[u16]> {
+	let native_pixels = frame.native_pixels.as_ref()?;
+	if native_pixels.pixel_format != pixel_format {
+		return None;
+	}
+	if native_pixels.pixels.len() as u64 != frame.image.width() as u64 * frame.image.height() as u64 {
+		return None;
+	}
+	let unedited = frame.image.pixels().zip(&native_pixels.pixels).all(|(pixel, native_pixel)| {
+		let color = parse_pixel(*native_pixel, pixel_format);
+		match pixel[3] {
+			0 => pixel[0] == 0 && pixel[1] == 0 && pixel[2] == 0 && color[3] == 0,
+			255 => pixel[0] == color[0] && pixel[1] == color[1] && pixel[2] == color[2],
+			_ => false
+		}
+	});
+	if unedited {
+		Some(&native_pixels.pixels)
+	} else {
+		None
+	}
+}
+
+/// How the 5- and 6-bit color channels of 16-bit pixels are widened to 8 bits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ChannelExpansion {
+	/// Repeats each channel's high bits in its low bits, so the full range of
+	/// a channel maps onto 0-255 and white stays white.
+	#[default]
+	BitReplication,
+	/// Shifts each channel into the high bits the way older versions of
+	/// Spritist did, leaving whatever bits of the neighboring channel follow it
+	/// in the low bits. Use this to compare exports with older ones byte for byte.
+	Legacy
+}
+
+pub fn parse_pixel(pixel: u16, pixel_format: PixelFormat) -> Rgba<u8> {
+	parse_pixel_with_expansion(pixel, pixel_format, ChannelExpansion::BitReplication)
+}
+
+pub fn parse_pixel_with_expansion(pixel: u16, pixel_format: PixelFormat, expansion: ChannelExpansion) -> Rgba<u8> {
+	match (pixel_format, expansion) {
+		(PixelFormat::Format555, ChannelExpansion::BitReplication) => parse_pixel_555(pixel),
+		(PixelFormat::Format565, ChannelExpansion::BitReplication) => parse_pixel_565(pixel),
+		(PixelFormat::Format555, ChannelExpansion::Legacy) => parse_pixel_555_legacy(pixel),
+		(PixelFormat::Format565, ChannelExpansion::Legacy) => parse_pixel_565_legacy(pixel)
+	}
+}
+
+fn expand_5_bits(channel: u16) -> u8 {
+	let channel = (channel & 0x1f) as u8;
+	(channel << 3) | (channel >> 2)
+}
+
+fn expand_6_bits(channel: u16) -> u8 {
+	let channel = (channel & 0x3f) as u8;
+	(channel << 2) | (channel >> 4)
+}
+
+fn with_alpha(r: u8, g: u8, b: u8) -> Rgba<u8> {
+	let a = if r == 0 && g == 0 && b == 0 { 0 } else { 255 };
+	Rgba([r, g, b, a])
+}
+
+fn parse_pixel_555(pixel: u16) -> Rgba<u8> {
+	with_alpha(expand_5_bits(pixel >> 10), expand_5_bits(pixel >> 5), expand_5_bits(pixel))
+}
+
+fn parse_pixel_565(pixel: u16) -> Rgba<u8> {
+	with_alpha(expand_5_bits(pixel >> 11), expand_6_bits(pixel >> 5), expand_5_bits(pixel))
+}
+
+fn parse_pixel_555_legacy(pixel: u16) -> Rgba<u8> {
+	with_alpha((pixel >> 7) as u8, (pixel >> 2) as u8, (pixel << 3) as u8)
+}
+
+fn parse_pixel_565_legacy(pixel: u16) -> Rgba<u8> {
+	with_alpha((pixel >> 8) as u8, (pixel >> 3) as u8, (pixel << 3) as u8)
+}
+
+/// For the big-endian Mac formats (M16, N16), whose pixels have 5 bits per
+/// channel in the top 15 bits (RRRRRGGGGGBBBBB0).
+pub fn parse_pixel_565_be(pixel: u16) -> Rgba<u8> {
+	with_alpha(expand_5_bits(pixel >> 11), expand_5_bits(pixel >> 6), expand_5_bits(pixel >> 1))
+}
+
+/// The inverse of `parse_pixel_565_be`.
+pub fn encode_pixel_565_be(pixel: &Rgba<u8>) -> u16 {
+	let r = ((pixel[0] as u16) & 0xf8) << 8;
+	let g = ((pixel[1] as u16) & 0xf8) << 3;
+	let b = ((pixel[2] as u16) & 0xf8) >> 2;
+	r | g | b
+}
+
+pub fn encode_pixel(pixel: &Rgba<u8>, pixel_format: PixelFormat) -> u16 {
+	match pixel_format {
+		PixelFormat::Format555 => encode_pixel_555(pixel),
+		PixelFormat::Format565 => encode_pixel_565(pixel)
+	}
+}
+
+fn encode_pixel_555(pixel: &Rgba<u8>) -> u16 {
+	let r = ((pixel[0] as u16) & 0xf8) << 7;
+	let g = ((pixel[1] as u16) & 0xf8) << 2;
+	let b = ((pixel[2] as u16) & 0xf8) >> 3;
+	r | g | b
+}
+
+fn encode_pixel_565(pixel: &Rgba<u8>) -> u16 {
+	let r = ((pixel[0] as u16) & 0xf8) << 8;
+	let g = ((pixel[1] as u16) & 0xfc) << 3;
+	let b = ((pixel[2] as u16) & 0xf8) >> 3;
+	r | g | b
+}
+
+pub fn black_to_transparent(img: RgbaImage) -> RgbaImage {
+	let mut img2 = RgbaImage::new(img.width(), img.height());
+	let _ = img2.copy_from(&img, 0, 0);
+	for pixel in img2.pixels_mut() {
+		if pixel[0] == 0 && pixel[1] == 0 && pixel[2] == 0 {
+			pixel[3] = 0;
+		}
+	}
+	img2
+}
+
+/// A sprite made of frames from an image format, which can only be exported.
+pub fn image_sprite_info(images: Vec<RgbaImage>) -> SpriteInfo {
+	SpriteInfo {
+		frames: images.into_iter().map(|image| Frame { image, color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None }).collect(),
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: true,
+		spr_variant: None,
+		dta_variant: None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sprite::NativePixels;
+
+	#[test]
+	fn channels_expand_to_the_full_range() {
+		assert_eq!(parse_pixel(0xffff, PixelFormat::Format565), Rgba([255, 255, 255, 255]));
+		assert_eq!(parse_pixel(0x7fff, PixelFormat::Format555), Rgba([255, 255, 255, 255]));
+		assert_eq!(parse_pixel(0x0821, PixelFormat::Format565), Rgba([8, 4, 8, 255]));
+		assert_eq!(parse_pixel(0x0000, PixelFormat::Format565), Rgba([0, 0, 0, 0]));
+		assert_eq!(parse_pixel_565_be(0xfffe), Rgba([255, 255, 255, 255]));
+		// the legacy expansion leaves the top bits of the next channel behind
+		assert_eq!(parse_pixel_with_expansion(0xffff, PixelFormat::Format565, ChannelExpansion::Legacy), Rgba([255, 255, 248, 255]));
+		assert_eq!(parse_pixel_with_expansion(0xf800, PixelFormat::Format565, ChannelExpansion::Legacy), Rgba([248, 0, 0, 255]));
+	}
+
+	#[test]
+	fn expanded_pixels_encode_to_the_same_value() {
+		for pixel in 1..=u16::MAX {
+			assert_eq!(encode_pixel(&parse_pixel(pixel, PixelFormat::Format565), PixelFormat::Format565), pixel);
+			assert_eq!(encode_pixel(&parse_pixel(pixel & 0x7fff, PixelFormat::Format555), PixelFormat::Format555), pixel & 0x7fff);
+			assert_eq!(encode_pixel_565_be(&parse_pixel_565_be(pixel)), pixel & 0xfffe);
+		}
+	}
+
+	#[test]
+	fn unedited_frames_expand_the_legacy_way_on_request() {
+		let pixels = vec![0xffff, 0x0000];
+		let image = RgbaImage::from_fn(2, 1, |x, _| parse_pixel(pixels[x as usize], PixelFormat::Format565));
+		let mut frame = Frame {
+			image,
+			color_indexes: Vec::new(),
+			metadata: FrameMetadata::default(),
+			native_pixels: Some(NativePixels { pixel_format: PixelFormat::Format565, pixels })
+		};
+		assert_eq!(*frame.expanded_image(ChannelExpansion::BitReplication).get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+		assert_eq!(*frame.expanded_image(ChannelExpansion::Legacy).get_pixel(0, 0), Rgba([255, 255, 248, 255]));
+		assert_eq!(*frame.expanded_image(ChannelExpansion::Legacy).get_pixel(1, 0), Rgba([0, 0, 0, 0]));
+		// edited frames don't have the pixels the legacy colors came from
+		frame.image.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+		assert_eq!(*frame.expanded_image(ChannelExpansion::Legacy).get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+	}
+}
@@ -0,0 +1,336 @@
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::{
+	SpriteFormat,
+	PixelFormat,
+	file_header_error,
+	image_header_error,
+	image_error,
+	buffer_offset,
+	buffer_at,
+	check_remaining,
+	decode_frames,
+	without_lost_frames,
+	unedited_native_pixels,
+	parse_pixel,
+	encode_pixel
+};
+use crate::{
+	inspect::{ FileReport, Anomaly, Coverage, inspect_uncompressed_frame, read_image_headers },
+	validate::ValidationIssue,
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_blk,
+	error::{ SpriteError, SpriteErrorKind },
+	sprite::{ Frame, FrameMetadata, NativePixels, SpriteInfo },
+	palette::Palette
+};
+
+struct FileHeader {
+	flags: u32,
+	cols: u16,
+	rows: u16,
+	image_count: u16
+}
+
+struct ImageHeader {
+	first_line_offset: u32,
+	width: u16,
+	height: u16
+}
+
+fn read_file_header(buffer: &mut &[u8]) -> Result<FileHeader, SpriteError> {
+	if buffer.remaining() < 10 { return Err(file_header_error(0)); }
+	Ok(FileHeader {
+		flags: buffer.get_u32_le(),
+		cols: buffer.get_u16_le(),
+		rows: buffer.get_u16_le(),
+		image_count: buffer.get_u16_le() // this should equal cols * rows
+	})
+}
+
+fn read_image_header(buffer: &mut &[u8], file_size: usize, frame: usize) -> Result<ImageHeader, SpriteError> {
+	let offset = buffer_offset(file_size, buffer);
+	if buffer.remaining() < 8 { return Err(image_header_error(frame, offset)); }
+	// offsets are stored relative to the end of the flags, 4 bytes in
+	let stored_offset = buffer.get_u32_le();
+	let first_line_offset = match stored_offset.checked_add(4) {
+		Some(first_line_offset) => first_line_offset,
+		None => return Err(SpriteError::new(SpriteErrorKind::BadLineOffset(stored_offset)).at_frame(frame).at_offset(offset))
+	};
+	let width = buffer.get_u16_le();
+	let height = buffer.get_u16_le();
+	if width != 128 || height != 128 {
+		return Err(blk_size_error(width.into(), height.into()).at_frame(frame).at_offset(offset));
+	}
+	Ok(ImageHeader {
+		width,
+		height,
+		first_line_offset
+	})
+}
+
+fn blk_size_error(width: u32, height: u32) -> SpriteError {
+	SpriteError::new(SpriteErrorKind::DimensionMismatch {
+		width,
+		height,
+		expected: "all frames in a BLK file must be 128 x 128 px".to_string()
+	})
+}
+
+fn read_image_data(contents: &[u8], header: &ImageHeader, pixel_format: PixelFormat, frame: usize) -> Result<Frame, SpriteError> {
+	let mut buffer = buffer_at(contents, header.first_line_offset, frame)?;
+	check_remaining(buffer, header.width as u64 * header.height as u64 * 2, contents.len(), frame)?;
+	let mut image = RgbaImage::new(header.width as u32, header.height as u32);
+	let mut pixels: Vec<u16> = Vec::with_capacity(header.width as usize * header.height as usize);
+	for y in 0..image.height() {
+		for x in 0..image.width() {
+			if buffer.remaining() < 2 { return Err(image_error(frame, buffer_offset(contents.len(), buffer))); }
+			let pixel_data = buffer.get_u16_le();
+			pixels.push(pixel_data);
+			let color = parse_pixel(pixel_data, pixel_format);
+			image.put_pixel(x, y, color);
+		}
+	}
+	Ok(Frame{
+		image,
+		color_indexes: Vec::new(),
+		metadata: FrameMetadata::default(),
+		native_pixels: Some(NativePixels{ pixel_format, pixels })
+	})
+}
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, SpriteError> {
+	without_lost_frames(salvage(contents)?)
+}
+
+/// Decodes every frame it can, replacing the ones that can't be decoded with
+/// transparent placeholders. Returns the errors for the frames that were lost.
+pub fn salvage(contents: &[u8]) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer)?;
+	let pixel_format = if file_header.flags & 0x00000001 == 1 {
+		PixelFormat::Format565
+	} else {
+		PixelFormat::Format555
+	};
+	let mut image_headers: Vec<Result<ImageHeader, SpriteError>> = Vec::new();
+	for i in 0..file_header.image_count {
+		image_headers.push(read_image_header(&mut buffer, contents.len(), i as usize));
+	}
+	// every BLK frame is a 128 x 128 tile, even if its header is broken
+	let (frames, lost_frames) = decode_frames(
+		&image_headers,
+		|image_header| (image_header.width.into(), image_header.height.into()),
+		Some((128, 128)),
+		|image_header, i| read_image_data(contents, image_header, pixel_format, i)
+	);
+	Ok((SpriteInfo{
+		frames,
+		pixel_format,
+		cols: file_header.cols,
+		rows: file_header.rows,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	}, lost_frames))
+}
+
+fn write_file_header(buffer: &mut BytesMut, pixel_format: PixelFormat, cols: u16, rows: u16, image_count: u16) {
+	buffer.put_u32_le(match pixel_format {
+		PixelFormat::Format555 => 0,
+		PixelFormat::Format565 => 1
+	});
+	buffer.put_u16_le(cols);
+	buffer.put_u16_le(rows);
+	buffer.put_u16_le(image_count);
+}
+
+fn write_image_header(buffer: &mut BytesMut, first_line_offset: u32) {
+	buffer.put_u32_le(first_line_offset - 4);
+	buffer.put_u16_le(128);
+	buffer.put_u16_le(128);
+}
+
+fn write_image_data(frame: &Frame, pixel_format: PixelFormat) -> BytesMut {
+	let mut buffer = BytesMut::new();
+	if let Some(native_pixels) = unedited_native_pixels(frame, pixel_format) {
+		for native_pixel in native_pixels {
+			buffer.put_u16_le(*native_pixel);
+		}
+		return buffer;
+	}
+	let image = &frame.image;
+	for y in 0..128 {
+		for x in 0..128 {
+			let pixel = image.get_pixel(x, y);
+			buffer.put_u16_le(encode_pixel(pixel, pixel_format));
+		}
+	}
+	buffer
+}
+
+pub fn encode(sprite_info: SpriteInfo) -> Result<Bytes, SpriteError> {
+	let tile_count = sprite_info.cols as usize * sprite_info.rows as usize;
+	if tile_count > u16::MAX as usize {
+		return Err(format!("Too many tiles for a BLK file ({} x {}). A BLK file can store at most {}.", sprite_info.cols, sprite_info.rows, u16::MAX).into());
+	}
+	if sprite_info.frames.len() != tile_count {
+		return Err("Incorrect number of frames for a BLK file. Must equal COLUMNS x ROWS (see View > View As Background).".into());
+	}
+
+	// write file header to buffer
+	let mut buffer = BytesMut::new();
+	write_file_header(&mut buffer, sprite_info.pixel_format, sprite_info.cols, sprite_info.rows, tile_count as u16);
+
+	// calculate initial offset of image data (= file header + image headers)
+	let size_of_headers = 10 + (8 * sprite_info.frames.len());
+	let size_of_image = 128 * 128 * 2;
+
+	for (i, frame) in sprite_info.frames.iter().enumerate() {
+		if frame.image.width() != 128 || frame.image.height() != 128 {
+			return Err(blk_size_error(frame.image.width(), frame.image.height()).at_frame(i));
+		}
+		let first_line_offset = size_of_headers + (size_of_image * i);
+		write_image_header(&mut buffer, first_line_offset as u32);
+	}
+
+	// get image data
+	let image_data: Vec<BytesMut> = sprite_info.frames.par_iter()
+		.map(|frame| write_image_data(frame, sprite_info.pixel_format))
+		.collect();
+	let mut images_buffer = BytesMut::with_capacity(size_of_image * image_data.len());
+	for image_buffer in image_data {
+		images_buffer.unsplit(image_buffer);
+	}
+
+	// write image data to buffer
+	buffer.unsplit(images_buffer);
+
+	Ok(buffer.freeze())
+}
+
+pub fn validate(sprite_info: &SpriteInfo) -> Vec<ValidationIssue> {
+	let mut issues = Vec::new();
+	let frame_count = sprite_info.frames.len();
+	let tile_count = sprite_info.cols as usize * sprite_info.rows as usize;
+	if tile_count > u16::MAX as usize {
+		issues.push(ValidationIssue::TooManyFrames { frame_count: tile_count, limit: u16::MAX as usize });
+	} else if frame_count != tile_count {
+		issues.push(ValidationIssue::WrongTileCount { frame_count, cols: sprite_info.cols, rows: sprite_info.rows });
+	}
+	for (i, frame) in sprite_info.frames.iter().enumerate() {
+		let (width, height) = frame.image.dimensions();
+		if width != 128 || height != 128 {
+			issues.push(ValidationIssue::WrongTileSize { frame: i, width, height });
+		}
+	}
+	issues
+}
+
+/// Reports the layout of a file. See `inspect::inspect_file`.
+pub fn inspect(contents: &[u8]) -> Result<FileReport, SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer)?;
+	let mut anomalies: Vec<Anomaly> = Vec::new();
+	let image_headers = read_image_headers(file_header.image_count.into(), |i| read_image_header(&mut buffer, contents.len(), i), &mut anomalies);
+	let header_size = buffer_offset(contents.len(), buffer);
+	let mut coverage = Coverage::default();
+	coverage.add(0, header_size, None);
+	let mut frames = Vec::new();
+	for (i, image_header) in image_headers.iter().enumerate() {
+		let (frame, error) = inspect_uncompressed_frame(contents.len(), image_header.first_line_offset, image_header.width, image_header.height, i, &mut coverage);
+		frames.push(frame);
+		anomalies.extend(error.map(Anomaly::BadFrame));
+	}
+	if file_header.image_count as u32 != file_header.cols as u32 * file_header.rows as u32 {
+		anomalies.push(Anomaly::TileCountMismatch { image_count: file_header.image_count, cols: file_header.cols, rows: file_header.rows });
+	}
+	anomalies.extend(coverage.anomalies(contents.len()));
+	Ok(FileReport {
+		format: SpriteFormat::Blk,
+		file_size: contents.len(),
+		flags: file_header.flags,
+		pixel_format: if file_header.flags & 0x00000001 == 1 { PixelFormat::Format565 } else { PixelFormat::Format555 },
+		background_size: Some((file_header.cols, file_header.rows)),
+		header_size,
+		frames,
+		anomalies
+	})
+}
+
+pub struct BlkCodec;
+
+impl SpriteCodec for BlkCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::Blk
+	}
+
+	fn name(&self) -> &'static str {
+		"BLK"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["blk"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { sixteen_bit: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_blk(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents)?)
+	}
+
+	fn salvage(&self, contents: &[u8], _palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		Some(salvage(contents))
+	}
+
+	fn inspect(&self, contents: &[u8]) -> Option<Result<FileReport, SpriteError>> {
+		Some(inspect(contents))
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, _palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sprite_info(frames: Vec<Frame>, cols: u16, rows: u16) -> SpriteInfo {
+		SpriteInfo {
+			frames,
+			pixel_format: PixelFormat::Format565,
+			cols,
+			rows,
+			read_only: false,
+			spr_variant: None,
+			dta_variant: None
+		}
+	}
+
+	#[test]
+	fn too_many_tiles_is_an_error() {
+		// 256 x 256 tiles would wrap around to 0 in the header
+		assert!(encode(sprite_info(Vec::new(), 256, 256)).is_err());
+	}
+
+	#[test]
+	fn too_many_tiles_fails_validation() {
+		let issues = validate(&sprite_info(Vec::new(), 256, 256));
+		assert_eq!(issues, vec![ValidationIssue::TooManyFrames { frame_count: 65536, limit: 65535 }]);
+	}
+}
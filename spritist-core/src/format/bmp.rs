@@ -0,0 +1,64 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::io::Cursor;
+
+use image::{ ImageFormat, ImageReader, RgbaImage };
+use bmp::Image as BmpImage;
+use bmp::Pixel as BmpPixel;
+
+use super::{ SpriteFormat, black_to_transparent, image_sprite_info };
+use crate::{
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_bmp,
+	sprite::SpriteInfo,
+	palette::Palette
+};
+
+/// Decodes a BMP image. Black pixels are treated as transparent.
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, Box<dyn Error>> {
+	let image = black_to_transparent(ImageReader::with_format(Cursor::new(contents), ImageFormat::Bmp).decode()?.to_rgba8());
+	Ok(image_sprite_info(vec![image]))
+}
+
+pub fn encode(img: &RgbaImage, file_path: PathBuf) -> Result<(), Box<dyn Error>> {
+	let mut bmp_image = BmpImage::new(img.width(), img.height());
+	for (x, y) in bmp_image.coordinates() {
+		let rgba_pixel = img.get_pixel(x, y);
+		let bmp_pixel = if rgba_pixel[3] == 0 {
+			BmpPixel { r: 0, g: 0, b: 0 }
+		} else {
+			BmpPixel { r: rgba_pixel[0], g: rgba_pixel[1], b: rgba_pixel[2] }
+		};
+		bmp_image.set_pixel(x, y, bmp_pixel);
+	}
+	bmp_image.save(&file_path)?;
+	Ok(())
+}
+
+pub struct BmpCodec;
+
+impl SpriteCodec for BmpCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::Bmp
+	}
+
+	fn name(&self) -> &'static str {
+		"BMP"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["bmp"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { read_only: true, image: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_bmp(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		decode(contents)
+	}
+}
@@ -0,0 +1,591 @@
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::{ RgbaImage, Rgba };
+use rayon::prelude::*;
+
+use super::{
+	SpriteFormat,
+	PixelFormat,
+	file_header_error,
+	image_header_error,
+	image_error,
+	buffer_offset,
+	buffer_at,
+	decode_frames,
+	without_lost_frames,
+	unedited_native_pixels,
+	parse_pixel,
+	parse_pixel_565_be,
+	encode_pixel,
+	encode_pixel_565_be
+};
+use crate::{
+	inspect::{ FileReport, FrameReport, RunStats, Anomaly, Coverage, read_image_headers },
+	validate::{ ValidationIssue, check_for_errors, check_frame_count, check_frame_sizes, check_black_pixels },
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_c16,
+	error::{ SpriteError, SpriteErrorKind },
+	sprite::{ Frame, FrameMetadata, NativePixels, SpriteInfo },
+	palette::Palette
+};
+
+/// C16 files and their Mac sibling N16 share the same run-length compressed
+/// layout. N16 files store every word big-endian, with M16-style pixels.
+#[derive(Copy, Clone, PartialEq)]
+pub(super) enum RunFormat {
+	C16,
+	N16
+}
+
+impl RunFormat {
+	fn name(self) -> &'static str {
+		match self {
+			RunFormat::C16 => "C16",
+			RunFormat::N16 => "N16"
+		}
+	}
+
+	fn get_u16(self, buffer: &mut &[u8]) -> u16 {
+		match self {
+			RunFormat::C16 => buffer.get_u16_le(),
+			RunFormat::N16 => buffer.get_u16()
+		}
+	}
+
+	fn get_u32(self, buffer: &mut &[u8]) -> u32 {
+		match self {
+			RunFormat::C16 => buffer.get_u32_le(),
+			RunFormat::N16 => buffer.get_u32()
+		}
+	}
+
+	fn put_u16(self, buffer: &mut BytesMut, value: u16) {
+		match self {
+			RunFormat::C16 => buffer.put_u16_le(value),
+			RunFormat::N16 => buffer.put_u16(value)
+		}
+	}
+
+	fn put_u32(self, buffer: &mut BytesMut, value: u32) {
+		match self {
+			RunFormat::C16 => buffer.put_u32_le(value),
+			RunFormat::N16 => buffer.put_u32(value)
+		}
+	}
+}
+
+struct FileHeader {
+	flags: u32,
+	image_count: u16
+}
+
+struct ImageHeader {
+	width: u16,
+	height: u16,
+	line_offsets: Vec<u32>
+}
+
+fn read_file_header(buffer: &mut &[u8], run_format: RunFormat) -> Result<FileHeader, SpriteError> {
+	if buffer.remaining() < 6 { return Err(file_header_error(0)); }
+	Ok(FileHeader {
+		flags: run_format.get_u32(buffer),
+		image_count: run_format.get_u16(buffer)
+	})
+}
+
+fn read_image_header(buffer: &mut &[u8], run_format: RunFormat, file_size: usize, frame: usize) -> Result<ImageHeader, SpriteError> {
+	if buffer.remaining() < 8 { return Err(image_header_error(frame, buffer_offset(file_size, buffer))); }
+	let first_line_offset = run_format.get_u32(buffer);
+	let width = run_format.get_u16(buffer);
+	let height = run_format.get_u16(buffer);
+	let mut line_offsets = Vec::new();
+	if height > 0 {
+		line_offsets.push(first_line_offset);
+	}
+	for _ in 1..height {
+		if buffer.remaining() < 4 { return Err(image_header_error(frame, buffer_offset(file_size, buffer))); }
+		line_offsets.push(run_format.get_u32(buffer));
+	}
+	Ok(ImageHeader {
+		width,
+		height,
+		line_offsets
+	})
+}
+
+fn read_image_data(contents: &[u8], header: &ImageHeader, run_format: RunFormat, pixel_format: PixelFormat, frame: usize) -> Result<Frame, SpriteError> {
+	let mut image = RgbaImage::new(header.width as u32, header.height as u32);
+	// transparent runs are stored as 0
+	let mut pixels: Vec<u16> = vec![0; header.width as usize * header.height as usize];
+	for (y, line_offset) in header.line_offsets.iter().enumerate() {
+		let mut buffer = buffer_at(contents, *line_offset, frame)?;
+		let mut x: u16 = 0;
+		while x < header.width {
+			let run_offset = buffer_offset(contents.len(), buffer);
+			if buffer.remaining() < 2 { return Err(image_error(frame, run_offset)); }
+			let run_header = run_format.get_u16(&mut buffer);
+			let run_type = run_header & 0x1; // 0 = transparent, 1 = color
+			let run_length = (run_header & 0xfffe) >> 1;
+			if run_length == 0 {
+				// end of line marker, so the rest of the line is transparent
+				break;
+			}
+			if run_length > header.width - x {
+				return Err(SpriteError::new(SpriteErrorKind::BadRunLength(run_length)).at_frame(frame).at_offset(run_offset));
+			}
+			if run_type == 1 {
+				for i in 0..run_length {
+					if buffer.remaining() < 2 { return Err(image_error(frame, buffer_offset(contents.len(), buffer))); }
+					let pixel_data = run_format.get_u16(&mut buffer);
+					pixels[y * header.width as usize + (x + i) as usize] = pixel_data;
+					// runs decide what's transparent, so black in a color run is solid
+					let mut color = match run_format {
+						RunFormat::C16 => parse_pixel(pixel_data, pixel_format),
+						RunFormat::N16 => parse_pixel_565_be(pixel_data)
+					};
+					color[3] = 255;
+					image.put_pixel((x + i) as u32, y as u32, color);
+				}
+			} else {
+				for i in 0..run_length {
+					image.put_pixel((x + i) as u32, y as u32, Rgba([0, 0, 0, 0]));
+				}
+			}
+			x += run_length;
+		}
+	}
+	// N16 pixels aren't in either of the usual pixel formats
+	let native_pixels = match run_format {
+		RunFormat::C16 => Some(NativePixels{ pixel_format, pixels }),
+		RunFormat::N16 => None
+	};
+	Ok(Frame{
+		image,
+		color_indexes: Vec::new(),
+		metadata: FrameMetadata::default(),
+		native_pixels
+	})
+}
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, SpriteError> {
+	without_lost_frames(salvage(contents)?)
+}
+
+/// Decodes every frame it can, replacing the ones that can't be decoded with
+/// transparent placeholders. Returns the errors for the frames that were lost.
+pub fn salvage(contents: &[u8]) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	salvage_runs(contents, RunFormat::C16)
+}
+
+pub(super) fn salvage_runs(contents: &[u8], run_format: RunFormat) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer, run_format)?;
+	let pixel_format = match run_format {
+		RunFormat::C16 => {
+			if file_header.flags & 0x00000002 == 0 {
+				return Err(SpriteError::new(SpriteErrorKind::UnsupportedFlags(file_header.flags)).at_offset(0));
+			}
+			if file_header.flags & 0x00000001 == 1 {
+				PixelFormat::Format565
+			} else {
+				PixelFormat::Format555
+			}
+		}
+		// like M16, N16 only has one pixel layout, so the flags aren't checked
+		RunFormat::N16 => PixelFormat::Format565
+	};
+	let mut image_headers: Vec<Result<ImageHeader, SpriteError>> = Vec::new();
+	for i in 0..file_header.image_count {
+		image_headers.push(read_image_header(&mut buffer, run_format, contents.len(), i as usize));
+	}
+	let (frames, lost_frames) = decode_frames(
+		&image_headers,
+		|image_header| (image_header.width.into(), image_header.height.into()),
+		None,
+		|image_header, i| read_image_data(contents, image_header, run_format, pixel_format, i)
+	);
+	Ok((SpriteInfo{
+		frames,
+		pixel_format,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	}, lost_frames))
+}
+
+/// Reports the layout of a file. See `inspect::inspect_file`.
+pub fn inspect(contents: &[u8]) -> Result<FileReport, SpriteError> {
+	inspect_runs(contents, RunFormat::C16)
+}
+
+pub(super) fn inspect_runs(contents: &[u8], run_format: RunFormat) -> Result<FileReport, SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer, run_format)?;
+	let mut anomalies: Vec<Anomaly> = Vec::new();
+	let image_headers = read_image_headers(file_header.image_count.into(), |i| read_image_header(&mut buffer, run_format, contents.len(), i), &mut anomalies);
+	let header_size = buffer_offset(contents.len(), buffer);
+	let mut coverage = Coverage::default();
+	coverage.add(0, header_size, None);
+	let mut frames = Vec::new();
+	for (i, image_header) in image_headers.iter().enumerate() {
+		let (frame, error) = inspect_image_data(contents, image_header, run_format, i, &mut coverage);
+		frames.push(frame);
+		anomalies.extend(error.map(Anomaly::BadFrame));
+	}
+	anomalies.extend(coverage.anomalies(contents.len()));
+	Ok(FileReport {
+		format: match run_format {
+			RunFormat::C16 => SpriteFormat::C16,
+			RunFormat::N16 => SpriteFormat::N16
+		},
+		file_size: contents.len(),
+		flags: file_header.flags,
+		pixel_format: match run_format {
+			RunFormat::C16 if file_header.flags & 0x00000001 == 0 => PixelFormat::Format555,
+			_ => PixelFormat::Format565
+		},
+		background_size: None,
+		header_size,
+		frames,
+		anomalies
+	})
+}
+
+// walks the runs the same way `read_image_data` does, without decoding them
+fn inspect_image_data(contents: &[u8], header: &ImageHeader, run_format: RunFormat, frame: usize, coverage: &mut Coverage) -> (FrameReport, Option<SpriteError>) {
+	let mut runs = RunStats::default();
+	let mut data_size = 0;
+	let mut error: Option<SpriteError> = None;
+	let mut end_of_image = None;
+	for line_offset in &header.line_offsets {
+		let mut buffer = match buffer_at(contents, *line_offset, frame) {
+			Ok(buffer) => buffer,
+			Err(why) => {
+				error = Some(why);
+				break;
+			}
+		};
+		let mut x: u16 = 0;
+		while x < header.width {
+			let run_offset = buffer_offset(contents.len(), buffer);
+			if buffer.remaining() < 2 {
+				error = Some(image_error(frame, run_offset));
+				buffer = &[];
+				break;
+			}
+			let run_header = run_format.get_u16(&mut buffer);
+			let run_length = (run_header & 0xfffe) >> 1;
+			if run_length == 0 {
+				break;
+			}
+			if run_length > header.width - x {
+				error = Some(SpriteError::new(SpriteErrorKind::BadRunLength(run_length)).at_frame(frame).at_offset(run_offset));
+				break;
+			}
+			if run_header & 0x1 == 1 {
+				if buffer.remaining() < run_length as usize * 2 {
+					error = Some(image_error(frame, contents.len()));
+					buffer = &[];
+					break;
+				}
+				buffer.advance(run_length as usize * 2);
+				runs.color_runs += 1;
+				runs.color_pixels += run_length as u64;
+			} else {
+				runs.transparent_runs += 1;
+				runs.transparent_pixels += run_length as u64;
+			}
+			x += run_length;
+		}
+		// lines that fill the whole width still end with a marker
+		let mut next_run = buffer;
+		if error.is_none() && x == header.width && next_run.remaining() >= 2 && run_format.get_u16(&mut next_run) == 0 {
+			buffer = next_run;
+		}
+		let line_end = buffer_offset(contents.len(), buffer);
+		coverage.add(*line_offset as usize, line_end, Some(frame));
+		data_size += line_end - *line_offset as usize;
+		end_of_image = Some(line_end);
+		if error.is_some() {
+			break;
+		}
+	}
+	// the last line is followed by an end of image marker
+	if let (None, Some(end_of_image)) = (&error, end_of_image) {
+		let mut buffer = &contents[end_of_image..];
+		if buffer.remaining() >= 2 && run_format.get_u16(&mut buffer) == 0 {
+			coverage.add(end_of_image, end_of_image + 2, Some(frame));
+			data_size += 2;
+		}
+	}
+	(FrameReport {
+		offset: header.line_offsets.first().copied().unwrap_or(0),
+		width: header.width,
+		height: header.height,
+		line_offsets: header.line_offsets.clone(),
+		data_size,
+		raw_size: header.width as usize * header.height as usize * 2,
+		runs: Some(runs)
+	}, error)
+}
+
+fn write_file_header(buffer: &mut BytesMut, run_format: RunFormat, pixel_format: PixelFormat, image_count: u16) {
+	run_format.put_u32(buffer, match (run_format, pixel_format) {
+		(RunFormat::C16, PixelFormat::Format555) => 2,
+		(RunFormat::C16, PixelFormat::Format565) | (RunFormat::N16, _) => 3
+	});
+	run_format.put_u16(buffer, image_count);
+}
+
+fn write_image_header(buffer: &mut BytesMut, run_format: RunFormat, width: u16, height: u16, line_offsets: Vec<u32>) {
+	run_format.put_u32(buffer, line_offsets.first().copied().unwrap_or(0));
+	run_format.put_u16(buffer, width);
+	run_format.put_u16(buffer, height);
+	for line_offset in line_offsets.iter().skip(1) {
+		run_format.put_u32(buffer, *line_offset);
+	}
+}
+
+// line offsets are from the start of the image data, since the image's place
+// in the file isn't known until the images before it have been written
+fn write_image_data(frame: &Frame, run_format: RunFormat, pixel_format: PixelFormat) -> Result<(BytesMut, Vec<u32>), SpriteError> {
+	let image = &frame.image;
+	let native_pixels = match run_format {
+		RunFormat::C16 => unedited_native_pixels(frame, pixel_format),
+		RunFormat::N16 => None
+	};
+	let mut buffer = BytesMut::new();
+	let mut line_offsets: Vec<u32> = Vec::new();
+
+	for y in 0..image.height() {
+		line_offsets.push(u32::try_from(buffer.len()).map_err(|_| too_big_error(run_format))?);
+		let mut transparent_run: usize = 0;
+		let mut color_run: Vec<u16> = Vec::new();
+		for x in 0..image.width() {
+			let pixel = image.get_pixel(x, y);
+			if pixel[3] == 0 {
+				// transparent pixel
+				if !color_run.is_empty() {
+					// end active color run
+					write_color_run(&mut buffer, run_format, &color_run)?;
+					color_run.clear();
+				}
+				transparent_run += 1;
+			} else {
+				// color pixel
+				if transparent_run > 0 {
+					// end active transparent run
+					write_transparent_run(&mut buffer, run_format, transparent_run)?;
+					transparent_run = 0;
+				}
+				color_run.push(match native_pixels {
+					Some(native_pixels) => native_pixels[(y * image.width() + x) as usize],
+					None => encode_color_pixel(pixel, run_format, pixel_format)
+				});
+			}
+		}
+		// wrap up active run
+		if !color_run.is_empty() {
+			write_color_run(&mut buffer, run_format, &color_run)?;
+		} else if transparent_run > 0 {
+			write_transparent_run(&mut buffer, run_format, transparent_run)?;
+		}
+		// end of line
+		run_format.put_u16(&mut buffer, 0);
+	}
+	// end of image
+	run_format.put_u16(&mut buffer, 0);
+
+	Ok((buffer, line_offsets))
+}
+
+fn encode_color_pixel(pixel: &Rgba<u8>, run_format: RunFormat, pixel_format: PixelFormat) -> u16 {
+	let encoded_pixel = match run_format {
+		RunFormat::C16 => encode_pixel(pixel, pixel_format),
+		RunFormat::N16 => encode_pixel_565_be(pixel)
+	};
+	if encoded_pixel == 0 {
+		// if solid black, save as dark grey
+		match (run_format, pixel_format) {
+			(RunFormat::C16, PixelFormat::Format555) => 0x0421,
+			(RunFormat::C16, PixelFormat::Format565) => 0x0821,
+			(RunFormat::N16, _) => 0x0842
+		}
+	} else {
+		encoded_pixel
+	}
+}
+
+// the length goes in the top 15 bits and the run type in the bottom one
+fn run_header(run_format: RunFormat, run_length: usize, run_type: u16) -> Result<u16, SpriteError> {
+	match u16::try_from(run_length) {
+		Ok(run_length) if run_length <= 0x7fff => Ok(run_length << 1 | run_type),
+		_ => Err(format!("Unable to save {}. A run of {} pixels is longer than the {} a run can hold.", run_format.name(), run_length, 0x7fff).into())
+	}
+}
+
+fn write_color_run(buffer: &mut BytesMut, run_format: RunFormat, color_run: &[u16]) -> Result<(), SpriteError> {
+	run_format.put_u16(buffer, run_header(run_format, color_run.len(), 1)?);
+	for pixel in color_run {
+		run_format.put_u16(buffer, *pixel);
+	}
+	Ok(())
+}
+
+fn write_transparent_run(buffer: &mut BytesMut, run_format: RunFormat, transparent_run: usize) -> Result<(), SpriteError> {
+	run_format.put_u16(buffer, run_header(run_format, transparent_run, 0)?);
+	Ok(())
+}
+
+// offsets are 32-bit, so nothing can start more than 4 GB into the file
+fn too_big_error(run_format: RunFormat) -> SpriteError {
+	format!("Unable to save {}. The file would be too big for its image offsets.", run_format.name()).into()
+}
+
+pub fn encode(sprite_info: SpriteInfo) -> Result<Bytes, SpriteError> {
+	encode_runs(sprite_info, RunFormat::C16)
+}
+
+pub fn validate(sprite_info: &SpriteInfo) -> Vec<ValidationIssue> {
+	validate_runs(sprite_info, RunFormat::C16)
+}
+
+// the frame count and sizes the headers can hold
+fn check_limits(frames: &[Frame], issues: &mut Vec<ValidationIssue>) {
+	check_frame_count(frames, u16::MAX as usize, issues);
+	// run headers only have 15 bits for the length, and a line is at least one run
+	check_frame_sizes(frames, 0x7fff, u16::MAX as u32, issues);
+}
+
+pub(super) fn validate_runs(sprite_info: &SpriteInfo, run_format: RunFormat) -> Vec<ValidationIssue> {
+	let mut issues = Vec::new();
+	check_limits(&sprite_info.frames, &mut issues);
+	check_black_pixels(&sprite_info.frames, match run_format {
+		RunFormat::C16 => sprite_info.pixel_format,
+		RunFormat::N16 => PixelFormat::Format565
+	}, &mut issues);
+	issues
+}
+
+pub(super) fn encode_runs(sprite_info: SpriteInfo, run_format: RunFormat) -> Result<Bytes, SpriteError> {
+	let mut issues = Vec::new();
+	check_limits(&sprite_info.frames, &mut issues);
+	check_for_errors(&issues, run_format.name())?;
+
+	// write file header to buffer
+	let mut buffer = BytesMut::new();
+	write_file_header(&mut buffer, run_format, sprite_info.pixel_format, sprite_info.frames.len() as u16);
+
+	// calculate initial offset of image data (= file header + image headers)
+	let mut image_offset = buffer.len() as u64;
+	for frame in &sprite_info.frames {
+		let image_header_size = 8 + (frame.image.height().saturating_sub(1) as u64 * 4);
+		image_offset += image_header_size;
+	}
+
+	// get image data
+	let image_data: Vec<(BytesMut, Vec<u32>)> = sprite_info.frames.par_iter()
+		.enumerate()
+		.map(|(i, frame)| write_image_data(frame, run_format, sprite_info.pixel_format).map_err(|why| why.at_frame(i)))
+		.collect::<Result<_, _>>()?;
+	let mut image_headers_buffer = BytesMut::new();
+	let mut images_buffer = BytesMut::new();
+	for (i, (frame, (image_buffer, line_offsets))) in sprite_info.frames.iter().zip(image_data).enumerate() {
+		let line_offsets = line_offsets.iter()
+			.map(|line_offset| u32::try_from(image_offset + *line_offset as u64).map_err(|_| too_big_error(run_format).at_frame(i)))
+			.collect::<Result<_, _>>()?;
+		write_image_header(&mut image_headers_buffer, run_format, frame.image.width() as u16, frame.image.height() as u16, line_offsets);
+		image_offset += image_buffer.len() as u64;
+		images_buffer.unsplit(image_buffer);
+	}
+
+	// write image data to buffer
+	buffer.unsplit(image_headers_buffer);
+	buffer.unsplit(images_buffer);
+
+	Ok(buffer.freeze())
+}
+
+pub struct C16Codec;
+
+impl SpriteCodec for C16Codec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::C16
+	}
+
+	fn name(&self) -> &'static str {
+		"C16"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["c16"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { sixteen_bit: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_c16(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents)?)
+	}
+
+	fn salvage(&self, contents: &[u8], _palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		Some(salvage(contents))
+	}
+
+	fn inspect(&self, contents: &[u8]) -> Option<Result<FileReport, SpriteError>> {
+		Some(inspect(contents))
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, _palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::format::n16;
+
+	fn sprite_info(image: RgbaImage) -> SpriteInfo {
+		SpriteInfo {
+			frames: vec![Frame { image, color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None }],
+			pixel_format: PixelFormat::Format565,
+			cols: 0,
+			rows: 0,
+			read_only: false,
+			spr_variant: None,
+			dta_variant: None
+		}
+	}
+
+	#[test]
+	fn runs_longer_than_a_run_header_can_hold_are_an_error() {
+		for color in [Rgba([0, 0, 0, 0]), Rgba([255, 255, 255, 255])] {
+			let contents = encode(sprite_info(RgbaImage::from_pixel(0x7fff, 1, color))).unwrap();
+			assert_eq!(decode(&contents).unwrap().frames[0].image.width(), 0x7fff);
+		}
+		assert_eq!(run_header(RunFormat::C16, 0x7fff, 1), Ok(0xffff));
+		assert!(run_header(RunFormat::C16, 0x8000, 0).is_err());
+	}
+
+	#[test]
+	fn frames_too_large_for_the_headers_are_an_error() {
+		for (width, height) in [(0x8000, 1), (1, 65536)] {
+			let why = encode(sprite_info(RgbaImage::new(width, height))).unwrap_err();
+			assert_eq!(why.to_string(), format!("Unable to save C16. Frame 0 is {} x {} px, but the format can only store frames up to 32767 x 65535 px.", width, height));
+		}
+		assert!(n16::encode(sprite_info(RgbaImage::new(1, 65536))).is_err());
+	}
+}
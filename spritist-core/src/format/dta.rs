@@ -0,0 +1,327 @@
+// https://github.com/openc2e/openc2e/blob/main/src/fileformats/charsetdta.cpp
+
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::{ Rgba, RgbaImage };
+
+use super::{ SpriteFormat, PixelFormat, image_error, buffer_offset };
+use crate::{
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_dta,
+	error::{ SpriteError, SpriteErrorKind },
+	sprite::{ Frame, FrameMetadata, SpriteInfo },
+	palette::Palette
+};
+
+/// The charset layouts, named by character size. Each has its own file size.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DtaVariant {
+	/// 128 characters, with no width table.
+	Font4x8,
+	/// 128 characters.
+	Font6x12,
+	/// 128 characters.
+	Font11x12,
+	/// 256 characters.
+	Font6x12Extended
+}
+
+impl DtaVariant {
+	pub fn from_file_size(size: usize) -> Option<DtaVariant> {
+		match size {
+			4096 => Some(DtaVariant::Font4x8),
+			9472 => Some(DtaVariant::Font6x12),
+			17152 => Some(DtaVariant::Font11x12),
+			18944 => Some(DtaVariant::Font6x12Extended),
+			_ => None
+		}
+	}
+
+	pub fn char_width(&self) -> usize {
+		match self {
+			DtaVariant::Font4x8 => 4,
+			DtaVariant::Font11x12 => 11,
+			DtaVariant::Font6x12 | DtaVariant::Font6x12Extended => 6
+		}
+	}
+
+	pub fn char_height(&self) -> usize {
+		match self {
+			DtaVariant::Font4x8 => 8,
+			_ => 12
+		}
+	}
+
+	pub fn char_count(&self) -> usize {
+		match self {
+			DtaVariant::Font6x12Extended => 256,
+			_ => 128
+		}
+	}
+
+	fn has_width_table(&self) -> bool {
+		*self != DtaVariant::Font4x8
+	}
+}
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, SpriteError> {
+	let size = contents.len();
+	let dta_variant = match DtaVariant::from_file_size(size) {
+		Some(dta_variant) => dta_variant,
+		None => return Err(SpriteError::new(SpriteErrorKind::InvalidFileSize(size)))
+	};
+
+	let char_width = dta_variant.char_width();
+	let char_height = dta_variant.char_height();
+	let char_length = char_width * char_height;
+	let char_count = dta_variant.char_count();
+
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut buffer = contents;
+
+	let mut char_data_list = Vec::new();
+	for i in 0..char_count {
+		let mut char_data = Vec::new();
+		for _ in 0..char_length {
+			if buffer.remaining() < 1 { return Err(image_error(i, buffer_offset(size, buffer))); }
+			char_data.push(buffer.get_u8());
+		}
+		char_data_list.push(char_data);
+	}
+
+	for (i, char_data) in char_data_list.iter().enumerate() {
+		let frame_width = if dta_variant.has_width_table() {
+			if buffer.remaining() < 2 { return Err(image_error(i, buffer_offset(size, buffer))); }
+			buffer.get_u16_le() as usize
+		} else {
+			char_width
+		};
+		if frame_width > char_width {
+			return Err(SpriteError::new(SpriteErrorKind::DimensionMismatch {
+				width: frame_width as u32,
+				height: char_height as u32,
+				expected: format!("characters in this charset can be at most {} px wide", char_width)
+			}).at_frame(i).at_offset(buffer_offset(size, buffer) - 2));
+		}
+
+		let mut image = RgbaImage::new(frame_width as u32, char_height as u32);
+
+		for y in 0..char_height {
+			for x in 0..frame_width {
+				let color_index = char_data[x + (y * char_width)];
+				let color = match color_index {
+					0 => Rgba([0, 0, 0, 0]),
+					1 => Rgba([255, 255, 255, 255]),
+					2 => Rgba([128, 128, 128, 255]),
+					_ => Rgba([0, 0, 128, 255])
+				};
+				image.put_pixel(x as u32, y as u32, color);
+			}
+		}
+
+		frames.push(Frame{ image, color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None });
+	}
+
+	Ok(SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: Some(dta_variant)
+	})
+}
+
+const CHARSET_COLORS: [(u8, u8, u8); 3] = [
+	(255, 255, 255),
+	(128, 128, 128),
+	(0, 0, 128)
+];
+
+fn encode_pixel(pixel: &Rgba<u8>) -> u8 {
+	if pixel[3] < 128 {
+		return 0;
+	}
+	let mut closest_index = 0;
+	let mut closest_distance = u32::MAX;
+	for (i, (r, g, b)) in CHARSET_COLORS.iter().enumerate() {
+		let dr = pixel[0].abs_diff(*r) as u32;
+		let dg = pixel[1].abs_diff(*g) as u32;
+		let db = pixel[2].abs_diff(*b) as u32;
+		let distance = (dr * dr) + (dg * dg) + (db * db);
+		if distance < closest_distance {
+			closest_index = i;
+			closest_distance = distance;
+		}
+	}
+	closest_index as u8 + 1
+}
+
+fn dimension_error(frame: &Frame, expected: String) -> SpriteError {
+	SpriteError::new(SpriteErrorKind::DimensionMismatch {
+		width: frame.image.width(),
+		height: frame.image.height(),
+		expected
+	})
+}
+
+// the layout for charsets that didn't come from a DTA file, picked from the
+// glyph dimensions. Only fonts with glyphs wider than 6 px use 11x12
+fn guess_variant(frames: &[Frame]) -> Option<DtaVariant> {
+	let max_width = frames.iter().map(|frame| frame.image.width()).max().unwrap_or(0);
+	let char_height = frames.first().map(|frame| frame.image.height()).unwrap_or(0);
+	match (frames.len(), char_height) {
+		(128, 8) => Some(DtaVariant::Font4x8),
+		(128, 12) if max_width <= 6 => Some(DtaVariant::Font6x12),
+		(128, 12) => Some(DtaVariant::Font11x12),
+		(256, 12) => Some(DtaVariant::Font6x12Extended),
+		_ => None
+	}
+}
+
+/// Encodes the charset in the layout it was read as, or one that fits its
+/// glyphs if it didn't come from a DTA file.
+pub fn encode(sprite_info: SpriteInfo) -> Result<Bytes, SpriteError> {
+	let frames = &sprite_info.frames;
+	let dta_variant = match sprite_info.dta_variant.or_else(|| guess_variant(frames)) {
+		Some(dta_variant) => dta_variant,
+		None => return Err("Invalid charset. Must have 128 characters that are 8 px tall, or 128 or 256 characters that are 12 px tall.".into())
+	};
+	if frames.len() != dta_variant.char_count() {
+		return Err(format!("Invalid charset. Must have {} characters.", dta_variant.char_count()).into());
+	}
+	let char_width = dta_variant.char_width();
+	let char_height = dta_variant.char_height();
+	let has_width_table = dta_variant.has_width_table();
+
+	for (i, frame) in frames.iter().enumerate() {
+		if frame.image.height() as usize != char_height {
+			return Err(dimension_error(frame, format!("all characters in this charset must be {} px tall", char_height)).at_frame(i));
+		}
+		if !has_width_table && frame.image.width() as usize != char_width {
+			return Err(dimension_error(frame, format!("all characters in a {} px tall charset must be {} px wide", char_height, char_width)).at_frame(i));
+		}
+		if frame.image.width() as usize > char_width {
+			return Err(dimension_error(frame, format!("characters in this charset can be at most {} px wide", char_width)).at_frame(i));
+		}
+	}
+
+	let mut buffer = BytesMut::new();
+
+	// character data is always padded to the full character width
+	for frame in frames.iter() {
+		for y in 0..char_height {
+			for x in 0..char_width {
+				if x < frame.image.width() as usize {
+					buffer.put_u8(encode_pixel(frame.image.get_pixel(x as u32, y as u32)));
+				} else {
+					buffer.put_u8(0);
+				}
+			}
+		}
+	}
+
+	if has_width_table {
+		for frame in frames.iter() {
+			buffer.put_u16_le(frame.image.width() as u16);
+		}
+	}
+
+	Ok(buffer.freeze())
+}
+
+pub struct DtaCodec;
+
+impl SpriteCodec for DtaCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::Dta
+	}
+
+	fn name(&self) -> &'static str {
+		"Charset DTA"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["dta"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities::default()
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_dta(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents)?)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// a charset whose glyphs are at most 6 px wide, so a 6x12 layout would also fit
+	// the glyphs of the 11x12 one
+	fn charset(dta_variant: DtaVariant) -> Vec<u8> {
+		let char_width = dta_variant.char_width();
+		let glyph_width = |i: usize| if dta_variant.has_width_table() { i % 7 } else { char_width };
+		let mut contents = Vec::new();
+		for i in 0..dta_variant.char_count() {
+			for y in 0..dta_variant.char_height() {
+				for x in 0..char_width {
+					contents.push(if x < glyph_width(i) { ((i + x + y) % 4) as u8 } else { 0 });
+				}
+			}
+		}
+		if dta_variant.has_width_table() {
+			for i in 0..dta_variant.char_count() {
+				contents.extend_from_slice(&(glyph_width(i) as u16).to_le_bytes());
+			}
+		}
+		contents
+	}
+
+	#[test]
+	fn charsets_save_in_the_layout_they_were_read_as() {
+		for dta_variant in [DtaVariant::Font4x8, DtaVariant::Font6x12, DtaVariant::Font11x12, DtaVariant::Font6x12Extended] {
+			let contents = charset(dta_variant);
+			assert_eq!(DtaVariant::from_file_size(contents.len()), Some(dta_variant));
+			let sprite_info = decode(&contents).unwrap();
+			assert_eq!(sprite_info.dta_variant, Some(dta_variant));
+			assert_eq!(sprite_info.frames.len(), dta_variant.char_count());
+			assert_eq!(sprite_info.frames[5].image.height() as usize, dta_variant.char_height());
+			assert_eq!(encode(sprite_info).unwrap().as_ref(), contents.as_slice(), "{:?}", dta_variant);
+		}
+	}
+
+	#[test]
+	fn glyph_colors_map_onto_the_charset_palette() {
+		let mut sprite_info = decode(&charset(DtaVariant::Font4x8)).unwrap();
+		let glyph = &mut sprite_info.frames[0].image;
+		for (x, color) in [[0, 0, 0, 0], [250, 240, 255, 255], [140, 120, 130, 255], [10, 0, 150, 255]].into_iter().enumerate() {
+			glyph.put_pixel(x as u32, 0, Rgba(color));
+		}
+		// semi-transparent pixels count as transparent
+		glyph.put_pixel(0, 1, Rgba([255, 255, 255, 100]));
+		let contents = encode(sprite_info).unwrap();
+		assert_eq!(contents[..5], [0, 1, 2, 3, 0]);
+	}
+
+	#[test]
+	fn charsets_too_wide_for_their_layout_are_refused() {
+		let mut sprite_info = decode(&charset(DtaVariant::Font6x12)).unwrap();
+		sprite_info.frames[0].image = RgbaImage::new(8, 12);
+		assert!(encode(sprite_info.clone()).is_err());
+		// charsets from other formats get a layout that fits
+		sprite_info.dta_variant = None;
+		assert_eq!(encode(sprite_info).unwrap().len(), 17152);
+	}
+}
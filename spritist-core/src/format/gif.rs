@@ -0,0 +1,51 @@
+use std::{
+	error::Error,
+	io::Cursor
+};
+
+use image::{ AnimationDecoder, RgbaImage, codecs::gif::GifDecoder };
+
+use super::{ SpriteFormat, image_sprite_info };
+use crate::{
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_gif,
+	sprite::SpriteInfo,
+	palette::Palette
+};
+
+/// Decodes every frame of a GIF animation.
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, Box<dyn Error>> {
+	let mut images: Vec<RgbaImage> = Vec::new();
+	for gif_frame in GifDecoder::new(Cursor::new(contents))?.into_frames() {
+		images.push(gif_frame?.into_buffer());
+	}
+	Ok(image_sprite_info(images))
+}
+
+pub struct GifCodec;
+
+impl SpriteCodec for GifCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::Gif
+	}
+
+	fn name(&self) -> &'static str {
+		"GIF"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["gif"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { read_only: true, image: false, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_gif(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		decode(contents)
+	}
+}
@@ -0,0 +1,218 @@
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::RgbaImage;
+
+use super::{ SpriteFormat, PixelFormat, file_header_error, image_header_error, image_error, buffer_offset, buffer_at, check_remaining, decode_frames, without_lost_frames, parse_pixel_565_be, encode_pixel_565_be };
+use crate::{
+	inspect::{ FileReport, Anomaly, Coverage, inspect_uncompressed_frame, read_image_headers },
+	validate::{ ValidationIssue, check_frame_count, check_frame_sizes },
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_m16,
+	error::SpriteError,
+	sprite::{ Frame, FrameMetadata, SpriteInfo },
+	palette::Palette
+};
+
+struct FileHeader {
+	image_count: u16
+}
+
+struct ImageHeader {
+	offset: u32,
+	width: u16,
+	height: u16
+}
+
+fn read_file_header(buffer: &mut &[u8]) -> Result<FileHeader, SpriteError> {
+	if buffer.remaining() < 6 { return Err(file_header_error(0)); }
+	let _pixel_format = buffer.get_u32(); // not used because it should always be 1
+	Ok(FileHeader {
+		image_count: buffer.get_u16()
+	})
+}
+
+fn read_image_header(buffer: &mut &[u8], file_size: usize, frame: usize) -> Result<ImageHeader, SpriteError> {
+	if buffer.remaining() < 8 { return Err(image_header_error(frame, buffer_offset(file_size, buffer))); }
+	let offset = buffer.get_u32();
+	let width = buffer.get_u16();
+	let height = buffer.get_u16();
+	Ok(ImageHeader {
+		offset,
+		width,
+		height
+	})
+}
+
+fn read_image_data(contents: &[u8], header: &ImageHeader, frame: usize) -> Result<RgbaImage, SpriteError> {
+	let mut buffer = buffer_at(contents, header.offset, frame)?;
+	check_remaining(buffer, header.width as u64 * header.height as u64 * 2, contents.len(), frame)?;
+	let mut image = RgbaImage::new(header.width.into(), header.height.into());
+	for y in 0..header.height {
+		for x in 0..header.width {
+			if buffer.remaining() < 2 { return Err(image_error(frame, buffer_offset(contents.len(), buffer))); }
+			let mut color = parse_pixel_565_be(buffer.get_u16());
+			if color[0] == 0 && color[1] == 0 && color[2] == 0 {
+				color[3] = 0;
+			}
+			image.put_pixel(x.into(), y.into(), color);
+		}
+	}
+	Ok(image)
+}
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, SpriteError> {
+	without_lost_frames(salvage(contents)?)
+}
+
+/// Decodes every frame it can, replacing the ones that can't be decoded with
+/// transparent placeholders. Returns the errors for the frames that were lost.
+pub fn salvage(contents: &[u8]) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer)?;
+	let mut image_headers: Vec<Result<ImageHeader, SpriteError>> = Vec::new();
+	for i in 0..file_header.image_count {
+		image_headers.push(read_image_header(&mut buffer, contents.len(), i as usize));
+	}
+	let (frames, lost_frames) = decode_frames(
+		&image_headers,
+		|image_header| (image_header.width.into(), image_header.height.into()),
+		None,
+		|image_header, i| read_image_data(contents, image_header, i).map(|image| {
+			Frame{ image, color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None }
+		})
+	);
+	Ok((SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	}, lost_frames))
+}
+
+fn write_file_header(image_count: u16) -> Bytes {
+	let mut buffer = BytesMut::new();
+	buffer.put_u32(1); // 565 format
+	buffer.put_u16(image_count);
+	buffer.freeze()
+}
+
+fn write_image_header(offset: u32, width: u16, height: u16) -> Bytes {
+	let mut buffer = BytesMut::new();
+	buffer.put_u32(offset);
+	buffer.put_u16(width);
+	buffer.put_u16(height);
+	buffer.freeze()
+}
+
+fn write_image_data(image: &RgbaImage) -> Bytes {
+	let mut buffer = BytesMut::new();
+	for y in 0..image.height() {
+		for x in 0..image.width() {
+			let pixel = image.get_pixel(x, y);
+			buffer.put_u16(encode_pixel_565_be(pixel));
+		}
+	}
+	buffer.freeze()
+}
+
+pub fn encode(sprite_info: SpriteInfo) -> Result<Bytes, SpriteError> {
+	let frame_count = sprite_info.frames.len();
+
+	let mut images_buffer = BytesMut::new();
+	let mut headers_buffer = BytesMut::new();
+	for frame in &sprite_info.frames {
+		let offset = 6 + (frame_count * 8) + images_buffer.len();
+		headers_buffer.extend_from_slice(&write_image_header(offset as u32, frame.image.width() as u16, frame.image.height() as u16));
+		images_buffer.extend_from_slice(&write_image_data(&frame.image));
+	}
+
+	let mut buffer = BytesMut::new();
+	buffer.extend_from_slice(&write_file_header(frame_count as u16));
+	buffer.extend_from_slice(&headers_buffer);
+	buffer.extend_from_slice(&images_buffer);
+
+	Ok(buffer.freeze())
+}
+
+pub fn validate(sprite_info: &SpriteInfo) -> Vec<ValidationIssue> {
+	let mut issues = Vec::new();
+	check_frame_count(&sprite_info.frames, u16::MAX as usize, &mut issues);
+	check_frame_sizes(&sprite_info.frames, u16::MAX as u32, u16::MAX as u32, &mut issues);
+	issues
+}
+
+/// Reports the layout of a file. See `inspect::inspect_file`.
+pub fn inspect(contents: &[u8]) -> Result<FileReport, SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer)?;
+	let mut anomalies: Vec<Anomaly> = Vec::new();
+	let image_headers = read_image_headers(file_header.image_count.into(), |i| read_image_header(&mut buffer, contents.len(), i), &mut anomalies);
+	let header_size = buffer_offset(contents.len(), buffer);
+	let mut coverage = Coverage::default();
+	coverage.add(0, header_size, None);
+	let mut frames = Vec::new();
+	for (i, image_header) in image_headers.iter().enumerate() {
+		let (frame, error) = inspect_uncompressed_frame(contents.len(), image_header.offset, image_header.width, image_header.height, i, &mut coverage);
+		frames.push(frame);
+		anomalies.extend(error.map(Anomaly::BadFrame));
+	}
+	anomalies.extend(coverage.anomalies(contents.len()));
+	Ok(FileReport {
+		format: SpriteFormat::M16,
+		file_size: contents.len(),
+		flags: (&contents[..4]).get_u32(),
+		pixel_format: PixelFormat::Format565,
+		background_size: None,
+		header_size,
+		frames,
+		anomalies
+	})
+}
+
+pub struct M16Codec;
+
+impl SpriteCodec for M16Codec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::M16
+	}
+
+	fn name(&self) -> &'static str {
+		"M16"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["m16"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { sixteen_bit: true, fixed_pixel_format: Some(PixelFormat::Format555), ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_m16(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents)?)
+	}
+
+	fn salvage(&self, contents: &[u8], _palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		Some(salvage(contents))
+	}
+
+	fn inspect(&self, contents: &[u8]) -> Option<Result<FileReport, SpriteError>> {
+		Some(inspect(contents))
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, _palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info)?)
+	}
+}
@@ -0,0 +1,121 @@
+use std::error::Error;
+
+use bytes::Bytes;
+
+use super::{
+	SpriteFormat,
+	PixelFormat,
+	without_lost_frames,
+	c16::{ RunFormat, salvage_runs, inspect_runs, validate_runs, encode_runs }
+};
+use crate::{
+	inspect::FileReport,
+	validate::ValidationIssue,
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_n16,
+	error::SpriteError,
+	sprite::SpriteInfo,
+	palette::Palette
+};
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, SpriteError> {
+	without_lost_frames(salvage(contents)?)
+}
+
+/// Decodes every frame it can, replacing the ones that can't be decoded with
+/// transparent placeholders. Returns the errors for the frames that were lost.
+pub fn salvage(contents: &[u8]) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	salvage_runs(contents, RunFormat::N16)
+}
+
+pub fn encode(sprite_info: SpriteInfo) -> Result<Bytes, SpriteError> {
+	encode_runs(sprite_info, RunFormat::N16)
+}
+
+pub fn validate(sprite_info: &SpriteInfo) -> Vec<ValidationIssue> {
+	validate_runs(sprite_info, RunFormat::N16)
+}
+
+/// Reports the layout of a file. See `inspect::inspect_file`.
+pub fn inspect(contents: &[u8]) -> Result<FileReport, SpriteError> {
+	inspect_runs(contents, RunFormat::N16)
+}
+
+pub struct N16Codec;
+
+impl SpriteCodec for N16Codec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::N16
+	}
+
+	fn name(&self) -> &'static str {
+		"N16"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["n16"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { sixteen_bit: true, fixed_pixel_format: Some(PixelFormat::Format555), ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_n16(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents)?)
+	}
+
+	fn salvage(&self, contents: &[u8], _palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		Some(salvage(contents))
+	}
+
+	fn inspect(&self, contents: &[u8]) -> Option<Result<FileReport, SpriteError>> {
+		Some(inspect(contents))
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, _palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::Rgba;
+
+	use super::*;
+
+	// one 3 x 1 frame: a transparent pixel, then a red and a blue one
+	const N16_FILE: [u8; 26] = [
+		0, 0, 0, 3, 0, 1,
+		0, 0, 0, 14, 0, 3, 0, 1,
+		0, 2, 0, 5, 0xf8, 0x00, 0x00, 0x3e, 0, 0,
+		0, 0
+	];
+
+	#[test]
+	fn words_are_big_endian() {
+		let sprite_info = decode(&N16_FILE).unwrap();
+		let image = &sprite_info.frames[0].image;
+		assert_eq!(image.dimensions(), (3, 1));
+		assert_eq!(*image.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+		assert_eq!(*image.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+		assert_eq!(*image.get_pixel(2, 0), Rgba([0, 0, 255, 255]));
+		assert_eq!(encode(sprite_info).unwrap().as_ref(), &N16_FILE[..]);
+	}
+
+	#[test]
+	fn black_is_saved_as_dark_grey() {
+		let mut sprite_info = decode(&N16_FILE).unwrap();
+		sprite_info.frames[0].image.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+		let contents = encode(sprite_info).unwrap();
+		assert_eq!(contents[18..20], [0x08, 0x42]);
+		assert_eq!(*decode(&contents).unwrap().frames[0].image.get_pixel(1, 0), Rgba([8, 8, 8, 255]));
+	}
+}
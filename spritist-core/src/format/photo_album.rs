@@ -0,0 +1,240 @@
+// https://sheeslostknowledge.blogspot.com/2014/12/extracting-information-from-creatures-1.html
+// https://github.com/LoneShee/SLKExamples/blob/master/C1_Photoalbum2HTML.py
+
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::RgbaImage;
+
+use super::{ SpriteFormat, PixelFormat, file_header_error, image_header_error, image_error, buffer_offset, check_remaining, PixelBudget };
+use crate::{
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_photo_album,
+	error::SpriteError,
+	sprite::{ Frame, FrameMetadata, SpriteInfo },
+	palette::Palette
+};
+
+fn read_c_string(buffer: &mut &[u8], last_byte: Option<u8>, file_size: usize, frame: usize) -> Result<String, SpriteError> {
+	let mut string_len;
+	match last_byte {
+		Some(byte) => string_len = byte as u32,
+		None => {
+			if buffer.remaining() < 1 { return Err(image_error(frame, buffer_offset(file_size, buffer))); }
+			string_len = buffer.get_u8() as u32;
+		}
+	}
+
+	if string_len == 255 {
+		if buffer.remaining() < 2 { return Err(image_error(frame, buffer_offset(file_size, buffer))); }
+		string_len = buffer.get_u16_le() as u32;
+	}
+
+	let mut string = "".to_string();
+	for _ in 0..string_len {
+		if buffer.remaining() < 1 { return Err(image_error(frame, buffer_offset(file_size, buffer))); }
+		let next_char = buffer.get_u8() as char;
+		string.push(next_char);
+	}
+
+	Ok(string)
+}
+
+pub fn decode(contents: &[u8], palette: &Palette) -> Result<SpriteInfo, SpriteError> {
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut buffer = contents;
+
+	// file header
+	if buffer.remaining() < 2 { return Err(file_header_error(0)); }
+	let image_count = buffer.get_u16_le();
+
+	// image headers
+	let mut budget = PixelBudget::default();
+	for frame in 0..image_count as usize {
+		let timestamp = read_c_string(&mut buffer, None, contents.len(), frame)?;
+
+		if buffer.remaining() < 12 { return Err(image_header_error(frame, buffer_offset(contents.len(), buffer))); }
+		let width = buffer.get_u32_le();
+		let height = buffer.get_u32_le();
+		let unknown = buffer.get_u32_le();
+		budget.allocate(width.into(), height.into(), frame)?;
+		check_remaining(buffer, width as u64 * height as u64, contents.len(), frame)?;
+
+		let mut image = RgbaImage::new(width, height);
+		let mut color_indexes: Vec<u8> = vec![0; (width * height) as usize];
+		let mut last_byte = 0_u8;
+
+		// rows are stored bottom-up
+		for i in 0..(width * height) {
+			if buffer.remaining() < 1 { return Err(image_error(frame, buffer_offset(contents.len(), buffer))); }
+			let color_index = buffer.get_u8();
+			if i == (width * height) - 1 { last_byte = color_index; }
+			let pixel = palette.get_color(color_index);
+			let x = i % width;
+			let y = (height - 1) - (i / width);
+			color_indexes[(x + (y * width)) as usize] = color_index;
+			image.put_pixel(x, y, pixel);
+		}
+
+		// the last pixel byte doubles as the length of the comment
+		let comment = read_c_string(&mut buffer, Some(last_byte), contents.len(), frame)?;
+
+		frames.push(Frame{ image, color_indexes, metadata: FrameMetadata { timestamp, comment, unknown }, native_pixels: None });
+	}
+
+	Ok(SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	})
+}
+
+fn encode_string(string: &str) -> Result<Vec<u8>, SpriteError> {
+	let mut bytes = Vec::new();
+	for c in string.chars() {
+		if c as u32 > 255 {
+			return Err(format!("Unable to save Photo Album. \"{}\" contains characters that can't be stored in a Photo Album.", string).into());
+		}
+		bytes.push(c as u8);
+	}
+	if bytes.len() > u16::MAX as usize {
+		return Err("Unable to save Photo Album. A timestamp or comment is too long.".into());
+	}
+	Ok(bytes)
+}
+
+fn write_string_length(buffer: &mut BytesMut, length: usize) {
+	if length < 255 {
+		buffer.put_u8(length as u8);
+	} else {
+		buffer.put_u8(255);
+		buffer.put_u16_le(length as u16);
+	}
+}
+
+pub fn encode(sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, SpriteError> {
+	if sprite_info.frames.len() > u16::MAX as usize {
+		return Err("Unable to save Photo Album. Too many photos.".into());
+	}
+
+	let mut buffer = BytesMut::new();
+	buffer.put_u16_le(sprite_info.frames.len() as u16);
+
+	for (i, frame) in sprite_info.frames.iter().enumerate() {
+		let width = frame.image.width();
+		let height = frame.image.height();
+		let pixel_count = (width * height) as usize;
+
+		let timestamp = encode_string(&frame.metadata.timestamp).map_err(|why| why.at_frame(i))?;
+		let comment = encode_string(&frame.metadata.comment).map_err(|why| why.at_frame(i))?;
+		if pixel_count == 0 && !comment.is_empty() {
+			return Err(SpriteError::from("Unable to save Photo Album. Empty photos can't have a comment.").at_frame(i));
+		}
+
+		write_string_length(&mut buffer, timestamp.len());
+		buffer.extend_from_slice(&timestamp);
+
+		buffer.put_u32_le(width);
+		buffer.put_u32_le(height);
+		buffer.put_u32_le(frame.metadata.unknown);
+
+		let mut color_indexes = frame.color_indexes.clone();
+		if color_indexes.len() != pixel_count {
+			color_indexes = palette.get_closest_color_indexes(&frame.image);
+		}
+
+		// rows are stored bottom-up, and the last pixel byte is replaced by
+		// the first byte of the comment length
+		for y in (0..height).rev() {
+			for x in 0..width {
+				if y == 0 && x == width - 1 { break; }
+				buffer.put_u8(color_indexes[(x + (y * width)) as usize]);
+			}
+		}
+		if pixel_count > 0 {
+			write_string_length(&mut buffer, comment.len());
+			buffer.extend_from_slice(&comment);
+		}
+	}
+
+	Ok(buffer.freeze())
+}
+
+pub struct PhotoAlbumCodec;
+
+impl SpriteCodec for PhotoAlbumCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::PhotoAlbum
+	}
+
+	fn name(&self) -> &'static str {
+		"Photo Album"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["photo album"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { indexed: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_photo_album(contents)
+	}
+
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents, palette)?)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info, palette)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::palette::original_palette;
+
+	// one 2 x 2 photo with a timestamp and a comment long enough to need the
+	// 255 escape before its length
+	fn photo_album(comment: &str) -> Vec<u8> {
+		let mut contents = vec![1, 0, 5];
+		contents.extend_from_slice(b"12:00");
+		contents.extend_from_slice(&[2, 0, 0, 0, 2, 0, 0, 0, 7, 0, 0, 0]);
+		// the bottom row, then the top row, whose last pixel is the comment length
+		contents.extend_from_slice(&[3, 4, 1, 255]);
+		contents.extend_from_slice(&(comment.len() as u16).to_le_bytes());
+		contents.extend_from_slice(comment.as_bytes());
+		contents
+	}
+
+	#[test]
+	fn photos_keep_their_timestamp_and_comment() {
+		let palette = original_palette();
+		let comment = "Norn ".repeat(60);
+		let contents = photo_album(&comment);
+		let sprite_info = decode(&contents, &palette).unwrap();
+		let frame = &sprite_info.frames[0];
+		assert_eq!(frame.metadata, FrameMetadata { timestamp: "12:00".to_string(), comment, unknown: 7 });
+		assert_eq!(frame.color_indexes, [1, 255, 3, 4]);
+		assert_eq!(*frame.image.get_pixel(0, 1), palette.get_color(3));
+		assert_eq!(encode(sprite_info, &palette).unwrap().as_ref(), contents.as_slice());
+	}
+
+	#[test]
+	fn edited_comments_are_saved() {
+		let palette = original_palette();
+		let mut sprite_info = decode(&photo_album(&"Norn ".repeat(60)), &palette).unwrap();
+		sprite_info.frames[0].metadata.comment = "Alice".to_string();
+		let saved = encode(sprite_info, &palette).unwrap();
+		// short comments have a one byte length, which is the last pixel
+		assert_eq!(saved[20..], [3, 4, 1, 5, b'A', b'l', b'i', b'c', b'e']);
+		assert_eq!(decode(&saved, &palette).unwrap().frames[0].metadata.comment, "Alice");
+	}
+}
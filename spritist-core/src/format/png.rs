@@ -0,0 +1,62 @@
+use std::{
+	fs::File,
+	io::BufWriter,
+	error::Error,
+	path::PathBuf,
+	io::Cursor
+};
+use image::{ ImageFormat, ImageReader, RgbaImage };
+use png::{ Encoder, ColorType, BitDepth, Compression };
+
+use super::{ SpriteFormat, image_sprite_info };
+use crate::{
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_png,
+	sprite::SpriteInfo,
+	palette::Palette
+};
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, Box<dyn Error>> {
+	let image = ImageReader::with_format(Cursor::new(contents), ImageFormat::Png).decode()?.to_rgba8();
+	Ok(image_sprite_info(vec![image]))
+}
+
+pub fn encode(img: &RgbaImage, file_path: PathBuf) -> Result<(), Box<dyn Error>> {
+	let file = File::create(file_path)?;
+	let file_buffer = &mut BufWriter::new(file);
+	let mut encoder = Encoder::new(file_buffer, img.width(), img.height());
+	encoder.set_color(ColorType::Rgba);
+	encoder.set_depth(BitDepth::Eight);
+	encoder.set_compression(Compression::Best);
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(img.as_raw())?;
+	Ok(())
+}
+
+pub struct PngCodec;
+
+impl SpriteCodec for PngCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::Png
+	}
+
+	fn name(&self) -> &'static str {
+		"PNG"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["png"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { read_only: true, image: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_png(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		decode(contents)
+	}
+}
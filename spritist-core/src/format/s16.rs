@@ -0,0 +1,265 @@
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::{
+	SpriteFormat,
+	PixelFormat,
+	file_header_error,
+	image_header_error,
+	image_error,
+	buffer_offset,
+	buffer_at,
+	check_remaining,
+	decode_frames,
+	without_lost_frames,
+	unedited_native_pixels,
+	parse_pixel,
+	encode_pixel
+};
+use crate::{
+	inspect::{ FileReport, Anomaly, Coverage, inspect_uncompressed_frame, read_image_headers },
+	validate::{ ValidationIssue, check_frame_count, check_frame_sizes, check_black_pixels },
+	codec::{ SpriteCodec, Capabilities },
+	detect::score_s16,
+	error::SpriteError,
+	sprite::{ Frame, FrameMetadata, NativePixels, SpriteInfo },
+	palette::Palette
+};
+
+struct FileHeader {
+	flags: u32,
+	image_count: u16
+}
+
+struct ImageHeader {
+	offset: u32,
+	width: u16,
+	height: u16
+}
+
+fn read_file_header(buffer: &mut &[u8]) -> Result<FileHeader, SpriteError> {
+	if buffer.remaining() < 6 { return Err(file_header_error(0)); }
+	Ok(FileHeader {
+		flags: buffer.get_u32_le(),
+		image_count: buffer.get_u16_le()
+	})
+}
+
+fn read_image_header(buffer: &mut &[u8], file_size: usize, frame: usize) -> Result<ImageHeader, SpriteError> {
+	if buffer.remaining() < 8 { return Err(image_header_error(frame, buffer_offset(file_size, buffer))); }
+	let offset = buffer.get_u32_le();
+	let width = buffer.get_u16_le();
+	let height = buffer.get_u16_le();
+	Ok(ImageHeader {
+		offset,
+		width,
+		height
+	})
+}
+
+fn read_image_data(contents: &[u8], header: &ImageHeader, pixel_format: PixelFormat, frame: usize) -> Result<Frame, SpriteError> {
+	let mut buffer = buffer_at(contents, header.offset, frame)?;
+	check_remaining(buffer, header.width as u64 * header.height as u64 * 2, contents.len(), frame)?;
+	let mut image = RgbaImage::new(header.width.into(), header.height.into());
+	let mut pixels: Vec<u16> = Vec::with_capacity(header.width as usize * header.height as usize);
+	for y in 0..header.height {
+		for x in 0..header.width {
+			if buffer.remaining() < 2 { return Err(image_error(frame, buffer_offset(contents.len(), buffer))); }
+			let pixel_data = buffer.get_u16_le();
+			pixels.push(pixel_data);
+			let color = parse_pixel(pixel_data, pixel_format);
+			image.put_pixel(x.into(), y.into(), color);
+		}
+	}
+	Ok(Frame{
+		image,
+		color_indexes: Vec::new(),
+		metadata: FrameMetadata::default(),
+		native_pixels: Some(NativePixels{ pixel_format, pixels })
+	})
+}
+
+pub fn decode(contents: &[u8]) -> Result<SpriteInfo, SpriteError> {
+	without_lost_frames(salvage(contents)?)
+}
+
+/// Decodes every frame it can, replacing the ones that can't be decoded with
+/// transparent placeholders. Returns the errors for the frames that were lost.
+pub fn salvage(contents: &[u8]) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer)?;
+	let pixel_format = if file_header.flags & 0x00000001 == 1 {
+		PixelFormat::Format565
+	} else {
+		PixelFormat::Format555
+	};
+	let mut image_headers: Vec<Result<ImageHeader, SpriteError>> = Vec::new();
+	for i in 0..file_header.image_count {
+		image_headers.push(read_image_header(&mut buffer, contents.len(), i as usize));
+	}
+	let (frames, lost_frames) = decode_frames(
+		&image_headers,
+		|image_header| (image_header.width.into(), image_header.height.into()),
+		None,
+		|image_header, i| read_image_data(contents, image_header, pixel_format, i)
+	);
+	Ok((SpriteInfo{
+		frames,
+		pixel_format,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: None,
+		dta_variant: None
+	}, lost_frames))
+}
+
+fn write_file_header(pixel_format: PixelFormat, image_count: u16) -> Bytes {
+	let mut buffer = BytesMut::new();
+	buffer.put_u32_le(match pixel_format {
+		PixelFormat::Format555 => 0,
+		PixelFormat::Format565 => 1
+	});
+	buffer.put_u16_le(image_count);
+	buffer.freeze()
+}
+
+fn write_image_header(offset: u32, width: u16, height: u16) -> Bytes {
+	let mut buffer = BytesMut::new();
+	buffer.put_u32_le(offset);
+	buffer.put_u16_le(width);
+	buffer.put_u16_le(height);
+	buffer.freeze()
+}
+
+fn write_image_data(frame: &Frame, pixel_format: PixelFormat) -> Bytes {
+	let mut buffer = BytesMut::new();
+	if let Some(native_pixels) = unedited_native_pixels(frame, pixel_format) {
+		for native_pixel in native_pixels {
+			buffer.put_u16_le(*native_pixel);
+		}
+		return buffer.freeze();
+	}
+	for pixel in frame.image.pixels() {
+		if pixel[3] == 0 {
+			buffer.put_u16_le(0);
+		} else {
+			let encoded_pixel = encode_pixel(pixel, pixel_format);
+			if encoded_pixel == 0 {
+				// if solid black, save as dark grey
+				buffer.put_u16_le(match pixel_format {
+					PixelFormat::Format555 => 0x0421,
+					PixelFormat::Format565 => 0x0821
+				});
+			} else {
+				buffer.put_u16_le(encoded_pixel);
+			}
+		}
+	}
+	buffer.freeze()
+}
+
+pub fn encode(sprite_info: SpriteInfo) -> Result<Bytes, SpriteError> {
+	let frame_count = sprite_info.frames.len();
+
+	let image_data: Vec<Bytes> = sprite_info.frames.par_iter()
+		.map(|frame| write_image_data(frame, sprite_info.pixel_format))
+		.collect();
+	let mut images_buffer = BytesMut::new();
+	let mut headers_buffer = BytesMut::new();
+	for (frame, image_buffer) in sprite_info.frames.iter().zip(image_data) {
+		let offset = 6 + (frame_count * 8) + images_buffer.len();
+		headers_buffer.extend_from_slice(&write_image_header(offset as u32, frame.image.width() as u16, frame.image.height() as u16));
+		images_buffer.extend_from_slice(&image_buffer);
+	}
+
+	let mut buffer = BytesMut::new();
+	buffer.extend_from_slice(&write_file_header(sprite_info.pixel_format, frame_count as u16));
+	buffer.extend_from_slice(&headers_buffer);
+	buffer.extend_from_slice(&images_buffer);
+
+	Ok(buffer.freeze())
+}
+
+pub fn validate(sprite_info: &SpriteInfo) -> Vec<ValidationIssue> {
+	let mut issues = Vec::new();
+	check_frame_count(&sprite_info.frames, u16::MAX as usize, &mut issues);
+	check_frame_sizes(&sprite_info.frames, u16::MAX as u32, u16::MAX as u32, &mut issues);
+	check_black_pixels(&sprite_info.frames, sprite_info.pixel_format, &mut issues);
+	issues
+}
+
+/// Reports the layout of a file. See `inspect::inspect_file`.
+pub fn inspect(contents: &[u8]) -> Result<FileReport, SpriteError> {
+	let mut buffer = contents;
+	let file_header = read_file_header(&mut buffer)?;
+	let mut anomalies: Vec<Anomaly> = Vec::new();
+	let image_headers = read_image_headers(file_header.image_count.into(), |i| read_image_header(&mut buffer, contents.len(), i), &mut anomalies);
+	let header_size = buffer_offset(contents.len(), buffer);
+	let mut coverage = Coverage::default();
+	coverage.add(0, header_size, None);
+	let mut frames = Vec::new();
+	for (i, image_header) in image_headers.iter().enumerate() {
+		let (frame, error) = inspect_uncompressed_frame(contents.len(), image_header.offset, image_header.width, image_header.height, i, &mut coverage);
+		frames.push(frame);
+		anomalies.extend(error.map(Anomaly::BadFrame));
+	}
+	anomalies.extend(coverage.anomalies(contents.len()));
+	Ok(FileReport {
+		format: SpriteFormat::S16,
+		file_size: contents.len(),
+		flags: file_header.flags,
+		pixel_format: if file_header.flags & 0x00000001 == 1 { PixelFormat::Format565 } else { PixelFormat::Format555 },
+		background_size: None,
+		header_size,
+		frames,
+		anomalies
+	})
+}
+
+pub struct S16Codec;
+
+impl SpriteCodec for S16Codec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::S16
+	}
+
+	fn name(&self) -> &'static str {
+		"S16"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["s16"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { sixteen_bit: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_s16(contents)
+	}
+
+	fn decode(&self, contents: &[u8], _palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents)?)
+	}
+
+	fn salvage(&self, contents: &[u8], _palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		Some(salvage(contents))
+	}
+
+	fn inspect(&self, contents: &[u8]) -> Option<Result<FileReport, SpriteError>> {
+		Some(inspect(contents))
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, _palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, _palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode(sprite_info)?)
+	}
+}
@@ -0,0 +1,888 @@
+use std::error::Error;
+
+use bytes::{ Bytes, BytesMut, Buf, BufMut };
+use image::RgbaImage;
+
+use super::{
+	SpriteFormat,
+	PixelFormat,
+	file_header_error,
+	image_header_error,
+	image_error,
+	buffer_offset,
+	check_remaining,
+	PixelBudget,
+	placeholder_frame,
+	without_lost_frames
+};
+use crate::{
+	codec::{ SpriteCodec, Capabilities },
+	detect::{ score_spr, score_spr_single_width, score_spr_double_width, score_spr_multi_sprite, score_spr_prototype },
+	error::{ SpriteError, SpriteErrorKind },
+	sprite::{ Frame, FrameMetadata, SpriteInfo },
+	palette::Palette,
+	validate::{ ValidationIssue, check_for_errors, check_frame_count, check_frame_sizes, check_transparent_color_index }
+};
+
+/// The SPR layouts Spritist can read and write. Creatures 1 uses `Standard`;
+/// the others come from earlier prototypes and other games using the format.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SprVariant {
+	Standard,
+	/// Rows stored bottom-up after a width and height for each frame.
+	SingleWidth { unknown: u32 },
+	/// Like `SingleWidth`, but each frame also has a padded row width.
+	/// `padded_widths` are the row widths the file used, so frames of the same
+	/// width are padded the same way when saved.
+	DoubleWidth { unknown: u32, padded_widths: Vec<u32> },
+	/// Several double-width sprites stored one after another.
+	MultiSprite { unknown: u32, sprites: Vec<SubSprite> },
+	/// Standard layout with 16-bit offsets and 8-bit frame sizes.
+	Prototype
+}
+
+/// One of the sprites in a multi-sprite SPR file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubSprite {
+	/// The header value after the sprite's frame count, kept as-is.
+	pub unknown: u32,
+	pub frame_count: usize,
+	/// The padded row width of each frame, as in `SprVariant::DoubleWidth`.
+	pub padded_widths: Vec<u32>
+}
+
+struct ImageHeader {
+	width: u16,
+	height: u16
+}
+struct ImageHeaderPrototype {
+	width: u8,
+	height: u8
+}
+
+pub fn decode(contents: &[u8], palette: &Palette) -> Result<SpriteInfo, SpriteError> {
+	without_lost_frames(salvage(contents, palette)?)
+}
+
+fn read_image_data(buffer: &mut &[u8], image_header: &ImageHeader, palette: &Palette, file_size: usize, frame: usize, budget: &mut PixelBudget) -> Result<Frame, SpriteError> {
+	budget.allocate(image_header.width.into(), image_header.height.into(), frame)?;
+	check_remaining(buffer, image_header.width as u64 * image_header.height as u64, file_size, frame)?;
+	let mut image = RgbaImage::new(image_header.width.into(), image_header.height.into());
+	let mut color_indexes: Vec<u8> = Vec::new();
+	for y in 0..image_header.height {
+		for x in 0..image_header.width {
+			let color_index = buffer.get_u8();
+			color_indexes.push(color_index);
+			let pixel = palette.get_color(color_index);
+			image.put_pixel(x.into(), y.into(), pixel);
+		}
+	}
+	Ok(Frame{ image, color_indexes, metadata: FrameMetadata::default(), native_pixels: None })
+}
+
+/// Decodes every frame it can, replacing the ones that can't be decoded with
+/// transparent placeholders. Returns the errors for the frames that were lost.
+/// Frames are stored one after another, so once one frame's data is cut short
+/// all the frames after it are lost too.
+pub fn salvage(contents: &[u8], palette: &Palette) -> Result<(SpriteInfo, Vec<SpriteError>), SpriteError> {
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut buffer = contents;
+
+	// file header
+	if buffer.remaining() < 2 { return Err(file_header_error(0)); }
+	let image_count = buffer.get_u16_le();
+
+	// image headers
+	let mut image_headers: Vec<Result<ImageHeader, SpriteError>> = Vec::new();
+	for i in 0..image_count as usize {
+		if buffer.remaining() < 8 {
+			image_headers.push(Err(image_header_error(i, buffer_offset(contents.len(), buffer))));
+			continue;
+		}
+		let _offset = buffer.get_u32_le();
+		image_headers.push(Ok(ImageHeader {
+			width: buffer.get_u16_le(),
+			height: buffer.get_u16_le()
+		}));
+	}
+
+	// image data
+	let mut budget = PixelBudget::default();
+	let mut lost_frames: Vec<SpriteError> = Vec::new();
+	let mut data_is_cut_short = false;
+	for (i, image_header) in image_headers.iter().enumerate() {
+		let frame = match image_header {
+			Ok(_) if data_is_cut_short => Err(image_error(i, contents.len())),
+			Ok(image_header) => read_image_data(&mut buffer, image_header, palette, contents.len(), i, &mut budget),
+			Err(why) => Err(why.clone())
+		};
+		match frame {
+			Ok(frame) => frames.push(frame),
+			Err(why) => {
+				let size = image_header.as_ref().ok().map(|image_header| (image_header.width.into(), image_header.height.into()));
+				frames.push(placeholder_frame(size, &mut budget, i));
+				lost_frames.push(why);
+				data_is_cut_short = true;
+			}
+		}
+	}
+
+	Ok((SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: Some(SprVariant::Standard),
+		dta_variant: None
+	}, lost_frames))
+}
+
+pub fn decode_single_width(contents: &[u8], palette: &Palette) -> Result<SpriteInfo, SpriteError> {
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut buffer = contents;
+
+	// file header
+	if buffer.remaining() < 6 { return Err(file_header_error(0)); }
+	let image_count = buffer.get_u16_le();
+	let unknown = buffer.get_u32_le();
+
+	// image data
+	let mut budget = PixelBudget::default();
+	for i in 0..image_count as usize {
+		if buffer.remaining() < 4 { return Err(image_header_error(i, buffer_offset(contents.len(), buffer))); }
+		let width = buffer.get_u16_le();
+		let height = buffer.get_u16_le();
+		budget.allocate(width.into(), height.into(), i)?;
+		check_remaining(buffer, width as u64 * height as u64, contents.len(), i)?;
+
+		let mut image = RgbaImage::new(width.into(), height.into());
+		let mut color_indexes: Vec<u8> = vec![0; width as usize * height as usize];
+		for y in (0..height).rev() {
+			for x in 0..width {
+				if buffer.remaining() < 1 { return Err(image_error(i, buffer_offset(contents.len(), buffer))); }
+				let color_index = buffer.get_u8();
+				color_indexes[x as usize + (y as usize * width as usize)] = color_index;
+				let pixel = palette.get_color(color_index);
+				image.put_pixel(x.into(), y.into(), pixel);
+			}
+		}
+		frames.push(Frame{ image, color_indexes, metadata: FrameMetadata::default(), native_pixels: None });
+	}
+
+	Ok(SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: Some(SprVariant::SingleWidth { unknown }),
+		dta_variant: None
+	})
+}
+
+// `file_size` and `first_frame` are only used to say where errors happened,
+// `budget` is shared by all the sprites in a multi-sprite file
+// returns the sprite's unknown header value, its frames and their padded widths
+fn read_double_width_sprite(buffer: &mut &[u8], palette: &Palette, budget: &mut PixelBudget, file_size: usize, first_frame: usize) -> Result<(u32, Vec<Frame>, Vec<u32>), SpriteError> {
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut padded_widths: Vec<u32> = Vec::new();
+
+	// file header
+	if buffer.remaining() < 6 { return Err(file_header_error(buffer_offset(file_size, buffer))); }
+	let image_count = buffer.get_u16_le();
+	let unknown = buffer.get_u32_le();
+
+	// image data
+	for i in first_frame..(first_frame + image_count as usize) {
+		let header_offset = buffer_offset(file_size, buffer);
+		if buffer.remaining() < 10 { return Err(image_header_error(i, header_offset)); }
+		let padded_width = buffer.get_u32_le();
+		let height = buffer.get_u32_le();
+		let width = buffer.get_u16_le();
+
+		if height > 65535 {
+			return Err(SpriteError::new(SpriteErrorKind::DimensionMismatch {
+				width: width.into(),
+				height,
+				expected: "SPR frames can be at most 65535 px tall".to_string()
+			}).at_frame(i).at_offset(header_offset));
+		}
+		if padded_width < width as u32 {
+			return Err(SpriteError::new(SpriteErrorKind::DimensionMismatch {
+				width: width.into(),
+				height,
+				expected: format!("the padded width is only {} px", padded_width)
+			}).at_frame(i).at_offset(header_offset));
+		}
+		budget.allocate(width.into(), height.into(), i)?;
+		check_remaining(buffer, padded_width as u64 * height as u64, file_size, i)?;
+
+		let mut image = RgbaImage::new(width.into(), height);
+		let mut color_indexes: Vec<u8> = vec![0; width as usize * height as usize];
+		for y in (0..height).rev() {
+			for x in 0..padded_width {
+				if buffer.remaining() < 1 { return Err(image_error(i, buffer_offset(file_size, buffer))); }
+				let color_index = buffer.get_u8();
+				if x < (width as u32) {
+					color_indexes[(x + (y * width as u32)) as usize] = color_index;
+					let pixel = palette.get_color(color_index);
+					image.put_pixel(x, y, pixel);
+				}
+			}
+		}
+		frames.push(Frame{ image, color_indexes, metadata: FrameMetadata::default(), native_pixels: None });
+		padded_widths.push(padded_width);
+	}
+
+	Ok((unknown, frames, padded_widths))
+}
+
+pub fn decode_double_width(contents: &[u8], palette: &Palette) -> Result<SpriteInfo, SpriteError> {
+	let mut buffer = contents;
+	let (unknown, frames, padded_widths) = read_double_width_sprite(&mut buffer, palette, &mut PixelBudget::default(), contents.len(), 0)?;
+
+	Ok(SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: Some(SprVariant::DoubleWidth { unknown, padded_widths }),
+		dta_variant: None
+	})
+}
+
+pub fn decode_multi_sprite(contents: &[u8], palette: &Palette) -> Result<SpriteInfo, SpriteError> {
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut buffer = contents;
+
+	// file header
+	if buffer.remaining() < 6 { return Err(file_header_error(0)); }
+	let sprite_count = buffer.get_u16_le();
+	let unknown = buffer.get_u32_le();
+
+	// sprites
+	let mut budget = PixelBudget::default();
+	let mut sprites: Vec<SubSprite> = Vec::new();
+	for _ in 0..sprite_count {
+		let (sprite_unknown, sprite_frames, padded_widths) = read_double_width_sprite(&mut buffer, palette, &mut budget, contents.len(), frames.len())?;
+		sprites.push(SubSprite { unknown: sprite_unknown, frame_count: sprite_frames.len(), padded_widths });
+		frames.extend(sprite_frames);
+	}
+
+	Ok(SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: Some(SprVariant::MultiSprite { unknown, sprites }),
+		dta_variant: None
+	})
+}
+
+pub fn decode_prototype(contents: &[u8], palette: &Palette) -> Result<SpriteInfo, SpriteError> {
+	let mut frames: Vec<Frame> = Vec::new();
+	let mut buffer = contents;
+
+	// file header
+	if buffer.remaining() < 2 { return Err(file_header_error(0)); }
+	let image_count = buffer.get_u16_le();
+
+	// image headers
+	let mut image_headers: Vec<ImageHeaderPrototype> = Vec::new();
+	for i in 0..image_count as usize {
+		if buffer.remaining() < 4 { return Err(image_header_error(i, buffer_offset(contents.len(), buffer))); }
+		let _offset = buffer.get_u16_le();
+		image_headers.push(ImageHeaderPrototype {
+			width: buffer.get_u8(),
+			height: buffer.get_u8()
+		})
+	}
+
+	// image data
+	let mut budget = PixelBudget::default();
+	for (i, image_header) in image_headers.iter().enumerate() {
+		budget.allocate(image_header.width.into(), image_header.height.into(), i)?;
+		check_remaining(buffer, image_header.width as u64 * image_header.height as u64, contents.len(), i)?;
+		let mut image = RgbaImage::new(image_header.width.into(), image_header.height.into());
+		let mut color_indexes: Vec<u8> = Vec::new();
+		for y in 0..image_header.height {
+			for x in 0..image_header.width {
+				if buffer.remaining() < 1 { return Err(image_error(i, buffer_offset(contents.len(), buffer))); }
+				let color_index = buffer.get_u8();
+				color_indexes.push(color_index);
+				let pixel = palette.get_color(color_index);
+				image.put_pixel(x.into(), y.into(), pixel);
+			}
+		}
+		frames.push(Frame{ image, color_indexes, metadata: FrameMetadata::default(), native_pixels: None });
+	}
+
+	Ok(SpriteInfo{
+		frames,
+		pixel_format: PixelFormat::Format565,
+		cols: 0,
+		rows: 0,
+		read_only: false,
+		spr_variant: Some(SprVariant::Prototype),
+		dta_variant: None
+	})
+}
+
+// returns a palette index for every pixel of the frame, in row-major order
+fn get_color_indexes(frame: &Frame, palette: &Palette) -> Vec<u8> {
+	if frame.color_indexes.is_empty() {
+		return palette.get_closest_color_indexes(&frame.image);
+	}
+	let width = frame.image.width();
+	let height = frame.image.height();
+	let mut color_indexes = Vec::with_capacity((width * height) as usize);
+	for y in 0..height {
+		for x in 0..width {
+			let pixel_index = x + (y * width);
+			match frame.color_indexes.get(pixel_index as usize) {
+				Some(color_index) => color_indexes.push(*color_index),
+				None => {
+					let color = frame.image.get_pixel(x, y);
+					color_indexes.push(palette.find_color_index(*color).unwrap_or(0));
+				}
+			}
+		}
+	}
+	color_indexes
+}
+
+/// The layout a sprite is written in as `format`. `SpriteFormat::Spr` keeps
+/// the layout the sprite was read from (standard if it didn't come from an SPR
+/// file), and the other SPR formats always use their own, keeping the header
+/// values the sprite already has for that layout. A sprite saved as
+/// multi-sprite that wasn't read as one becomes a single sprite.
+pub fn layout_for_format(sprite_info: &SpriteInfo, format: SpriteFormat) -> SprVariant {
+	match (format, &sprite_info.spr_variant) {
+		(SpriteFormat::SprSingleWidth, Some(variant @ SprVariant::SingleWidth { .. })) |
+		(SpriteFormat::SprDoubleWidth, Some(variant @ SprVariant::DoubleWidth { .. })) |
+		(SpriteFormat::SprMultiSprite, Some(variant @ SprVariant::MultiSprite { .. })) => variant.clone(),
+		(SpriteFormat::SprSingleWidth, _) => SprVariant::SingleWidth { unknown: 0 },
+		(SpriteFormat::SprDoubleWidth, _) => SprVariant::DoubleWidth { unknown: 0, padded_widths: Vec::new() },
+		(SpriteFormat::SprMultiSprite, _) => SprVariant::MultiSprite {
+			unknown: 0,
+			sprites: vec![SubSprite { unknown: 0, frame_count: sprite_info.frames.len(), padded_widths: Vec::new() }]
+		},
+		(SpriteFormat::SprPrototype, _) => SprVariant::Prototype,
+		(_, variant) => variant.clone().unwrap_or(SprVariant::Standard)
+	}
+}
+
+/// Encodes a sprite in the SPR layout it was read from, or the standard
+/// layout if it didn't come from an SPR file.
+pub fn encode(sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, SpriteError> {
+	encode_as(sprite_info, SpriteFormat::Spr, palette)
+}
+
+/// Encodes a sprite in the layout of `format`. See `layout_for_format`.
+pub fn encode_as(sprite_info: SpriteInfo, format: SpriteFormat, palette: &Palette) -> Result<Bytes, SpriteError> {
+	let layout = layout_for_format(&sprite_info, format);
+	let mut issues = Vec::new();
+	check_limits(&sprite_info.frames, &layout, &mut issues);
+	check_for_errors(&issues, "SPR")?;
+	match layout {
+		SprVariant::SingleWidth { unknown } => encode_single_width(&sprite_info.frames, unknown, palette),
+		SprVariant::DoubleWidth { unknown, padded_widths } => encode_double_width(&sprite_info.frames, unknown, &padded_widths, palette),
+		SprVariant::MultiSprite { unknown, sprites } => encode_multi_sprite(&sprite_info.frames, unknown, &sprites, palette),
+		SprVariant::Prototype => encode_prototype(&sprite_info.frames, palette),
+		SprVariant::Standard => encode_standard(&sprite_info.frames, palette)
+	}
+}
+
+/// Checks a sprite against the rules of the SPR layout `encode_as` would
+/// write for `format`.
+pub fn validate(sprite_info: &SpriteInfo, format: SpriteFormat, palette: &Palette) -> Vec<ValidationIssue> {
+	let mut issues = Vec::new();
+	check_limits(&sprite_info.frames, &layout_for_format(sprite_info, format), &mut issues);
+	check_transparent_color_index(&sprite_info.frames, |frame| get_color_indexes(frame, palette), &mut issues);
+	issues
+}
+
+// the frame counts and sizes the headers of `layout` can hold
+fn check_limits(frames: &[Frame], layout: &SprVariant, issues: &mut Vec<ValidationIssue>) {
+	match layout {
+		SprVariant::Prototype => {
+			check_frame_count(frames, u16::MAX as usize, issues);
+			check_frame_sizes(frames, u8::MAX as u32, u8::MAX as u32, issues);
+		}
+		SprVariant::MultiSprite { sprites, .. } => {
+			let expected: usize = sprites.iter().map(|sprite| sprite.frame_count).sum();
+			if frames.len() != expected {
+				issues.push(ValidationIssue::WrongSubSpriteFrameCount { frame_count: frames.len(), expected });
+			}
+			// each sprite counts its own frames
+			if let Some(frame_count) = sprites.iter().map(|sprite| sprite.frame_count).max() {
+				if frame_count > u16::MAX as usize {
+					issues.push(ValidationIssue::TooManyFrames { frame_count, limit: u16::MAX as usize });
+				}
+			}
+			check_frame_sizes(frames, u16::MAX as u32, u16::MAX as u32, issues);
+		}
+		_ => {
+			check_frame_count(frames, u16::MAX as usize, issues);
+			check_frame_sizes(frames, u16::MAX as u32, u16::MAX as u32, issues);
+		}
+	}
+}
+
+pub fn encode_standard(frames: &[Frame], palette: &Palette) -> Result<Bytes, SpriteError> {
+	let frame_count = frames.len();
+
+	let mut images_buffer = BytesMut::new();
+	let mut headers_buffer = BytesMut::new();
+	for frame in frames {
+		let offset = 2 + (frame_count * 8) + images_buffer.len();
+		let width = frame.image.width();
+		let height = frame.image.height();
+
+		headers_buffer.put_u32_le(offset as u32);
+		headers_buffer.put_u16_le(width as u16);
+		headers_buffer.put_u16_le(height as u16);
+
+		images_buffer.extend_from_slice(&get_color_indexes(frame, palette));
+	}
+
+	let mut buffer = BytesMut::new();
+	buffer.put_u16_le(frame_count as u16);
+	buffer.extend_from_slice(&headers_buffer);
+	buffer.extend_from_slice(&images_buffer);
+
+	Ok(buffer.freeze())
+}
+
+pub fn encode_single_width(frames: &[Frame], unknown: u32, palette: &Palette) -> Result<Bytes, SpriteError> {
+	let mut buffer = BytesMut::new();
+	buffer.put_u16_le(frames.len() as u16);
+	buffer.put_u32_le(unknown);
+
+	for frame in frames {
+		let width = frame.image.width() as usize;
+		let height = frame.image.height() as usize;
+		buffer.put_u16_le(width as u16);
+		buffer.put_u16_le(height as u16);
+
+		let color_indexes = get_color_indexes(frame, palette);
+		for y in (0..height).rev() {
+			buffer.extend_from_slice(&color_indexes[(y * width)..((y + 1) * width)]);
+		}
+	}
+
+	Ok(buffer.freeze())
+}
+
+// frames keep the padded width they were read with unless they've become too
+// wide for it. Padding bytes are written as 0
+fn write_double_width_sprite(buffer: &mut BytesMut, frames: &[Frame], unknown: u32, padded_widths: &[u32], palette: &Palette) {
+	buffer.put_u16_le(frames.len() as u16);
+	buffer.put_u32_le(unknown);
+
+	for (i, frame) in frames.iter().enumerate() {
+		let width = frame.image.width() as usize;
+		let height = frame.image.height() as usize;
+		let padded_width = match padded_widths.get(i) {
+			Some(padded_width) if *padded_width as usize >= width => *padded_width as usize,
+			// rows are usually padded to a multiple of 4 bytes
+			_ => (width + 3) / 4 * 4
+		};
+		buffer.put_u32_le(padded_width as u32);
+		buffer.put_u32_le(height as u32);
+		buffer.put_u16_le(width as u16);
+
+		let color_indexes = get_color_indexes(frame, palette);
+		for y in (0..height).rev() {
+			buffer.extend_from_slice(&color_indexes[(y * width)..((y + 1) * width)]);
+			buffer.put_bytes(0, padded_width - width);
+		}
+	}
+}
+
+pub fn encode_double_width(frames: &[Frame], unknown: u32, padded_widths: &[u32], palette: &Palette) -> Result<Bytes, SpriteError> {
+	let mut buffer = BytesMut::new();
+	write_double_width_sprite(&mut buffer, frames, unknown, padded_widths, palette);
+	Ok(buffer.freeze())
+}
+
+pub fn encode_multi_sprite(frames: &[Frame], unknown: u32, sprites: &[SubSprite], palette: &Palette) -> Result<Bytes, SpriteError> {
+	let sprite_frame_count: usize = sprites.iter().map(|sprite| sprite.frame_count).sum();
+	if sprite_frame_count != frames.len() {
+		return Err(format!("Unable to save multi-sprite SPR. The file had {} frames when it was opened but now has {}, so they can't be split back into its {} sprites. Restore the original number of frames or save it in another format.", sprite_frame_count, frames.len(), sprites.len()).into());
+	}
+
+	let mut buffer = BytesMut::new();
+	buffer.put_u16_le(sprites.len() as u16);
+	buffer.put_u32_le(unknown);
+
+	let mut first_frame = 0;
+	for sprite in sprites {
+		let sprite_frames = &frames[first_frame..(first_frame + sprite.frame_count)];
+		write_double_width_sprite(&mut buffer, sprite_frames, sprite.unknown, &sprite.padded_widths, palette);
+		first_frame += sprite.frame_count;
+	}
+
+	Ok(buffer.freeze())
+}
+
+pub fn encode_prototype(frames: &[Frame], palette: &Palette) -> Result<Bytes, SpriteError> {
+	let frame_count = frames.len();
+
+	let mut images_buffer = BytesMut::new();
+	let mut headers_buffer = BytesMut::new();
+	for (i, frame) in frames.iter().enumerate() {
+		let offset = 2 + (frame_count * 4) + images_buffer.len();
+		let width = frame.image.width();
+		let height = frame.image.height();
+
+		if width > 255 || height > 255 {
+			return Err(SpriteError::new(SpriteErrorKind::DimensionMismatch {
+				width,
+				height,
+				expected: "prototype SPR frames can be at most 255 x 255 px".to_string()
+			}).at_frame(i));
+		}
+		if offset > 65535 {
+			return Err(SpriteError::from("Unable to save prototype SPR. Prototype SPR files can be at most 64 KB.").at_frame(i));
+		}
+
+		headers_buffer.put_u16_le(offset as u16);
+		headers_buffer.put_u8(width as u8);
+		headers_buffer.put_u8(height as u8);
+
+		images_buffer.extend_from_slice(&get_color_indexes(frame, palette));
+	}
+
+	let mut buffer = BytesMut::new();
+	buffer.put_u16_le(frame_count as u16);
+	buffer.extend_from_slice(&headers_buffer);
+	buffer.extend_from_slice(&images_buffer);
+
+	Ok(buffer.freeze())
+}
+
+pub struct SprCodec;
+
+impl SpriteCodec for SprCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::Spr
+	}
+
+	fn name(&self) -> &'static str {
+		"SPR"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["spr"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { indexed: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_spr(contents)
+	}
+
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode(contents, palette)?)
+	}
+
+	fn salvage(&self, contents: &[u8], palette: &Palette) -> Option<Result<(SpriteInfo, Vec<SpriteError>), SpriteError>> {
+		Some(salvage(contents, palette))
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info, self.format(), palette)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode_as(sprite_info, self.format(), palette)?)
+	}
+}
+
+pub struct SprSingleWidthCodec;
+
+impl SpriteCodec for SprSingleWidthCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::SprSingleWidth
+	}
+
+	fn name(&self) -> &'static str {
+		"SPR (single-width)"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["spr"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { indexed: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_spr_single_width(contents)
+	}
+
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode_single_width(contents, palette)?)
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info, self.format(), palette)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode_as(sprite_info, self.format(), palette)?)
+	}
+}
+
+pub struct SprDoubleWidthCodec;
+
+impl SpriteCodec for SprDoubleWidthCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::SprDoubleWidth
+	}
+
+	fn name(&self) -> &'static str {
+		"SPR (double-width)"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["spr"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { indexed: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_spr_double_width(contents)
+	}
+
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode_double_width(contents, palette)?)
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info, self.format(), palette)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode_as(sprite_info, self.format(), palette)?)
+	}
+}
+
+pub struct SprMultiSpriteCodec;
+
+impl SpriteCodec for SprMultiSpriteCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::SprMultiSprite
+	}
+
+	fn name(&self) -> &'static str {
+		"SPR (multi-sprite)"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["spr"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { indexed: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_spr_multi_sprite(contents)
+	}
+
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode_multi_sprite(contents, palette)?)
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info, self.format(), palette)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode_as(sprite_info, self.format(), palette)?)
+	}
+}
+
+pub struct SprPrototypeCodec;
+
+impl SpriteCodec for SprPrototypeCodec {
+	fn format(&self) -> SpriteFormat {
+		SpriteFormat::SprPrototype
+	}
+
+	fn name(&self) -> &'static str {
+		"SPR (prototype)"
+	}
+
+	fn extensions(&self) -> &'static [&'static str] {
+		&["spr"]
+	}
+
+	fn capabilities(&self) -> Capabilities {
+		Capabilities { indexed: true, ..Capabilities::default() }
+	}
+
+	fn detect(&self, contents: &[u8]) -> Option<u32> {
+		score_spr_prototype(contents)
+	}
+
+	fn decode(&self, contents: &[u8], palette: &Palette) -> Result<SpriteInfo, Box<dyn Error>> {
+		Ok(decode_prototype(contents, palette)?)
+	}
+
+	fn validate(&self, sprite_info: &SpriteInfo, palette: &Palette) -> Vec<ValidationIssue> {
+		validate(sprite_info, self.format(), palette)
+	}
+
+	fn encode(&self, sprite_info: SpriteInfo, palette: &Palette) -> Result<Bytes, Box<dyn Error>> {
+		Ok(encode_as(sprite_info, self.format(), palette)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::Rgba;
+
+	use super::*;
+	use crate::{ codec::codec, palette::original_palette };
+
+	fn indexed_frame(width: u32, height: u32, color_indexes: Vec<u8>) -> Frame {
+		let palette = original_palette();
+		let image = RgbaImage::from_fn(width, height, |x, y| palette.get_color(color_indexes[(y * width + x) as usize]));
+		Frame { image, color_indexes, metadata: FrameMetadata::default(), native_pixels: None }
+	}
+
+	fn sprite_info(frames: Vec<Frame>, spr_variant: Option<SprVariant>) -> SpriteInfo {
+		SpriteInfo {
+			frames,
+			pixel_format: PixelFormat::Format565,
+			cols: 0,
+			rows: 0,
+			read_only: false,
+			spr_variant,
+			dta_variant: None
+		}
+	}
+
+	#[test]
+	fn layout_codecs_write_their_own_layout() {
+		let palette = original_palette();
+		let frames = vec![indexed_frame(3, 2, vec![1, 2, 3, 4, 5, 6]), indexed_frame(2, 1, vec![7, 0])];
+		let cases = [
+			(SpriteFormat::SprSingleWidth, SprVariant::SingleWidth { unknown: 0 }),
+			(SpriteFormat::SprDoubleWidth, SprVariant::DoubleWidth { unknown: 0, padded_widths: vec![4, 4] }),
+			(SpriteFormat::SprMultiSprite, SprVariant::MultiSprite { unknown: 0, sprites: vec![SubSprite { unknown: 0, frame_count: 2, padded_widths: vec![4, 4] }] }),
+			(SpriteFormat::SprPrototype, SprVariant::Prototype)
+		];
+		for (format, variant) in cases {
+			let contents = codec(format).encode(sprite_info(frames.clone(), None), &palette).unwrap();
+			assert_eq!(codec(format).detect(&contents), Some(crate::detect::EXACT_MATCH), "{:?}", format);
+			let decoded = codec(format).decode(&contents, &palette).unwrap();
+			assert_eq!(decoded.spr_variant, Some(variant), "{:?}", format);
+			let color_indexes: Vec<&Vec<u8>> = decoded.frames.iter().map(|frame| &frame.color_indexes).collect();
+			assert_eq!(color_indexes, [&frames[0].color_indexes, &frames[1].color_indexes], "{:?}", format);
+		}
+	}
+
+	#[test]
+	fn double_width_padding_round_trips() {
+		let palette = original_palette();
+		let mut contents: Vec<u8> = Vec::new();
+		contents.extend_from_slice(&[1, 0, 9, 0, 0, 0]);
+		// a 2 x 2 frame padded to 8 bytes a row instead of 4
+		contents.extend_from_slice(&[8, 0, 0, 0, 2, 0, 0, 0, 2, 0]);
+		contents.extend_from_slice(&[3, 4, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0]);
+		let sprite_info = decode_double_width(&contents, &palette).unwrap();
+		assert_eq!(sprite_info.spr_variant, Some(SprVariant::DoubleWidth { unknown: 9, padded_widths: vec![8] }));
+		assert_eq!(sprite_info.frames[0].color_indexes, [1, 2, 3, 4]);
+		assert_eq!(encode(sprite_info, &palette).unwrap().as_ref(), contents.as_slice());
+	}
+
+	#[test]
+	fn multi_sprite_files_keep_their_sprites() {
+		let palette = original_palette();
+		let frames = vec![indexed_frame(1, 1, vec![1]), indexed_frame(2, 1, vec![2, 3]), indexed_frame(3, 1, vec![4, 5, 6])];
+		let spr_variant = SprVariant::MultiSprite {
+			unknown: 5,
+			sprites: vec![
+				SubSprite { unknown: 1, frame_count: 1, padded_widths: vec![4] },
+				SubSprite { unknown: 2, frame_count: 2, padded_widths: vec![4, 8] }
+			]
+		};
+		let contents = encode(sprite_info(frames, Some(spr_variant.clone())), &palette).unwrap();
+		let decoded = decode_multi_sprite(&contents, &palette).unwrap();
+		assert_eq!(decoded.spr_variant, Some(spr_variant));
+		assert_eq!(decoded.frames[2].color_indexes, [4, 5, 6]);
+		assert_eq!(encode(decoded, &palette).unwrap(), contents);
+	}
+
+	#[test]
+	fn prototype_files_round_trip() {
+		let palette = original_palette();
+		let frames = vec![indexed_frame(2, 2, vec![1, 2, 3, 4]), indexed_frame(1, 3, vec![5, 6, 7])];
+		let contents = encode(sprite_info(frames, Some(SprVariant::Prototype)), &palette).unwrap();
+		// a 16-bit offset and 8-bit width and height for each frame
+		assert_eq!(contents.len(), 2 + 2 * 4 + 4 + 3);
+		let decoded = decode_prototype(&contents, &palette).unwrap();
+		assert_eq!(decoded.spr_variant, Some(SprVariant::Prototype));
+		assert_eq!(decoded.frames[1].color_indexes, [5, 6, 7]);
+		assert_eq!(encode(decoded, &palette).unwrap(), contents);
+	}
+
+	#[test]
+	fn spr_codec_keeps_the_layout_it_was_read_as() {
+		let palette = original_palette();
+		let frames = vec![indexed_frame(2, 2, vec![1, 2, 3, 4])];
+		let single_width = sprite_info(frames, Some(SprVariant::SingleWidth { unknown: 7 }));
+		let contents = codec(SpriteFormat::Spr).encode(single_width, &palette).unwrap();
+		assert_eq!(decode_single_width(&contents, &palette).unwrap().spr_variant, Some(SprVariant::SingleWidth { unknown: 7 }));
+		assert_eq!(contents.len(), 6 + 4 + 4);
+	}
+
+	#[test]
+	fn frames_too_large_for_the_headers_are_an_error() {
+		let palette = original_palette();
+		let cases = [
+			(SprVariant::Standard, (65536, 1)),
+			(SprVariant::SingleWidth { unknown: 0 }, (1, 65536)),
+			(SprVariant::DoubleWidth { unknown: 0, padded_widths: Vec::new() }, (65536, 1)),
+			(SprVariant::Prototype, (1, 256))
+		];
+		for (spr_variant, (width, height)) in cases {
+			let frames = vec![Frame { image: RgbaImage::new(width, height), color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None }];
+			let why = encode(sprite_info(frames, Some(spr_variant.clone())), &palette).unwrap_err();
+			assert!(why.to_string().starts_with(&format!("Unable to save SPR. Frame 0 is {} x {} px", width, height)), "{:?}: {}", spr_variant, why);
+		}
+	}
+
+	#[test]
+	fn double_width_frames_can_be_at_most_65535_px_tall() {
+		let frame = Frame {
+			image: RgbaImage::from_pixel(1, 65536, Rgba([0, 0, 0, 0])),
+			color_indexes: Vec::new(),
+			metadata: FrameMetadata::default(),
+			native_pixels: None
+		};
+		let sprite_info = SpriteInfo {
+			frames: vec![frame],
+			pixel_format: PixelFormat::Format565,
+			cols: 0,
+			rows: 0,
+			read_only: false,
+			spr_variant: Some(SprVariant::DoubleWidth { unknown: 0, padded_widths: Vec::new() }),
+			dta_variant: None
+		};
+		let issues = validate(&sprite_info, SpriteFormat::Spr, &original_palette());
+		assert_eq!(issues, vec![ValidationIssue::FrameTooLarge { frame: 0, width: 1, height: 65536, max_width: 65535, max_height: 65535 }]);
+	}
+}
@@ -0,0 +1,197 @@
+use std::{
+	fs,
+	error::Error,
+	path::Path
+};
+
+use image::{ GenericImage, Rgba, RgbaImage };
+
+use crate::{
+	sprite::{ Frame, FrameMetadata },
+	codec::{ codec, codecs },
+	detect::score_formats,
+	palette::original_palette
+};
+
+/// Opens a plain image (PNG or BMP), detecting which from the file contents.
+/// Black pixels in BMP images are treated as transparent.
+pub fn get_image(file_path: &Path) -> Result<RgbaImage, Box<dyn Error>> {
+	let bytes = fs::read(file_path)?;
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	let image_codec = score_formats(&bytes, extension).into_iter()
+		.map(|format_score| codec(format_score.format))
+		.find(|codec| codec.capabilities().image);
+	match image_codec {
+		Some(image_codec) => {
+			let sprite_info = image_codec.decode(&bytes, &original_palette())?;
+			let frame = sprite_info.frames.into_iter().next().ok_or("Image has no frames.")?;
+			Ok(frame.image)
+		}
+		None => {
+			let image_names: Vec<&str> = codecs().iter()
+				.filter(|codec| codec.capabilities().image)
+				.map(|codec| codec.name())
+				.collect();
+			Err(format!("File is not a {} image.", image_names.join(" or ")).into())
+		}
+	}
+}
+
+/// Cuts an image into 128 x 128 BLK tiles, column by column. Returns the tiles
+/// along with the number of columns and rows. Edge tiles are padded with black.
+pub fn image_to_blk_frames(png_image: &RgbaImage) -> (Vec<Frame>, u32, u32) {
+	let cols = (png_image.width() as f32 / 128.0).ceil() as u32;
+	let rows = (png_image.height() as f32 / 128.0).ceil() as u32;
+
+	let mut frames: Vec<Frame> = Vec::new();
+	for col in 0..cols {
+		for row in 0..rows {
+			let tile_x = col * 128_u32;
+			let tile_y = row * 128_u32;
+			let mut tile_image = RgbaImage::new(128, 128);
+			for y in 0..128 {
+				for x in 0..128 {
+					let image_x = tile_x + x;
+					let image_y = tile_y + y;
+					let pixel = if image_x < png_image.width() && image_y < png_image.height() {
+						*png_image.get_pixel(image_x, image_y)
+					} else {
+						Rgba([0, 0, 0, 255])
+					};
+					tile_image.put_pixel(x, y, pixel);
+				}
+			}
+			frames.push(Frame{
+				image: tile_image,
+				color_indexes: Vec::new(),
+				metadata: FrameMetadata::default(),
+				native_pixels: None
+			});
+		}
+	}
+
+	(frames, cols, rows)
+}
+
+/// Cuts a spritesheet into a grid of `cols` x `rows` equally sized frames,
+/// skipping any tiles that are completely transparent.
+pub fn import_spritesheet_as_frames(file_path: &Path, cols: u32, rows: u32) -> Result<Vec<Frame>, Box<dyn Error>> {
+	let png_image = get_image(file_path)?;
+	let tile_width = png_image.width() / cols;
+	let tile_height = png_image.height() / rows;
+
+	let mut frames: Vec<Frame> = Vec::new();
+
+	for tile_y in 0..rows {
+		for tile_x in 0..cols {
+			let image_x = tile_x * tile_width;
+			let image_y = tile_y * tile_height;
+			let mut image = RgbaImage::new(tile_width, tile_height);
+			let mut empty_image = true;
+			for y in 0..tile_height {
+				for x in 0..tile_width {
+					if image_x + x < png_image.width() && image_y + y < png_image.height() {
+						let pixel = *png_image.get_pixel(image_x + x, image_y + y);
+						if pixel[3] == 255 {
+							empty_image = false;
+						}
+						image.put_pixel(x, y, pixel);
+					} else {
+						return Err("Invalid spritesheet dimensions".into());
+					}
+				}
+			}
+			if !empty_image {
+				frames.push(Frame { image, color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None })
+			}
+		}
+	}
+
+	Ok(frames)
+}
+
+/// Cuts a SpriteBuilder-style spritesheet into frames. The color of the top-left
+/// pixel is used as the divider color between frames.
+pub fn import_spritebuilder_spritesheet_as_frames(file_path: &Path) -> Result<Vec<Frame>, Box<dyn Error>> {
+	let spritesheet = get_image(file_path)?;
+	let divider_color = *spritesheet.get_pixel(0, 0);
+	let mut spritesheet = spritesheet.clone();
+	let mut frames: Vec<Frame> = Vec::new();
+
+	// find margin
+	let mut margin = 0;
+	for y in 0..spritesheet.height() {
+		for x in 0..spritesheet.width() {
+			if margin == 0 && *spritesheet.get_pixel(x, y) != divider_color {
+				margin = u32::min(x, y);
+				break;
+			}
+		}
+	}
+
+	// divide into sprites
+	for _try in 0..10000 {
+		if let Some(next_sprite) = get_next_sprite(&mut spritesheet, margin, &divider_color) {
+			frames.push(Frame {
+				image: next_sprite,
+				color_indexes: Vec::new(),
+				metadata: FrameMetadata::default(),
+				native_pixels: None
+			});
+		} else {
+			break;
+		}
+	}
+
+	Ok(frames)
+}
+
+fn get_next_sprite(spritesheet: &mut RgbaImage, margin: u32, divider_color: &Rgba<u8>) -> Option<RgbaImage> {
+	for y in margin..spritesheet.height()-margin {
+		for x in margin..spritesheet.width()-margin {
+			if spritesheet.get_pixel(x, y) != divider_color {
+				// find width
+				let mut sprite_width = 0;
+				for last_x in x..spritesheet.width() {
+					if spritesheet.get_pixel(last_x, y) == divider_color {
+						sprite_width = last_x - x;
+						break;
+					} else if last_x == spritesheet.width() - 1 {
+						sprite_width = spritesheet.width() - x;
+						break;
+					}
+				}
+
+				// find height
+				let mut sprite_height = 0;
+				for last_y in y..spritesheet.height() {
+					if spritesheet.get_pixel(x, last_y) == divider_color {
+						sprite_height = last_y - y;
+						break;
+					} else if last_y == spritesheet.height() - 1 {
+						sprite_height = spritesheet.height() - y;
+						break;
+					}
+				}
+
+				if sprite_width == 0 || sprite_height == 0 {
+					return None;
+				}
+
+				// copy sprite to new image
+				let mut subimage = spritesheet.sub_image(x, y, sprite_width, sprite_height);
+				let sprite = subimage.to_image();
+
+				// remove sprite from spritesheet
+				for y2 in 0..sprite_height {
+					for x2 in 0..sprite_width {
+						subimage.put_pixel(x2, y2, *divider_color);
+					}
+				}
+
+				return Some(sprite);
+			}
+		}
+	}
+	None
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::{
+	codec::codec,
+	error::{ SpriteError, SpriteErrorKind },
+	format::{ PixelFormat, SpriteFormat }
+};
+
+/// The layout of a sprite file, for working out why a game won't load it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileReport {
+	pub format: SpriteFormat,
+	pub file_size: usize,
+	/// The flags word at the start of the file.
+	pub flags: u32,
+	pub pixel_format: PixelFormat,
+	/// Columns and rows of tiles, for backgrounds.
+	pub background_size: Option<(u16, u16)>,
+	/// The size of the file header and all the image headers.
+	pub header_size: usize,
+	pub frames: Vec<FrameReport>,
+	pub anomalies: Vec<Anomaly>
+}
+
+/// Where one frame's data is and how it's stored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrameReport {
+	/// Where the frame's pixel data starts.
+	pub offset: u32,
+	pub width: u16,
+	pub height: u16,
+	/// Where each line starts, for compressed formats. Empty for the others.
+	pub line_offsets: Vec<u32>,
+	/// How many bytes of the file the frame's data takes up.
+	pub data_size: usize,
+	/// How many bytes the frame would take up uncompressed.
+	pub raw_size: usize,
+	/// Run statistics, for compressed formats.
+	pub runs: Option<RunStats>
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RunStats {
+	pub color_runs: u32,
+	pub transparent_runs: u32,
+	pub color_pixels: u64,
+	pub transparent_pixels: u64
+}
+
+/// Something about a file that decoders put up with but games might not.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Anomaly {
+	/// Bytes between the headers and frames that nothing points at.
+	UnreferencedBytes { offset: usize, length: usize },
+	/// Bytes after the end of the last frame.
+	TrailingData { offset: usize, length: usize },
+	/// Two frames share some of their data.
+	OverlappingFrames { frame: usize, other_frame: usize, offset: usize },
+	/// A frame's data starts inside the file or image headers.
+	FrameInHeaders { frame: usize, offset: usize },
+	/// A background's frame count isn't its columns times its rows.
+	TileCountMismatch { image_count: u16, cols: u16, rows: u16 },
+	/// A frame's header or data couldn't be read. Frames after a broken image
+	/// header aren't reported, because the headers after it can't be found.
+	BadFrame(SpriteError)
+}
+
+impl fmt::Display for Anomaly {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Anomaly::UnreferencedBytes { offset, length } => write!(f, "{} unreferenced byte(s) at {} ({:#x})", length, offset, offset),
+			Anomaly::TrailingData { offset, length } => write!(f, "{} byte(s) of trailing data at {} ({:#x})", length, offset, offset),
+			Anomaly::OverlappingFrames { frame, other_frame, offset } => write!(f, "Frames {} and {} overlap at {} ({:#x})", other_frame, frame, offset, offset),
+			Anomaly::FrameInHeaders { frame, offset } => write!(f, "Frame {} starts inside the headers at {} ({:#x})", frame, offset, offset),
+			Anomaly::TileCountMismatch { image_count, cols, rows } => write!(f, "The header has {} frame(s), but {} x {} tiles need {}", image_count, cols, rows, *cols as u32 * *rows as u32),
+			Anomaly::BadFrame(why) => write!(f, "{}", why)
+		}
+	}
+}
+
+/// Reports the layout of a file in `format`, or returns None if the format
+/// can't be inspected. Only the file header has to be readable; problems with
+/// the frames are listed in the report's anomalies.
+pub fn inspect_file(contents: &[u8], format: SpriteFormat) -> Option<Result<FileReport, SpriteError>> {
+	codec(format).inspect(contents)
+}
+
+/// The parts of a file that its headers point at, for finding the parts they
+/// don't.
+#[derive(Default)]
+pub(crate) struct Coverage {
+	// start, end and the frame the bytes belong to (None for the headers)
+	ranges: Vec<(usize, usize, Option<usize>)>
+}
+
+impl Coverage {
+	pub(crate) fn add(&mut self, start: usize, end: usize, frame: Option<usize>) {
+		if end > start {
+			self.ranges.push((start, end, frame));
+		}
+	}
+
+	pub(crate) fn anomalies(mut self, file_size: usize) -> Vec<Anomaly> {
+		let mut anomalies: Vec<Anomaly> = Vec::new();
+		self.ranges.sort_by_key(|(start, end, _)| (*start, *end));
+		let mut covered_to = 0;
+		let mut last_frame: Option<usize> = None;
+		for (start, end, frame) in &self.ranges {
+			if *start > covered_to {
+				anomalies.push(Anomaly::UnreferencedBytes { offset: covered_to, length: start - covered_to });
+			} else if *start < covered_to {
+				// the range starts inside the one that reaches furthest so far
+				match (*frame, last_frame) {
+					(Some(frame), Some(other_frame)) if frame != other_frame => {
+						let (frame, other_frame) = (frame.max(other_frame), frame.min(other_frame));
+						let already_reported = anomalies.iter().any(|anomaly| matches!(anomaly,
+							Anomaly::OverlappingFrames { frame: reported_frame, other_frame: reported_other_frame, .. }
+							if *reported_frame == frame && *reported_other_frame == other_frame
+						));
+						if !already_reported {
+							anomalies.push(Anomaly::OverlappingFrames { frame, other_frame, offset: *start });
+						}
+					}
+					(Some(frame), None) => anomalies.push(Anomaly::FrameInHeaders { frame, offset: *start }),
+					_ => {}
+				}
+			}
+			if *end > covered_to {
+				covered_to = *end;
+				last_frame = *frame;
+			}
+		}
+		if covered_to < file_size {
+			anomalies.push(Anomaly::TrailingData { offset: covered_to, length: file_size - covered_to });
+		}
+		anomalies
+	}
+}
+
+/// Reports on a frame stored as `width` x `height` 16-bit pixels at `offset`.
+/// Also returns the error if the frame doesn't fit in the file.
+pub(crate) fn inspect_uncompressed_frame(file_size: usize, offset: u32, width: u16, height: u16, frame: usize, coverage: &mut Coverage) -> (FrameReport, Option<SpriteError>) {
+	let raw_size = width as usize * height as usize * 2;
+	let start = (offset as usize).min(file_size);
+	let end = (offset as usize).saturating_add(raw_size).min(file_size);
+	coverage.add(start, end, Some(frame));
+	let error = if offset as usize > file_size {
+		Some(SpriteError::new(SpriteErrorKind::BadLineOffset(offset)).at_frame(frame))
+	} else if end - start < raw_size {
+		Some(SpriteError::new(SpriteErrorKind::TruncatedImageData).at_frame(frame).at_offset(file_size))
+	} else {
+		None
+	};
+	(FrameReport {
+		offset,
+		width,
+		height,
+		line_offsets: Vec::new(),
+		data_size: end - start,
+		raw_size,
+		runs: None
+	}, error)
+}
+
+/// Reads image headers until one can't be read, which is added to
+/// `anomalies`, since the ones after it can't be found.
+pub(crate) fn read_image_headers<H>(image_count: usize, mut read_image_header: impl FnMut(usize) -> Result<H, SpriteError>, anomalies: &mut Vec<Anomaly>) -> Vec<H> {
+	let mut image_headers: Vec<H> = Vec::new();
+	for i in 0..image_count {
+		match read_image_header(i) {
+			Ok(image_header) => image_headers.push(image_header),
+			Err(why) => {
+				anomalies.push(Anomaly::BadFrame(why));
+				break;
+			}
+		}
+	}
+	image_headers
+}
@@ -0,0 +1,32 @@
+//! Codecs for the sprite and palette formats used by the Creatures game series.
+//!
+//! Every format module in [`format`] exposes a `decode` function that turns the
+//! raw bytes of a file into a [`SpriteInfo`], and (where the format can be
+//! written) an `encode` function that turns a [`SpriteInfo`] back into bytes.
+//! Indexed formats (SPR, Photo Album) also take the [`Palette`] used to map
+//! color indexes to RGBA pixels. Each format also has a [`SpriteCodec`], and
+//! [`codec::codecs`] lists them all for code that works with any format.
+
+pub mod error;
+pub mod format;
+pub mod codec;
+pub mod detect;
+pub mod inspect;
+pub mod validate;
+pub mod color_match;
+pub mod dither;
+pub mod palette;
+pub mod palette_file;
+pub mod palette_generator;
+pub mod pixel_format;
+pub mod sprite;
+pub mod file;
+pub mod import;
+pub mod export;
+pub mod batch;
+
+pub use error::{ SpriteError, SpriteErrorKind };
+pub use codec::{ Capabilities, SpriteCodec };
+pub use format::{ ChannelExpansion, PixelFormat, SpriteFormat };
+pub use palette::{ Palette, PaletteType };
+pub use sprite::{ Frame, FrameMetadata, NativePixels, SpriteInfo };
@@ -0,0 +1,328 @@
+use std::{
+	error::Error,
+	ops::RangeInclusive,
+	sync::{ Arc, Mutex }
+};
+
+use bytes::Buf;
+use image::{ Rgba, RgbaImage };
+use rayon::prelude::*;
+
+use crate::{
+	sprite::Frame,
+	color_match::{ ColorMetric, LookupTable },
+	dither::{ DitherMethod, dither_image }
+};
+
+/// A 256-color palette used by the indexed C1 formats (SPR, Photo Album).
+/// Index 0 is always transparent.
+#[derive(Clone)]
+pub struct Palette {
+	pub palette_type: PaletteType,
+	pub file_name: Option<String>,
+	pub colors: [(u8, u8, u8); 256],
+	/// How colors are compared when matching them to the palette.
+	pub color_metric: ColorMetric,
+	// closest colors worked out so far, shared between clones
+	lookup_tables: Arc<Mutex<Vec<Arc<LookupTable>>>>
+}
+
+/// The entries C1 replaces with its own colors whatever the palette file says
+/// (see `format_colors`), so they can't be edited or moved.
+pub const RESERVED_COLORS: RangeInclusive<u8> = 246..=255;
+
+// how far ordered dithering pushes each channel when converting to a palette,
+// about the gap between neighboring colors in the C1 palette
+const PALETTE_BAYER_SPREAD: i32 = 32;
+
+/// Where a [`Palette`] came from.
+#[derive(Clone, PartialEq)]
+pub enum PaletteType {
+	Original,
+	Reversed,
+	Custom
+}
+
+impl Palette {
+	pub fn new(palette_type: PaletteType, file_name: Option<String>, colors: [(u8, u8, u8); 256]) -> Palette {
+		Palette {
+			palette_type,
+			file_name,
+			colors,
+			color_metric: ColorMetric::default(),
+			lookup_tables: Arc::new(Mutex::new(Vec::new()))
+		}
+	}
+
+	/// The same palette, matching colors by `color_metric`.
+	pub fn with_color_metric(mut self, color_metric: ColorMetric) -> Palette {
+		self.color_metric = color_metric;
+		self
+	}
+
+	pub fn get_color(&self, color_index: u8) -> Rgba<u8> {
+		if color_index == 0 {
+			Rgba([0, 0, 0, 0])
+		} else {
+			match self.colors.get(color_index as usize) {
+				Some(color) => Rgba([color.0, color.1, color.2, 255]),
+				None => Rgba([0, 0, 0, 255])
+			}
+		}
+	}
+
+	pub fn find_color_index(&self, color: Rgba<u8>) -> Option<u8> {
+		if color[3] == 0 { return Some(0); }
+		for (i, palette_color) in self.colors.iter().enumerate() {
+			if palette_color.0 == color[0] && palette_color.1 == color[1] && palette_color.2 == color[2] {
+				return Some(i as u8);
+			}
+		}
+		None
+	}
+
+	pub fn is_reserved(color_index: u8) -> bool {
+		RESERVED_COLORS.contains(&color_index)
+	}
+
+	/// Changes one entry. Reserved entries can't be changed.
+	pub fn set_color(&mut self, color_index: u8, color: (u8, u8, u8)) -> Result<(), Box<dyn Error>> {
+		if Palette::is_reserved(color_index) {
+			return Err(format!("Color {} is reserved by C1 and can't be changed.", color_index).into());
+		}
+		self.colors[color_index as usize] = color;
+		Ok(())
+	}
+
+	/// Swaps two entries. Returns where each old color index ends up, for
+	/// `remap_color_indexes`.
+	pub fn swap_colors(&mut self, first: u8, second: u8) -> Result<[u8; 256], Box<dyn Error>> {
+		check_movable(first)?;
+		check_movable(second)?;
+		self.colors.swap(first as usize, second as usize);
+		let mut mapping = identity_mapping();
+		mapping.swap(first as usize, second as usize);
+		Ok(mapping)
+	}
+
+	/// Moves an entry to another index, shifting the entries in between along
+	/// by one. Returns where each old color index ends up, for
+	/// `remap_color_indexes`.
+	pub fn move_color(&mut self, from: u8, to: u8) -> Result<[u8; 256], Box<dyn Error>> {
+		check_movable(from)?;
+		check_movable(to)?;
+		let (from, to) = (from as usize, to as usize);
+		let mut mapping = identity_mapping();
+		if from < to {
+			self.colors[from..=to].rotate_left(1);
+			mapping[(from + 1)..=to].iter_mut().for_each(|color_index| *color_index -= 1);
+		} else {
+			self.colors[to..=from].rotate_right(1);
+			mapping[to..from].iter_mut().for_each(|color_index| *color_index += 1);
+		}
+		mapping[from] = to as u8;
+		Ok(mapping)
+	}
+
+	pub fn get_closest_color_indexes(&self, image: &RgbaImage) -> Vec<u8> {
+		let lookup_table = self.lookup_table();
+		image.pixels()
+			.map(|color| if color[3] == 0 { 0 } else { lookup_table.find((color[0], color[1], color[2])) })
+			.collect()
+	}
+
+	/// Like `get_closest_color_indexes`, but dithered.
+	pub fn get_dithered_color_indexes(&self, image: &RgbaImage, dither: DitherMethod) -> Vec<u8> {
+		let lookup_table = self.lookup_table();
+		dither_image(image, dither, [PALETTE_BAYER_SPREAD; 3], 0, |[r, g, b]| {
+			let color_index = lookup_table.find((r, g, b));
+			let (r, g, b) = self.colors[color_index as usize];
+			(color_index, [r, g, b])
+		})
+	}
+
+	/// Finds the palette color closest to `color` by `color_metric`, or 0 for
+	/// transparent pixels. Colors in the same 16-bit color (565) get the same
+	/// index, unless they're exactly in the palette.
+	pub fn find_closest_color_index(&self, color: &Rgba<u8>) -> u8 {
+		if color[3] == 0 { return 0; }
+		self.lookup_table().find((color[0], color[1], color[2]))
+	}
+
+	// builds the lookup table for the current colors and metric, unless an
+	// earlier one still fits. The lock isn't held while it's built, because
+	// building runs in parallel and the thread waiting for the lock could end up
+	// being asked to help
+	fn lookup_table(&self) -> Arc<LookupTable> {
+		let is_current = |lookup_table: &LookupTable| lookup_table.colors == self.colors && lookup_table.metric == self.color_metric;
+		if let Some(lookup_table) = self.lookup_tables.lock().unwrap().iter().find(|lookup_table| is_current(lookup_table)) {
+			return lookup_table.clone();
+		}
+		let lookup_table = Arc::new(LookupTable::new(&self.colors, self.color_metric));
+		let mut lookup_tables = self.lookup_tables.lock().unwrap();
+		// tables for colors the palette doesn't have anymore won't be needed again
+		lookup_tables.retain(|lookup_table| lookup_table.colors == self.colors);
+		lookup_tables.push(lookup_table.clone());
+		lookup_table
+	}
+}
+
+// index 0 is always transparent, so moving it would make other colors transparent
+fn check_movable(color_index: u8) -> Result<(), Box<dyn Error>> {
+	if color_index == 0 {
+		Err("Color 0 is transparent and can't be moved.".into())
+	} else if Palette::is_reserved(color_index) {
+		Err(format!("Color {} is reserved by C1 and can't be moved.", color_index).into())
+	} else {
+		Ok(())
+	}
+}
+
+fn identity_mapping() -> [u8; 256] {
+	let mut mapping = [0; 256];
+	for (i, color_index) in mapping.iter_mut().enumerate() {
+		*color_index = i as u8;
+	}
+	mapping
+}
+
+/// Reads a 768-byte C1 palette file (`palette.dta` or `.pal`), where each
+/// channel is stored as a 6-bit value.
+#[allow(clippy::type_complexity)]
+pub fn read_color_data(contents: &[u8]) -> Result<[(u8, u8, u8); 256], Box<dyn Error>> {
+	let mut buffer = contents;
+	let mut colors: [(u8, u8, u8); 256] = [(0, 0, 0); 256];
+
+	for color in &mut colors {
+		if buffer.remaining() < 3 {
+			return Err("Invalid palette data. File is too short.".into());
+		}
+		let r = buffer.get_u8() * 4;
+		let g = buffer.get_u8() * 4;
+		let b = buffer.get_u8() * 4;
+		*color = (r, g, b);
+	}
+
+	format_colors(&mut colors);
+
+	Ok(colors)
+}
+
+pub(crate) fn format_colors(colors: &mut [(u8, u8, u8); 256]) {
+	// replace last 10 colors, because that's what C1 does for some reason
+	colors[246] = (255, 255, 255);
+	colors[247] = (192, 192, 192);
+	colors[248] = (128, 128, 128);
+	colors[249] = (255, 0, 0);
+	colors[250] = (0, 255, 0);
+	colors[251] = (255, 255, 0);
+	colors[252] = (0, 0, 255);
+	colors[253] = (255, 0, 255);
+	colors[254] = (0, 255, 255);
+	colors[255] = (255, 255, 255);
+}
+
+/// Recolors an indexed frame by looking up its existing color indexes in a
+/// different palette.
+pub fn swap_palette_for_frame(frame: &Frame, palette: &Palette) -> Result<Frame, Box<dyn Error>> {
+	let width = frame.image.width();
+	let height = frame.image.height();
+
+	let mut new_image = RgbaImage::new(width, height);
+
+	// find color with - same index in new palette
+	for y in 0..height {
+		for x in 0..width {
+			let pixel_index = x + (y * width);
+			match frame.color_indexes.get(pixel_index as usize) {
+				Some(color_index) => {
+					let pixel = palette.get_color(*color_index);
+					new_image.put_pixel(x, y, pixel);
+				}
+				None => {
+					return Err("Unable to swap palettes because not all the frames have indexed color (SPR format). Try converting to palette instead.".into());
+				}
+			}
+		}
+	}
+
+	Ok(Frame{
+		image: new_image,
+		color_indexes: frame.color_indexes.clone(),
+		metadata: frame.metadata.clone(),
+		native_pixels: None
+	})
+}
+
+/// Maps every pixel of a frame to the closest color in a palette, dithering
+/// the colors in between.
+pub fn translate_colors_for_frame(frame: &Frame, palette: &Palette, dither: DitherMethod) -> Result<Frame, Box<dyn Error>> {
+	let width = frame.image.width();
+	let height = frame.image.height();
+
+	let color_indexes: Vec<u8> = palette.get_dithered_color_indexes(&frame.image, dither);
+	let mut new_image = RgbaImage::new(width, height);
+
+	for y in 0..height {
+		for x in 0..width {
+			let pixel_index = x + (y * width);
+			match color_indexes.get(pixel_index as usize) {
+				Some(color_index) => {
+					let pixel = palette.get_color(*color_index);
+					new_image.put_pixel(x, y, pixel);
+				}
+				None => {
+					return Err("Unable to swap palettes because not all the frames have indexed color (SPR format). Try converting to palette instead.".into());
+				}
+			}
+		}
+	}
+
+	Ok(Frame{ image: new_image, color_indexes, metadata: frame.metadata.clone(), native_pixels: None })
+}
+
+/// Maps every pixel of every frame to the closest color in a palette. The
+/// frames are translated in parallel and come back in the same order.
+pub fn translate_colors(frames: &[Frame], palette: &Palette, dither: DitherMethod) -> Result<Vec<Frame>, Box<dyn Error>> {
+	// build the lookup table once, rather than once per thread
+	palette.lookup_table();
+	let new_frames = frames.par_iter()
+		.map(|frame| translate_colors_for_frame(frame, palette, dither).map_err(|why| why.to_string()))
+		.collect::<Result<Vec<Frame>, String>>()?;
+	Ok(new_frames)
+}
+
+/// Changes the color indexes of indexed frames to match a reordered palette,
+/// using a mapping from `Palette::swap_colors` or `Palette::move_color`, so
+/// they look the same as before.
+pub fn remap_color_indexes(frames: &[Frame], mapping: &[u8; 256]) -> Vec<Frame> {
+	frames.par_iter()
+		.map(|frame| Frame {
+			image: frame.image.clone(),
+			color_indexes: frame.color_indexes.iter().map(|color_index| mapping[*color_index as usize]).collect(),
+			metadata: frame.metadata.clone(),
+			native_pixels: frame.native_pixels.clone()
+		})
+		.collect()
+}
+
+/// Redraws indexed frames with the colors of `palette`, after it's been
+/// edited. Frames without color indexes for every pixel are left as they are.
+pub fn recolor_frames(frames: &[Frame], palette: &Palette) -> Vec<Frame> {
+	frames.par_iter()
+		.map(|frame| swap_palette_for_frame(frame, palette).unwrap_or_else(|_| frame.clone()))
+		.collect()
+}
+
+pub fn original_palette() -> Palette {
+	let colors = [ (0, 0, 0), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (16, 8, 8), (20, 24, 40), (24, 40, 16), (24, 36, 48), (44, 16, 8), (40, 24, 36), (52, 40, 16), (48, 44, 48), (24, 28, 68), (20, 52, 84), (24, 60, 96), (36, 28, 68), (44, 52, 72), (44, 56, 104), (28, 64, 28), (28, 64, 40), (52, 72, 24), (52, 72, 44), (60, 96, 24), (60, 96, 40), (24, 64, 92), (28, 64, 100), (52, 68, 80), (44, 76, 104), (56, 96, 76), (60, 96, 112), (72, 24, 8), (72, 28, 36), (80, 44, 16), (72, 52, 44), (104, 24, 12), (108, 28, 36), (108, 48, 16), (104, 52, 36), (72, 56, 72), (72, 56, 104), (104, 52, 72), (116, 52, 104), (80, 72, 20), (80, 72, 48), (80, 100, 24), (76, 104, 44), (112, 72, 20), (108, 76, 44), (112, 100, 20), (116, 100, 48), (76, 76, 76), (76, 84, 108), (84, 100, 80), (84, 100, 112), (104, 84, 76), (104, 88, 104), (112, 104, 80), (108, 108, 108), (48, 60, 132), (56, 92, 144), (64, 60, 132), (76, 88, 140), (72, 88, 176), (80, 104, 140), (72, 108, 172), (100, 88, 136), (100, 92, 172), (108, 112, 140), (108, 116, 168), (76, 92, 196), (80, 116, 200), (92, 112, 236), (104, 120, 204), (100, 120, 244), (104, 140, 52), (92, 132, 76), (92, 128, 104), (108, 140, 76), (116, 136, 112), (120, 164, 76), (120, 164, 104), (88, 128, 140), (92, 128, 184), (112, 132, 148), (116, 136, 172), (124, 164, 140), (120, 164, 176), (88, 132, 204), (88, 144, 228), (88, 164, 240), (112, 136, 204), (116, 136, 252), (120, 160, 216), (112, 164, 236), (140, 24, 16), (144, 28, 36), (136, 52, 16), (140, 52, 40), (172, 24, 16), (172, 28, 32), (168, 48, 16), (172, 48, 40), (152, 52, 72), (140, 76, 20), (140, 80, 40), (144, 104, 20), (144, 104, 48), (172, 80, 20), (168, 84, 40), (176, 104, 20), (172, 108, 44), (136, 84, 72), (136, 88, 108), (140, 108, 76), (136, 116, 108), (172, 80, 72), (176, 84, 100), (168, 116, 72), (172, 116, 104), (208, 44, 28), (212, 52, 72), (200, 84, 20), (200, 84, 40), (204, 104, 20), (204, 112, 44), (232, 80, 20), (232, 80, 44), (232, 116, 20), (232, 116, 40), (204, 80, 72), (204, 84, 100), (204, 116, 72), (200, 116, 104), (232, 80, 80), (236, 88, 96), (240, 112, 72), (236, 112, 112), (144, 60, 132), (140, 80, 132), (132, 120, 144), (132, 120, 168), (168, 120, 136), (164, 124, 164), (128, 124, 196), (208, 48, 128), (216, 112, 136), (236, 116, 204), (164, 136, 44), (148, 132, 80), (144, 136, 112), (136, 172, 80), (140, 172, 108), (176, 136, 80), (172, 140, 108), (180, 164, 80), (180, 168, 112), (156, 196, 60), (164, 208, 92), (208, 136, 24), (208, 136, 48), (212, 168, 20), (208, 168, 44), (240, 140, 20), (236, 140, 44), (244, 172, 20), (244, 172, 48), (204, 140, 76), (200, 148, 104), (208, 168, 80), (208, 168, 112), (236, 144, 72), (236, 144, 100), (240, 172, 76), (236, 176, 108), (208, 196, 56), (244, 204, 12), (248, 204, 48), (252, 240, 12), (252, 236, 44), (212, 196, 80), (212, 196, 112), (200, 244, 80), (204, 244, 108), (248, 200, 76), (244, 204, 108), (248, 236, 76), (252, 232, 112), (140, 136, 144), (140, 144, 172), (144, 168, 144), (148, 168, 180), (168, 144, 140), (164, 152, 176), (176, 168, 144), (172, 168, 180), (136, 148, 204), (132, 152, 248), (148, 164, 208), (144, 168, 252), (160, 156, 196), (172, 172, 204), (164, 184, 244), (168, 200, 168), (152, 196, 196), (176, 192, 208), (168, 196, 252), (176, 232, 196), (184, 228, 232), (204, 144, 144), (200, 152, 164), (204, 176, 140), (200, 176, 176), (236, 144, 140), (236, 144, 164), (232, 180, 136), (232, 180, 168), (196, 184, 200), (196, 188, 224), (244, 172, 204), (212, 200, 144), (208, 200, 176), (204, 240, 136), (204, 228, 176), (240, 204, 144), (236, 208, 172), (248, 232, 144), (248, 236, 176), (212, 200, 204), (200, 200, 232), (212, 228, 204), (216, 232, 228), (228, 212, 208), (224, 208, 224), (240, 232, 208), (244, 244, 236), (252, 252, 252), (0, 0, 0), (0, 0, 0), (0, 0, 0), (255, 255, 255), (192, 192, 192), (128, 128, 128), (255, 0, 0), (0, 255, 0), (255, 255, 0), (0, 0, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255) ];
+	Palette::new(PaletteType::Original, None, colors)
+}
+
+pub fn reversed_palette() -> Palette {
+	let file_name = Some("reversed palette".to_string());
+	let mut colors = original_palette().colors;
+	colors.reverse();
+	format_colors(&mut colors);
+	Palette::new(PaletteType::Reversed, file_name, colors)
+}
@@ -0,0 +1,285 @@
+use std::{
+	fs,
+	error::Error,
+	io::Cursor,
+	path::Path
+};
+
+use image::{ ImageFormat, ImageReader, Rgba, RgbaImage };
+
+use crate::palette::{ Palette, PaletteType, read_color_data, format_colors };
+
+// size of each swatch in the PNG strips Spritist writes
+const SWATCH_WIDTH: u32 = 4;
+const SWATCH_HEIGHT: u32 = 16;
+
+/// The file formats a [`Palette`] can be read from and written to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PaletteFormat {
+	/// The C1 `palette.dta` format: 768 bytes of 6-bit channels.
+	Dta,
+	/// Paint Shop Pro's text format, also used by Aseprite and others.
+	JascPal,
+	/// GIMP and Inkscape's text format.
+	Gpl,
+	/// Adobe Color Table: 768 bytes of 8-bit channels, sometimes followed by a
+	/// color count and a transparent index.
+	Act,
+	/// A strip of 256 swatches, left to right.
+	Png
+}
+
+impl PaletteFormat {
+	pub const ALL: [PaletteFormat; 5] = [
+		PaletteFormat::Dta,
+		PaletteFormat::JascPal,
+		PaletteFormat::Gpl,
+		PaletteFormat::Act,
+		PaletteFormat::Png
+	];
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			PaletteFormat::Dta => "C1 Palette",
+			PaletteFormat::JascPal => "JASC-PAL",
+			PaletteFormat::Gpl => "GIMP Palette",
+			PaletteFormat::Act => "Adobe Color Table",
+			PaletteFormat::Png => "PNG Swatches"
+		}
+	}
+
+	/// File extensions for the format. C1 palettes sometimes use ".pal" too,
+	/// but reading tells them apart from JASC-PAL files by their contents.
+	pub fn extensions(&self) -> &'static [&'static str] {
+		match self {
+			PaletteFormat::Dta => &["dta"],
+			PaletteFormat::JascPal => &["pal"],
+			PaletteFormat::Gpl => &["gpl"],
+			PaletteFormat::Act => &["act"],
+			PaletteFormat::Png => &["png"]
+		}
+	}
+
+	pub fn from_extension(extension: &str) -> Option<PaletteFormat> {
+		let extension = extension.to_lowercase();
+		PaletteFormat::ALL.iter()
+			.find(|format| format.extensions().contains(&extension.as_str()))
+			.copied()
+	}
+
+	/// Works out the format from the file contents, using the extension for
+	/// formats that don't have a header.
+	pub fn detect(contents: &[u8], extension: Option<&str>) -> Option<PaletteFormat> {
+		if contents.starts_with(b"JASC-PAL") {
+			Some(PaletteFormat::JascPal)
+		} else if contents.starts_with(b"GIMP Palette") {
+			Some(PaletteFormat::Gpl)
+		} else if contents.starts_with(b"\x89PNG\r\n\x1a\n") {
+			Some(PaletteFormat::Png)
+		} else if contents.len() == 772 {
+			Some(PaletteFormat::Act)
+		} else if contents.len() >= 768 {
+			// 6-bit channels never go above 63
+			let is_act = extension.and_then(PaletteFormat::from_extension) == Some(PaletteFormat::Act);
+			if is_act || contents[..768].iter().any(|channel| *channel > 63) {
+				Some(PaletteFormat::Act)
+			} else {
+				Some(PaletteFormat::Dta)
+			}
+		} else {
+			None
+		}
+	}
+}
+
+/// Reads a palette in any of the [`PaletteFormat`]s. Palettes with fewer than
+/// 256 colors are padded with black. The last 10 colors are replaced with the
+/// ones C1 uses, whatever the file says.
+#[allow(clippy::type_complexity)]
+pub fn read_palette(contents: &[u8], extension: Option<&str>) -> Result<[(u8, u8, u8); 256], Box<dyn Error>> {
+	let format = PaletteFormat::detect(contents, extension)
+		.ok_or("Invalid palette data. File isn't a C1, JASC-PAL, GIMP, Adobe Color Table or PNG palette.")?;
+	let color_list = match format {
+		PaletteFormat::Dta => return read_color_data(contents),
+		PaletteFormat::JascPal => read_jasc_pal(contents)?,
+		PaletteFormat::Gpl => read_gpl(contents)?,
+		PaletteFormat::Act => read_act(contents),
+		PaletteFormat::Png => read_png_swatches(contents)?
+	};
+	if color_list.len() > 256 {
+		return Err(format!("Palette has {} colors, but C1 palettes can only have 256.", color_list.len()).into());
+	}
+	let mut colors: [(u8, u8, u8); 256] = [(0, 0, 0); 256];
+	colors[..color_list.len()].copy_from_slice(&color_list);
+	format_colors(&mut colors);
+	Ok(colors)
+}
+
+/// Reads a palette file, detecting its format from the contents.
+pub fn read_palette_file(file_path: &Path) -> Result<Palette, Box<dyn Error>> {
+	let bytes = fs::read(file_path)?;
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	let colors = read_palette(&bytes, extension)?;
+	let file_name = file_path.file_name().map(|file_name| file_name.to_string_lossy().into());
+	Ok(Palette::new(PaletteType::Custom, file_name, colors))
+}
+
+fn text_lines(contents: &[u8]) -> Result<Vec<&str>, Box<dyn Error>> {
+	let text = std::str::from_utf8(contents).map_err(|_| "Invalid palette data. File isn't valid text.")?;
+	Ok(text.lines().map(|line| line.trim()).collect())
+}
+
+fn parse_channel(channel: Option<&str>, line_number: usize) -> Result<u8, Box<dyn Error>> {
+	channel.and_then(|channel| channel.parse::<u8>().ok())
+		.ok_or_else(|| format!("Invalid palette data. Line {} isn't a color.", line_number + 1).into())
+}
+
+#[allow(clippy::type_complexity)]
+fn read_jasc_pal(contents: &[u8]) -> Result<Vec<(u8, u8, u8)>, Box<dyn Error>> {
+	let lines = text_lines(contents)?;
+	// header, version and color count
+	let color_count: usize = lines.get(2)
+		.and_then(|line| line.parse().ok())
+		.ok_or("Invalid palette data. JASC-PAL file is missing its color count.")?;
+	let mut colors = Vec::new();
+	for (i, line) in lines.iter().enumerate().skip(3).filter(|(_, line)| !line.is_empty()).take(color_count) {
+		let mut channels = line.split_whitespace();
+		colors.push((parse_channel(channels.next(), i)?, parse_channel(channels.next(), i)?, parse_channel(channels.next(), i)?));
+	}
+	Ok(colors)
+}
+
+fn read_act(contents: &[u8]) -> Vec<(u8, u8, u8)> {
+	let colors = contents[..768].chunks(3).map(|rgb| (rgb[0], rgb[1], rgb[2]));
+	// the optional color count is big-endian, and 0 means all of them
+	let color_count = match contents.get(768..770) {
+		Some(&[high, low]) => u16::from_be_bytes([high, low]) as usize,
+		_ => 256
+	};
+	if color_count > 0 && color_count < 256 {
+		colors.take(color_count).collect()
+	} else {
+		colors.collect()
+	}
+}
+
+#[allow(clippy::type_complexity)]
+fn read_gpl(contents: &[u8]) -> Result<Vec<(u8, u8, u8)>, Box<dyn Error>> {
+	let mut colors = Vec::new();
+	for (i, line) in text_lines(contents)?.iter().enumerate().skip(1) {
+		if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+			continue;
+		}
+		// anything after the channels is the color's name
+		let mut channels = line.split_whitespace();
+		colors.push((parse_channel(channels.next(), i)?, parse_channel(channels.next(), i)?, parse_channel(channels.next(), i)?));
+	}
+	Ok(colors)
+}
+
+// reads one color per pixel from small images, or the middle of each swatch
+// from strips of 256 swatches like the ones `write_palette` makes
+#[allow(clippy::type_complexity)]
+fn read_png_swatches(contents: &[u8]) -> Result<Vec<(u8, u8, u8)>, Box<dyn Error>> {
+	let image = ImageReader::with_format(Cursor::new(contents), ImageFormat::Png).decode()?.to_rgba8();
+	let (width, height) = image.dimensions();
+	let pixels: Vec<&Rgba<u8>> = if width as u64 * height as u64 <= 256 {
+		image.pixels().collect()
+	} else if width % 256 == 0 {
+		let swatch_width = width / 256;
+		(0..256).map(|i| image.get_pixel(i * swatch_width + swatch_width / 2, height / 2)).collect()
+	} else {
+		return Err(format!("Invalid palette image. It's {} x {} px, but palette images must have at most 256 pixels, or be a strip of 256 swatches.", width, height).into());
+	};
+	Ok(pixels.iter().map(|pixel| (pixel[0], pixel[1], pixel[2])).collect())
+}
+
+/// Writes a palette in the given format.
+pub fn write_palette(palette: &Palette, format: PaletteFormat) -> Result<Vec<u8>, Box<dyn Error>> {
+	let colors = &palette.colors;
+	Ok(match format {
+		PaletteFormat::Dta => colors.iter().flat_map(|(r, g, b)| [r / 4, g / 4, b / 4]).collect(),
+		PaletteFormat::JascPal => {
+			let mut text = String::from("JASC-PAL\r\n0100\r\n256\r\n");
+			for (r, g, b) in colors {
+				text.push_str(&format!("{} {} {}\r\n", r, g, b));
+			}
+			text.into_bytes()
+		}
+		PaletteFormat::Gpl => {
+			let name = palette.file_name.as_deref().unwrap_or("Creatures 1");
+			let mut text = format!("GIMP Palette\nName: {}\nColumns: 16\n#\n", name);
+			for (i, (r, g, b)) in colors.iter().enumerate() {
+				text.push_str(&format!("{:3} {:3} {:3}\tIndex {}\n", r, g, b, i));
+			}
+			text.into_bytes()
+		}
+		PaletteFormat::Act => {
+			let mut bytes: Vec<u8> = colors.iter().flat_map(|(r, g, b)| [*r, *g, *b]).collect();
+			// 256 colors, and index 0 is transparent
+			bytes.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+			bytes
+		}
+		PaletteFormat::Png => {
+			let image = RgbaImage::from_fn(256 * SWATCH_WIDTH, SWATCH_HEIGHT, |x, _| {
+				let (r, g, b) = colors[(x / SWATCH_WIDTH) as usize];
+				Rgba([r, g, b, 255])
+			});
+			let mut bytes = Cursor::new(Vec::new());
+			image.write_to(&mut bytes, ImageFormat::Png)?;
+			bytes.into_inner()
+		}
+	})
+}
+
+/// Writes a palette in the format that matches the file extension.
+pub fn write_palette_file(file_path: &Path, palette: &Palette) -> Result<(), Box<dyn Error>> {
+	let format = file_path.extension()
+		.and_then(|extension| extension.to_str())
+		.and_then(PaletteFormat::from_extension)
+		.ok_or("File does not have a valid palette extension (\".dta\", \".pal\", \".gpl\", \".act\", \".png\")")?;
+	fs::write(file_path, write_palette(palette, format)?)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::palette::original_palette;
+
+	#[test]
+	fn palettes_round_trip_in_every_format() {
+		let palette = original_palette();
+		for format in PaletteFormat::ALL {
+			let contents = write_palette(&palette, format).unwrap();
+			assert_eq!(PaletteFormat::detect(&contents, None), Some(format), "{:?}", format);
+			assert_eq!(read_palette(&contents, Some(format.extensions()[0])).unwrap(), palette.colors, "{:?}", format);
+		}
+	}
+
+	#[test]
+	fn palettes_are_written_in_each_formats_layout() {
+		let palette = original_palette();
+		let dta = write_palette(&palette, PaletteFormat::Dta).unwrap();
+		assert_eq!((dta.len(), &dta[3..6]), (768, &[63, 63, 63][..]));
+		let act = write_palette(&palette, PaletteFormat::Act).unwrap();
+		assert_eq!((act.len(), &act[3..6], &act[768..]), (772, &[252, 252, 252][..], &[1, 0, 0, 0][..]));
+		let jasc_pal = String::from_utf8(write_palette(&palette, PaletteFormat::JascPal).unwrap()).unwrap();
+		assert!(jasc_pal.starts_with("JASC-PAL\r\n0100\r\n256\r\n0 0 0\r\n252 252 252\r\n"));
+		let gpl = String::from_utf8(write_palette(&palette, PaletteFormat::Gpl).unwrap()).unwrap();
+		assert!(gpl.starts_with("GIMP Palette\nName: Creatures 1\nColumns: 16\n#\n  0   0   0\tIndex 0\n252 252 252\tIndex 1\n"));
+	}
+
+	#[test]
+	fn short_palettes_are_padded_with_black() {
+		let colors = read_palette(b"GIMP Palette\nName: Test\n#\n 10  20  30 first\n40 50 60\n", None).unwrap();
+		assert_eq!(colors[..3], [(10, 20, 30), (40, 50, 60), (0, 0, 0)]);
+		// the reserved colors are C1's whatever the file says
+		assert_eq!(colors[249], (255, 0, 0));
+
+		let mut act = vec![7; 768];
+		act.extend_from_slice(&[0, 1, 0xff, 0xff]);
+		let colors = read_palette(&act, None).unwrap();
+		assert_eq!(colors[..2], [(7, 7, 7), (0, 0, 0)]);
+	}
+}
@@ -0,0 +1,268 @@
+use std::{
+	cmp::Ordering,
+	collections::HashMap,
+	error::Error
+};
+
+use rayon::prelude::*;
+
+use crate::{
+	sprite::Frame,
+	palette::{ Palette, PaletteType, RESERVED_COLORS, format_colors }
+};
+
+/// How a palette is worked out from the colors in some artwork.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum GeneratorMethod {
+	/// Splits the colors into boxes along their widest channel until there's
+	/// a box for every free entry. Quick, and good at keeping rare colors.
+	#[default]
+	MedianCut,
+	/// Starts from the median cut palette and moves each color to the middle
+	/// of the pixels closest to it until it settles. Slower, but closer to the
+	/// art overall.
+	KMeans
+}
+
+impl GeneratorMethod {
+	pub const ALL: [GeneratorMethod; 2] = [
+		GeneratorMethod::MedianCut,
+		GeneratorMethod::KMeans
+	];
+
+	/// The name used on the command line and by the UI.
+	pub fn name(&self) -> &'static str {
+		match self {
+			GeneratorMethod::MedianCut => "median-cut",
+			GeneratorMethod::KMeans => "k-means"
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<GeneratorMethod> {
+		GeneratorMethod::ALL.iter()
+			.find(|method| method.name() == name.to_lowercase())
+			.copied()
+	}
+}
+
+// k-means stops after this many rounds even if the colors are still moving
+const MAX_K_MEANS_ROUNDS: usize = 16;
+
+// a group of similar colors from the art, weighted by how many pixels use them
+#[derive(Clone, Copy)]
+struct ColorCount {
+	color: [f32; 3],
+	count: u64
+}
+
+/// Works out a palette for the opaque pixels of `frames`. Index 0 stays
+/// transparent and the reserved entries (see `RESERVED_COLORS`) keep the
+/// colors C1 gives them, so the art gets the 245 entries in between. If the art
+/// has no more colors than that, they're used exactly.
+pub fn generate_palette(frames: &[Frame], method: GeneratorMethod) -> Result<Palette, Box<dyn Error>> {
+	let free_entries = *RESERVED_COLORS.start() as usize - 1;
+	let color_counts = count_colors(frames);
+	if color_counts.is_empty() {
+		return Err("There are no opaque pixels to make a palette from.".into());
+	}
+
+	let mut new_colors: Vec<(u8, u8, u8)> = if color_counts.len() <= free_entries {
+		color_counts.keys().copied().collect()
+	} else {
+		// group colors by their 16-bit (565) value, so big images with lots of
+		// subtly different colors don't take forever
+		let mut groups: HashMap<u16, ([u64; 3], u64)> = HashMap::new();
+		for ((r, g, b), count) in &color_counts {
+			let key = (*r as u16 >> 3) << 11 | (*g as u16 >> 2) << 5 | *b as u16 >> 3;
+			let (sums, total) = groups.entry(key).or_insert(([0; 3], 0));
+			for (sum, channel) in sums.iter_mut().zip([r, g, b]) {
+				*sum += *channel as u64 * count;
+			}
+			*total += count;
+		}
+		let mut colors: Vec<ColorCount> = groups.values()
+			.map(|(sums, count)| ColorCount {
+				color: [0, 1, 2].map(|channel| sums[channel] as f32 / *count as f32),
+				count: *count
+			})
+			.collect();
+		// keys aren't in any order, so sort to get the same palette every time
+		colors.sort_by(|a, b| a.color.partial_cmp(&b.color).unwrap_or(Ordering::Equal));
+		let mut centers = median_cut(&colors, free_entries);
+		if method == GeneratorMethod::KMeans {
+			centers = k_means(&colors, centers);
+		}
+		centers.iter().map(|center| (round(center[0]), round(center[1]), round(center[2]))).collect()
+	};
+	// darkest to lightest, so the palette is easier to find colors in
+	new_colors.sort_by_key(|(r, g, b)| (*r as u32 * 299 + *g as u32 * 587 + *b as u32 * 114, *r, *g, *b));
+	new_colors.dedup();
+
+	let mut colors: [(u8, u8, u8); 256] = [(0, 0, 0); 256];
+	colors[1..=new_colors.len()].copy_from_slice(&new_colors);
+	format_colors(&mut colors);
+	Ok(Palette::new(PaletteType::Custom, Some("generated palette".to_string()), colors))
+}
+
+fn round(channel: f32) -> u8 {
+	channel.round().clamp(0.0, 255.0) as u8
+}
+
+fn count_colors(frames: &[Frame]) -> HashMap<(u8, u8, u8), u64> {
+	let mut color_counts: HashMap<(u8, u8, u8), u64> = HashMap::new();
+	for frame in frames {
+		for pixel in frame.image.pixels().filter(|pixel| pixel[3] != 0) {
+			*color_counts.entry((pixel[0], pixel[1], pixel[2])).or_insert(0) += 1;
+		}
+	}
+	color_counts
+}
+
+// the weighted average color of each box
+fn median_cut(colors: &[ColorCount], box_count: usize) -> Vec<[f32; 3]> {
+	let mut boxes: Vec<Vec<ColorCount>> = vec![colors.to_vec()];
+	while boxes.len() < box_count {
+		// split the box with the widest channel, weighted by how many pixels
+		// it covers so busy areas of color get more entries
+		let widest = boxes.iter()
+			.enumerate()
+			.filter(|(_, colors)| colors.len() > 1)
+			.map(|(i, colors)| {
+				let (channel, range) = widest_channel(colors);
+				let count: u64 = colors.iter().map(|color| color.count).sum();
+				(i, channel, range * (count as f32).sqrt())
+			})
+			.max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal));
+		let (i, channel) = match widest {
+			Some((i, channel, _)) => (i, channel),
+			None => break
+		};
+		let mut colors = boxes.swap_remove(i);
+		colors.sort_by(|a, b| a.color[channel].partial_cmp(&b.color[channel]).unwrap_or(Ordering::Equal));
+		// split where half the pixels are on each side
+		let half: u64 = colors.iter().map(|color| color.count).sum::<u64>() / 2;
+		let mut running_count = 0;
+		let mut split = colors.len() - 1;
+		for (j, color) in colors.iter().enumerate() {
+			running_count += color.count;
+			if running_count > half {
+				split = j.max(1);
+				break;
+			}
+		}
+		let upper = colors.split_off(split);
+		boxes.push(colors);
+		boxes.push(upper);
+	}
+	boxes.iter().map(|colors| average(colors)).collect()
+}
+
+fn widest_channel(colors: &[ColorCount]) -> (usize, f32) {
+	(0..3)
+		.map(|channel| {
+			let (min, max) = colors.iter().fold((f32::MAX, f32::MIN), |(min, max), color| (min.min(color.color[channel]), max.max(color.color[channel])));
+			(channel, max - min)
+		})
+		.max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+		.unwrap_or((0, 0.0))
+}
+
+fn average(colors: &[ColorCount]) -> [f32; 3] {
+	let count: u64 = colors.iter().map(|color| color.count).sum();
+	let mut sums = [0.0_f64; 3];
+	for color in colors {
+		for (sum, channel) in sums.iter_mut().zip(color.color) {
+			*sum += channel as f64 * color.count as f64;
+		}
+	}
+	sums.map(|sum| (sum / count.max(1) as f64) as f32)
+}
+
+fn closest_center(color: &[f32; 3], centers: &[[f32; 3]]) -> usize {
+	let mut best_fit_index = 0;
+	let mut best_fit_dist = f32::MAX;
+	for (i, center) in centers.iter().enumerate() {
+		let dist = (0..3).map(|channel| (color[channel] - center[channel]).powi(2)).sum::<f32>();
+		if dist < best_fit_dist {
+			best_fit_index = i;
+			best_fit_dist = dist;
+		}
+	}
+	best_fit_index
+}
+
+fn k_means(colors: &[ColorCount], mut centers: Vec<[f32; 3]>) -> Vec<[f32; 3]> {
+	let mut assignments: Vec<usize> = Vec::new();
+	for _ in 0..MAX_K_MEANS_ROUNDS {
+		let new_assignments: Vec<usize> = colors.par_iter()
+			.map(|color| closest_center(&color.color, &centers))
+			.collect();
+		if new_assignments == assignments {
+			break;
+		}
+		assignments = new_assignments;
+		let mut members: Vec<Vec<ColorCount>> = vec![Vec::new(); centers.len()];
+		for (color, center) in colors.iter().zip(&assignments) {
+			members[*center].push(*color);
+		}
+		// centers nothing is closest to stay where they are
+		for (center, members) in centers.iter_mut().zip(&members) {
+			if !members.is_empty() {
+				*center = average(members);
+			}
+		}
+	}
+	centers
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{ Rgba, RgbaImage };
+
+	use super::*;
+	use crate::{ sprite::FrameMetadata, palette::original_palette };
+
+	fn frame(image: RgbaImage) -> Frame {
+		Frame { image, color_indexes: Vec::new(), metadata: FrameMetadata::default(), native_pixels: None }
+	}
+
+	#[test]
+	fn art_with_few_colors_gets_them_exactly() {
+		let image = RgbaImage::from_fn(3, 2, |x, y| match (x, y) {
+			(0, _) => Rgba([0, 0, 0, 0]),
+			(1, _) => Rgba([200, 100, 50, 255]),
+			_ => Rgba([10, 20, 30, 255])
+		});
+		for method in GeneratorMethod::ALL {
+			let palette = generate_palette(&[frame(image.clone())], method).unwrap();
+			// darkest first, then black for the entries nothing needed
+			assert_eq!(palette.colors[..4], [(0, 0, 0), (10, 20, 30), (200, 100, 50), (0, 0, 0)]);
+			assert_eq!(palette.colors[246..], original_palette().colors[246..]);
+		}
+	}
+
+	#[test]
+	fn colorful_art_fills_the_free_entries() {
+		let image = RgbaImage::from_fn(64, 64, |x, y| Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255]));
+		let frames = [frame(image)];
+		for method in GeneratorMethod::ALL {
+			let palette = generate_palette(&frames, method).unwrap();
+			assert_eq!(palette.colors[0], (0, 0, 0));
+			assert_eq!(palette.colors[246..], original_palette().colors[246..]);
+			let mut used_colors = palette.colors[1..246].to_vec();
+			used_colors.dedup();
+			assert!(used_colors.len() > 200, "{:?} {}", method, used_colors.len());
+			// every pixel has a color close to it
+			for pixel in frames[0].image.pixels() {
+				let (r, g, b) = palette.colors[palette.find_closest_color_index(pixel) as usize];
+				let distance = pixel[0].abs_diff(r).max(pixel[1].abs_diff(g)).max(pixel[2].abs_diff(b));
+				assert!(distance <= 32, "{:?} {:?}", method, pixel);
+			}
+		}
+	}
+
+	#[test]
+	fn transparent_art_has_no_palette() {
+		assert!(generate_palette(&[frame(RgbaImage::new(4, 4))], GeneratorMethod::MedianCut).is_err());
+	}
+}
@@ -0,0 +1,239 @@
+use image::Rgba;
+
+use crate::{
+	dither::{ DitherMethod, dither_image },
+	format::{ PixelFormat, parse_pixel, encode_pixel, unedited_native_pixels },
+	sprite::{ Frame, NativePixels }
+};
+
+/// How much converting frames to another pixel format changed them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConversionLoss {
+	/// Opaque pixels whose color changed.
+	pub changed_pixels: u64,
+	/// Opaque pixels in all the frames.
+	pub total_pixels: u64,
+	/// The biggest change to a single color channel, out of 255.
+	pub max_difference: u8
+}
+
+/// Re-encodes every pixel of a frame in `pixel_format`, so the image shows
+/// exactly the colors that will be saved. Dithering avoids banding in smooth
+/// gradients, which would otherwise lose their bottom 2 or 3 bits.
+pub fn convert_frame(frame: &Frame, pixel_format: PixelFormat, dither: DitherMethod) -> Frame {
+	// the gap between neighboring 5- and 6-bit channel values
+	let bayer_spread = match pixel_format {
+		PixelFormat::Format555 => [8, 8, 8],
+		PixelFormat::Format565 => [8, 4, 8]
+	};
+	let pixels = dither_image(&frame.image, dither, bayer_spread, 0, |[r, g, b]| {
+		let encoded_pixel = match encode_pixel(&Rgba([r, g, b, 255]), pixel_format) {
+			// 0 is transparent, so solid black is saved as dark grey
+			0 => match pixel_format {
+				PixelFormat::Format555 => 0x0421,
+				PixelFormat::Format565 => 0x0821
+			},
+			encoded_pixel => encoded_pixel
+		};
+		let color = parse_pixel(encoded_pixel, pixel_format);
+		(encoded_pixel, [color[0], color[1], color[2]])
+	});
+
+	let mut image = frame.image.clone();
+	for (pixel, encoded_pixel) in image.pixels_mut().zip(&pixels) {
+		if pixel[3] != 0 {
+			let color = parse_pixel(*encoded_pixel, pixel_format);
+			*pixel = Rgba([color[0], color[1], color[2], pixel[3]]);
+		}
+	}
+
+	Frame {
+		image,
+		color_indexes: frame.color_indexes.clone(),
+		metadata: frame.metadata.clone(),
+		native_pixels: Some(NativePixels{ pixel_format, pixels })
+	}
+}
+
+pub fn convert_frames(frames: &[Frame], pixel_format: PixelFormat, dither: DitherMethod) -> Vec<Frame> {
+	frames.iter().map(|frame| convert_frame(frame, pixel_format, dither)).collect()
+}
+
+/// Compares frames before and after `convert_frames`.
+pub fn conversion_loss(before: &[Frame], after: &[Frame]) -> ConversionLoss {
+	let mut loss = ConversionLoss::default();
+	for (before, after) in before.iter().zip(after) {
+		for (old_pixel, new_pixel) in before.image.pixels().zip(after.image.pixels()) {
+			if old_pixel[3] == 0 {
+				continue;
+			}
+			loss.total_pixels += 1;
+			let difference = (0..3).map(|channel| old_pixel[channel].abs_diff(new_pixel[channel])).max().unwrap_or(0);
+			if difference > 0 {
+				loss.changed_pixels += 1;
+				loss.max_difference = loss.max_difference.max(difference);
+			}
+		}
+	}
+	loss
+}
+
+/// Which pixel format a file's colors look like they're in, for files whose
+/// header flag may be wrong.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PixelFormatGuess {
+	pub pixel_format: PixelFormat,
+	/// The average change in green between neighboring pixels when the file is
+	/// read as 555. Real art is mostly smooth, so the lower the better.
+	pub roughness_555: f64,
+	/// The same for 565.
+	pub roughness_565: f64,
+	/// How many opaque pixels have the top bit set, which is never used in 555.
+	pub high_bit_pixels: u64,
+	/// How many opaque pixels were sampled.
+	pub sampled_pixels: u64,
+	/// Whether the evidence is strong enough to second-guess the header.
+	pub confident: bool
+}
+
+// frames sampled, spread evenly through the file
+const SAMPLE_FRAMES: usize = 32;
+// one format has to be this much smoother than the other to be sure of it
+const CONFIDENT_RATIO: f64 = 1.25;
+// too few pixels to say anything about
+const MIN_SAMPLED_PIXELS: u64 = 64;
+
+/// Decodes a sample of the frames' native pixels as both 555 and 565 and
+/// guesses which format the art was really drawn in. Read the wrong way, the
+/// green channel picks up a bit of red or loses its top bit to it, so
+/// mislabelled files show up as noisy green and magenta speckles.
+/// Returns None if the frames have no native pixels to go on.
+pub fn guess_pixel_format(frames: &[Frame]) -> Option<PixelFormatGuess> {
+	let step = (frames.len() / SAMPLE_FRAMES).max(1);
+	let mut roughness = [0_u64; 2];
+	let mut neighbor_pairs: u64 = 0;
+	let mut high_bit_pixels: u64 = 0;
+	let mut sampled_pixels: u64 = 0;
+
+	for frame in frames.iter().step_by(step) {
+		let pixels = match &frame.native_pixels {
+			Some(native_pixels) => &native_pixels.pixels,
+			None => continue
+		};
+		let width = frame.image.width() as usize;
+		if pixels.len() != width * frame.image.height() as usize {
+			continue;
+		}
+		let is_opaque = |i: usize| frame.image.as_raw()[i * 4 + 3] != 0;
+		for (i, pixel) in pixels.iter().enumerate() {
+			if !is_opaque(i) {
+				continue;
+			}
+			sampled_pixels += 1;
+			if pixel & 0x8000 != 0 {
+				high_bit_pixels += 1;
+			}
+			let right = if (i + 1) % width != 0 { Some(i + 1) } else { None };
+			let below = if i + width < pixels.len() { Some(i + width) } else { None };
+			for neighbor in [right, below].into_iter().flatten() {
+				if !is_opaque(neighbor) {
+					continue;
+				}
+				neighbor_pairs += 1;
+				for (j, pixel_format) in [PixelFormat::Format555, PixelFormat::Format565].into_iter().enumerate() {
+					roughness[j] += parse_pixel(*pixel, pixel_format)[1].abs_diff(parse_pixel(pixels[neighbor], pixel_format)[1]) as u64;
+				}
+			}
+		}
+	}
+
+	if sampled_pixels == 0 {
+		return None;
+	}
+	let roughness_555 = roughness[0] as f64 / neighbor_pairs.max(1) as f64;
+	let roughness_565 = roughness[1] as f64 / neighbor_pairs.max(1) as f64;
+
+	// 555 leaves the top bit empty, so anything more than a stray pixel or two
+	// with it set can only be 565
+	let (pixel_format, confident) = if high_bit_pixels * 200 > sampled_pixels {
+		(PixelFormat::Format565, true)
+	} else if roughness_565 <= roughness_555 {
+		(PixelFormat::Format565, roughness_555 > roughness_565 * CONFIDENT_RATIO)
+	} else {
+		(PixelFormat::Format555, roughness_565 > roughness_555 * CONFIDENT_RATIO)
+	};
+
+	Some(PixelFormatGuess {
+		pixel_format,
+		roughness_555,
+		roughness_565,
+		high_bit_pixels,
+		sampled_pixels,
+		confident: confident && sampled_pixels >= MIN_SAMPLED_PIXELS
+	})
+}
+
+/// Reads the frames' native pixels again as `pixel_format`, for files whose
+/// header has the wrong pixel format. Frames without native pixels, or that
+/// have been edited since they were opened, are left as they are.
+pub fn reinterpret_frames(frames: &[Frame], pixel_format: PixelFormat) -> Vec<Frame> {
+	frames.iter().map(|frame| {
+		let native_pixels = match &frame.native_pixels {
+			Some(native_pixels) => native_pixels,
+			None => return frame.clone()
+		};
+		let pixels = match unedited_native_pixels(frame, native_pixels.pixel_format) {
+			Some(pixels) => pixels.to_vec(),
+			None => return frame.clone()
+		};
+		let mut image = frame.image.clone();
+		for (pixel, native_pixel) in image.pixels_mut().zip(&pixels) {
+			if pixel[3] != 0 {
+				let color = parse_pixel(*native_pixel, pixel_format);
+				*pixel = Rgba([color[0], color[1], color[2], pixel[3]]);
+			}
+		}
+		Frame {
+			image,
+			color_indexes: frame.color_indexes.clone(),
+			metadata: frame.metadata.clone(),
+			native_pixels: Some(NativePixels{ pixel_format, pixels })
+		}
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{ Rgba, RgbaImage };
+
+	use super::*;
+	use crate::{
+		format::c16,
+		sprite::{ FrameMetadata, SpriteInfo }
+	};
+
+	#[test]
+	fn converted_black_stays_opaque() {
+		for (pixel_format, dark_grey) in [(PixelFormat::Format555, [8, 8, 8]), (PixelFormat::Format565, [8, 4, 8])] {
+			let frame = Frame {
+				image: RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 255])),
+				color_indexes: Vec::new(),
+				metadata: FrameMetadata::default(),
+				native_pixels: None
+			};
+			let sprite_info = SpriteInfo {
+				frames: vec![convert_frame(&frame, pixel_format, DitherMethod::None)],
+				pixel_format,
+				cols: 0,
+				rows: 0,
+				read_only: false,
+				spr_variant: None,
+				dta_variant: None
+			};
+			let decoded = c16::decode(&c16::encode(sprite_info).unwrap()).unwrap();
+			for pixel in decoded.frames[0].image.pixels() {
+				assert_eq!(*pixel, Rgba([dark_grey[0], dark_grey[1], dark_grey[2], 255]));
+			}
+		}
+	}
+}
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+
+use image::RgbaImage;
+
+use crate::format::{
+	PixelFormat,
+	ChannelExpansion,
+	spr::SprVariant,
+	dta::DtaVariant,
+	parse_pixel_with_expansion,
+	unedited_native_pixels
+};
+
+/// A decoded sprite file: its frames plus the file-level settings needed to
+/// write it back out again.
+#[derive(Clone)]
+pub struct SpriteInfo {
+	/// The images in the file, in file order.
+	pub frames: Vec<Frame>,
+	/// The 16-bit pixel layout of the file. Formats without 16-bit pixel data
+	/// (SPR, DTA, Photo Album, PNG, etc.) report `Format565`.
+	pub pixel_format: PixelFormat,
+	/// Number of tile columns. Only meaningful for BLK backgrounds, 0 otherwise.
+	pub cols: u16,
+	/// Number of tile rows. Only meaningful for BLK backgrounds, 0 otherwise.
+	pub rows: u16,
+	/// True if the file was read from a layout that can't be written back as-is.
+	pub read_only: bool,
+	/// The SPR layout the file was read from, so it can be saved the same way.
+	/// None for files that didn't come from an SPR file.
+	pub spr_variant: Option<SprVariant>,
+	/// The charset layout the file was read from. None for files that didn't
+	/// come from a DTA file.
+	pub dta_variant: Option<DtaVariant>
+}
+
+/// A single image within a sprite file.
+#[derive(Clone)]
+pub struct Frame {
+	/// The frame as RGBA pixels. Fully transparent pixels have an alpha of 0.
+	pub image: RgbaImage,
+	/// Palette indexes for each pixel, in row-major order, for frames that came
+	/// from an indexed format. Empty for frames with no indexed color data.
+	pub color_indexes: Vec<u8>,
+	/// Extra per-frame data stored by some formats (currently Photo Album).
+	pub metadata: FrameMetadata,
+	/// The frame's pixels exactly as a 16-bit format (S16, C16, BLK) stored
+	/// them, so frames that haven't been edited can be saved without any loss.
+	/// None for frames from other formats and frames made by editing.
+	pub native_pixels: Option<NativePixels>
+}
+
+impl Frame {
+	/// The frame's image with its 16-bit colors widened to 8 bits using
+	/// `expansion`. `image` always uses bit replication, so this only differs
+	/// from it for unedited frames from 16-bit formats with the legacy expansion.
+	pub fn expanded_image(&self, expansion: ChannelExpansion) -> Cow<'_, RgbaImage> {
+		if expansion == ChannelExpansion::BitReplication {
+			return Cow::Borrowed(&self.image);
+		}
+		let native_pixels = match &self.native_pixels {
+			Some(native_pixels) => native_pixels,
+			None => return Cow::Borrowed(&self.image)
+		};
+		match unedited_native_pixels(self, native_pixels.pixel_format) {
+			Some(pixels) => {
+				let mut image = self.image.clone();
+				for (pixel, native_pixel) in image.pixels_mut().zip(pixels) {
+					if pixel[3] != 0 {
+						let color = parse_pixel_with_expansion(*native_pixel, native_pixels.pixel_format, expansion);
+						pixel[0] = color[0];
+						pixel[1] = color[1];
+						pixel[2] = color[2];
+					}
+				}
+				Cow::Owned(image)
+			}
+			None => Cow::Borrowed(&self.image)
+		}
+	}
+}
+
+/// 16-bit pixel values for each pixel of a frame, in row-major order.
+/// Transparent pixels are stored as 0.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NativePixels {
+	pub pixel_format: PixelFormat,
+	pub pixels: Vec<u16>
+}
+
+/// The strings and header value a Creatures 1 Photo Album stores alongside
+/// each photo. Empty for frames from every other format.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct FrameMetadata {
+	/// When the photo was taken, as written by the game.
+	pub timestamp: String,
+	/// The caption shown under the photo.
+	pub comment: String,
+	/// The header value after the photo's width and height. Its meaning is
+	/// unknown, so it is kept as-is for round-tripping.
+	pub unknown: u32
+}
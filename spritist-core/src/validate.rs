@@ -0,0 +1,167 @@
+use std::fmt;
+
+use crate::{
+	error::SpriteError,
+	sprite::{ Frame, SpriteInfo },
+	palette::Palette,
+	codec::codec,
+	format::{ PixelFormat, SpriteFormat, MAX_FRAME_PIXELS, encode_pixel }
+};
+
+/// Something that will go wrong when a sprite is saved in a format, found
+/// before the file is written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationIssue {
+	/// More frames than the file header can count.
+	TooManyFrames { frame_count: usize, limit: usize },
+	/// A frame bigger than the format can store.
+	FrameTooLarge { frame: usize, width: u32, height: u32, max_width: u32, max_height: u32 },
+	/// A frame too big for Spritist to open again. See `format::MAX_FRAME_PIXELS`.
+	FrameTooLargeToReopen { frame: usize, width: u32, height: u32 },
+	/// A BLK tile that isn't 128 x 128.
+	WrongTileSize { frame: usize, width: u32, height: u32 },
+	/// A background whose frame count isn't its columns times its rows.
+	WrongTileCount { frame_count: usize, cols: u16, rows: u16 },
+	/// A multi-sprite SPR whose frames can't be split back into its sprites.
+	WrongSubSpriteFrameCount { frame_count: usize, expected: usize },
+	/// Opaque black pixels, which are saved as dark grey because black is
+	/// transparent in the format.
+	BlackPixels { frame_count: usize, pixel_count: u64 },
+	/// Opaque pixels whose closest palette color is index 0, which is
+	/// transparent in SPR files.
+	TransparentColorIndex { frame_count: usize, pixel_count: u64 },
+	/// A body part file without the number of frames the games expect.
+	BodyPartFrameCount { part: char, frame_count: usize, expected: usize }
+}
+
+impl ValidationIssue {
+	/// Whether the file would fail to save or come out broken, rather than
+	/// just look different.
+	pub fn is_error(&self) -> bool {
+		matches!(self,
+			ValidationIssue::TooManyFrames { .. } |
+			ValidationIssue::FrameTooLarge { .. } |
+			ValidationIssue::WrongTileSize { .. } |
+			ValidationIssue::WrongTileCount { .. } |
+			ValidationIssue::WrongSubSpriteFrameCount { .. }
+		)
+	}
+}
+
+impl fmt::Display for ValidationIssue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ValidationIssue::TooManyFrames { frame_count, limit } => write!(f, "There are {} frame(s), but the format can only store {}.", frame_count, limit),
+			ValidationIssue::FrameTooLarge { frame, width, height, max_width, max_height } => write!(f, "Frame {} is {} x {} px, but the format can only store frames up to {} x {} px.", frame, width, height, max_width, max_height),
+			ValidationIssue::FrameTooLargeToReopen { frame, width, height } => write!(f, "Frame {} is {} x {} px, which is too large for Spritist to open again.", frame, width, height),
+			ValidationIssue::WrongTileSize { frame, width, height } => write!(f, "Frame {} is {} x {} px, but all frames in a BLK file must be 128 x 128 px.", frame, width, height),
+			ValidationIssue::WrongTileCount { frame_count, cols, rows } => write!(f, "There are {} frame(s), but a {} x {} background needs {} (see View > View As Background).", frame_count, cols, rows, *cols as usize * *rows as usize),
+			ValidationIssue::WrongSubSpriteFrameCount { frame_count, expected } => write!(f, "There are {} frame(s), but the multi-sprite SPR file had {} when it was opened, so they can't be split back into its sprites.", frame_count, expected),
+			ValidationIssue::BlackPixels { frame_count, pixel_count } => write!(f, "{} pure black pixel(s) in {} frame(s) will be saved as dark grey, because black is transparent in the games.", pixel_count, frame_count),
+			ValidationIssue::TransparentColorIndex { frame_count, pixel_count } => write!(f, "{} pixel(s) in {} frame(s) will be transparent, because their closest palette color is index 0.", pixel_count, frame_count),
+			ValidationIssue::BodyPartFrameCount { part, frame_count, expected } => write!(f, "Body part \"{}\" files usually have {} frames, but this one has {}.", part, expected, frame_count)
+		}
+	}
+}
+
+/// Checks a sprite against the rules of `format` before it's saved. Body part
+/// frame counts are only checked if `file_name` looks like a body part file
+/// (like "a04a.c16").
+pub fn validate_sprite(sprite_info: &SpriteInfo, format: SpriteFormat, palette: &Palette, file_name: Option<&str>) -> Vec<ValidationIssue> {
+	let mut issues = codec(format).validate(sprite_info, palette);
+	for (i, frame) in sprite_info.frames.iter().enumerate() {
+		let (width, height) = frame.image.dimensions();
+		if width as u64 * height as u64 > MAX_FRAME_PIXELS {
+			issues.push(ValidationIssue::FrameTooLargeToReopen { frame: i, width, height });
+		}
+	}
+	if let Some(issue) = file_name.and_then(|file_name| check_body_part(sprite_info, format, file_name)) {
+		issues.push(issue);
+	}
+	issues
+}
+
+// C3 and DS body parts are named like "a04a.c16": the part, the genus and
+// sex, the life stage and the breed
+fn check_body_part(sprite_info: &SpriteInfo, format: SpriteFormat, file_name: &str) -> Option<ValidationIssue> {
+	if format != SpriteFormat::C16 {
+		return None;
+	}
+	let file_stem = file_name.to_lowercase();
+	let file_stem = file_stem.split('.').next().unwrap_or_default();
+	let chars: Vec<char> = file_stem.chars().collect();
+	if chars.len() != 4 || !chars[1].is_ascii_digit() || !chars[2].is_ascii_digit() || !chars[3].is_ascii_lowercase() {
+		return None;
+	}
+	let expected = match chars[0] {
+		// a set of poses for every expression, with eyes open and closed
+		'a' => 192,
+		'b'..='n' => 16,
+		_ => return None
+	};
+	let frame_count = sprite_info.frames.len();
+	if frame_count != expected {
+		Some(ValidationIssue::BodyPartFrameCount { part: chars[0], frame_count, expected })
+	} else {
+		None
+	}
+}
+
+/// Fails with the first issue that would stop a sprite from being written, so
+/// encoders can't write headers with truncated sizes or counts.
+pub(crate) fn check_for_errors(issues: &[ValidationIssue], format_name: &str) -> Result<(), SpriteError> {
+	match issues.iter().find(|issue| issue.is_error()) {
+		Some(issue) => Err(format!("Unable to save {}. {}", format_name, issue).into()),
+		None => Ok(())
+	}
+}
+
+pub(crate) fn check_frame_count(frames: &[Frame], limit: usize, issues: &mut Vec<ValidationIssue>) {
+	if frames.len() > limit {
+		issues.push(ValidationIssue::TooManyFrames { frame_count: frames.len(), limit });
+	}
+}
+
+pub(crate) fn check_frame_sizes(frames: &[Frame], max_width: u32, max_height: u32, issues: &mut Vec<ValidationIssue>) {
+	for (i, frame) in frames.iter().enumerate() {
+		let (width, height) = frame.image.dimensions();
+		if width > max_width || height > max_height {
+			issues.push(ValidationIssue::FrameTooLarge { frame: i, width, height, max_width, max_height });
+		}
+	}
+}
+
+pub(crate) fn check_black_pixels(frames: &[Frame], pixel_format: PixelFormat, issues: &mut Vec<ValidationIssue>) {
+	let mut frame_count = 0;
+	let mut pixel_count = 0;
+	for frame in frames {
+		let black_pixels = frame.image.pixels()
+			.filter(|pixel| pixel[3] != 0 && encode_pixel(pixel, pixel_format) == 0)
+			.count() as u64;
+		if black_pixels > 0 {
+			frame_count += 1;
+			pixel_count += black_pixels;
+		}
+	}
+	if pixel_count > 0 {
+		issues.push(ValidationIssue::BlackPixels { frame_count, pixel_count });
+	}
+}
+
+pub(crate) fn check_transparent_color_index(frames: &[Frame], color_indexes: impl Fn(&Frame) -> Vec<u8>, issues: &mut Vec<ValidationIssue>) {
+	let mut frame_count = 0;
+	let mut pixel_count = 0;
+	for frame in frames {
+		let transparent_pixels = frame.image.pixels()
+			.zip(color_indexes(frame))
+			.filter(|(pixel, color_index)| pixel[3] != 0 && *color_index == 0)
+			.count() as u64;
+		if transparent_pixels > 0 {
+			frame_count += 1;
+			pixel_count += transparent_pixels;
+		}
+	}
+	if pixel_count > 0 {
+		issues.push(ValidationIssue::TransparentColorIndex { frame_count, pixel_count });
+	}
+}
@@ -0,0 +1,86 @@
+use image::Rgba;
+
+use spritist_core::{
+	PixelFormat,
+	SpriteError,
+	SpriteInfo,
+	format::{ encode_pixel, blk, c16, s16 }
+};
+
+// Small hand-made files in both pixel formats, with pixels that re-encoding
+// would change: 555 pixels with the unused top bit set, and black in C16
+// color runs, which the encoders would otherwise save as dark grey.
+const FIXTURES: [(&str, &[u8], PixelFormat); 6] = [
+	("sprite_555.c16", include_bytes!("fixtures/sprite_555.c16"), PixelFormat::Format555),
+	("sprite_565.c16", include_bytes!("fixtures/sprite_565.c16"), PixelFormat::Format565),
+	("sprite_555.s16", include_bytes!("fixtures/sprite_555.s16"), PixelFormat::Format555),
+	("sprite_565.s16", include_bytes!("fixtures/sprite_565.s16"), PixelFormat::Format565),
+	("background_555.blk", include_bytes!("fixtures/background_555.blk"), PixelFormat::Format555),
+	("background_565.blk", include_bytes!("fixtures/background_565.blk"), PixelFormat::Format565)
+];
+
+type Decode = fn(&[u8]) -> Result<SpriteInfo, SpriteError>;
+type Encode = fn(SpriteInfo) -> Result<bytes::Bytes, SpriteError>;
+
+fn codec(name: &str) -> (Decode, Encode) {
+	match name.rsplit('.').next() {
+		Some("c16") => (c16::decode, c16::encode),
+		Some("s16") => (s16::decode, s16::encode),
+		Some("blk") => (blk::decode, blk::encode),
+		_ => unreachable!()
+	}
+}
+
+#[test]
+fn unedited_files_save_byte_for_byte() {
+	for (name, contents, pixel_format) in FIXTURES {
+		let (decode, encode) = codec(name);
+		let sprite_info = decode(contents).unwrap();
+		assert_eq!(sprite_info.pixel_format, pixel_format, "{}", name);
+		assert_eq!(encode(sprite_info).unwrap().as_ref(), contents, "{}", name);
+	}
+}
+
+// decodes a fixture, paints the second pixel of the second row of the first
+// frame white and saves it again. Returns the index of that pixel and the
+// native pixels of every frame before and after
+fn edit_first_frame(name: &str, contents: &[u8]) -> (usize, Vec<Vec<u16>>, Vec<Vec<u16>>) {
+	let (decode, encode) = codec(name);
+	let native_pixels = |sprite_info: &SpriteInfo| -> Vec<Vec<u16>> {
+		sprite_info.frames.iter().map(|frame| frame.native_pixels.clone().unwrap().pixels).collect()
+	};
+	let mut sprite_info = decode(contents).unwrap();
+	let before = native_pixels(&sprite_info);
+	let edited = sprite_info.frames[0].image.width() as usize + 1;
+	sprite_info.frames[0].image.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+	let saved = decode(&encode(sprite_info).unwrap()).unwrap();
+	(edited, before, native_pixels(&saved))
+}
+
+#[test]
+fn edited_frames_are_encoded_again() {
+	for (name, contents, pixel_format) in FIXTURES {
+		let (edited, before, after) = edit_first_frame(name, contents);
+		assert_eq!(after[0][edited], encode_pixel(&Rgba([255, 255, 255, 255]), pixel_format), "{}", name);
+		// the rest of the frame is encoded from its image, which has no room
+		// for the top bit of 555 pixels
+		if pixel_format == PixelFormat::Format555 {
+			assert!(before[0].iter().any(|pixel| pixel & 0x8000 != 0), "{}", name);
+			for (i, (old_pixel, new_pixel)) in before[0].iter().zip(&after[0]).enumerate() {
+				if i != edited {
+					assert_eq!(*new_pixel, old_pixel & 0x7fff, "{} pixel {}", name, i);
+				}
+			}
+		}
+		// frames that weren't touched are still saved as they were
+		assert_eq!(before[1..], after[1..], "{}", name);
+	}
+}
+
+#[test]
+fn edited_frames_save_black_as_dark_grey() {
+	let (_, before, after) = edit_first_frame("sprite_565.c16", include_bytes!("fixtures/sprite_565.c16"));
+	assert_eq!((before[0][2], after[0][2]), (0x0000, 0x0821));
+	assert_eq!(before[1][2], 0x0000);
+	assert_eq!(after[1][2], 0x0000);
+}
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+
+use tauri::{ AppHandle, State, Emitter };
+use tauri::async_runtime::spawn;
+
+use rfd::{ FileDialog, MessageDialog, MessageButtons };
+
+use spritist_core::batch::{ BatchTarget, batch_convert as batch_convert_files };
+
+use crate::{
+	error_dialog,
+	file::FileState,
+	palette::matching_palette
+};
+
+#[derive(Clone, serde::Serialize)]
+struct BatchFolderPayload {
+	field: String,
+	folder_path: String
+}
+
+#[tauri::command]
+pub fn get_batch_folder(file_state: State<FileState>) -> String {
+	let file_path = file_state.file_path.lock().unwrap().clone();
+	match file_path.as_ref().and_then(|file_path| file_path.parent()) {
+		Some(parent_dir) => parent_dir.to_string_lossy().to_string(),
+		None => "".to_string()
+	}
+}
+
+#[tauri::command]
+pub fn select_batch_folder(handle: AppHandle, field: String, folder_path: String) {
+	let mut folder_dialog = FileDialog::new()
+		.set_title(if field == "output" { "Select Output Folder" } else { "Select Input Folder" });
+	if !folder_path.is_empty() {
+		folder_dialog = folder_dialog.set_directory(&folder_path);
+	}
+	if let Some(folder_handle) = folder_dialog.pick_folder() {
+		handle.emit("update_batch_folder", BatchFolderPayload {
+			field,
+			folder_path: folder_handle.as_path().to_string_lossy().to_string()
+		}).unwrap();
+	}
+}
+
+#[tauri::command]
+pub fn batch_convert(handle: AppHandle, input_dir: String, output_dir: String, from_extension: String, to_target: String) {
+	let target = match BatchTarget::from_name(&to_target) {
+		Some(target) => target,
+		None => {
+			error_dialog(format!("Invalid conversion target \"{}\".", to_target));
+			return
+		}
+	};
+	let from_extension = if from_extension == "all" { None } else { Some(from_extension) };
+	let output_dir = if output_dir.is_empty() { input_dir.clone() } else { output_dir };
+
+	handle.emit("show_spinner", ()).unwrap();
+	spawn(async move {
+		let palette = matching_palette(&handle);
+		let result = batch_convert_files(&PathBuf::from(&input_dir), from_extension.as_deref(), &PathBuf::from(&output_dir), &target, &palette, None);
+		handle.emit("hide_spinner", ()).unwrap();
+		match result {
+			Ok(report) => {
+				handle.emit("successful_batch_convert", "".to_string()).unwrap();
+				handle.emit("notify", format!("Converted {} file(s), {} failed", report.converted.len(), report.failed.len())).unwrap();
+				MessageDialog::new()
+					.set_title("Batch Convert")
+					.set_description(report.summary())
+					.set_buttons(MessageButtons::Ok)
+					.show();
+			}
+			Err(why) => error_dialog(why.to_string())
+		}
+	});
+}
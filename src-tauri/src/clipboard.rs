@@ -9,8 +9,10 @@ use image::{ ImageBuffer, DynamicImage, RgbaImage };
 
 use arboard::{ Clipboard, ImageData };
 
+use spritist_core::{ Frame, FrameMetadata };
+
 use crate::{
-	file::{ FileState, Frame },
+	file::FileState,
 	selection::{ SelectionState, delete_frames },
 	history::add_state_to_history,
 	state::RedrawPayload
@@ -63,7 +65,9 @@ fn paste_from_real_clipboard(file_state: &State<FileState>, selection_state: &St
 	if let Some(image_buffer) = ImageBuffer::from_raw(image_data.width as u32, image_data.height as u32, image_data.bytes.into_owned()) {
 		let new_frame = Frame {
 			image: DynamicImage::ImageRgba8(image_buffer).into_rgba8(),
-			color_indexes: Vec::new()
+			color_indexes: Vec::new(),
+			metadata: FrameMetadata::default(),
+			native_pixels: None
 		};
 
 		let mut frames = file_state.frames.lock().unwrap();
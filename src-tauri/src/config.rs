@@ -7,11 +7,18 @@ use std::{
 use tauri::{ AppHandle, Manager, State, Emitter };
 use tauri::menu::MenuItemKind;
 
+use spritist_core::{ ChannelExpansion, color_match::ColorMetric, dither::DitherMethod };
+
+use crate::state::redraw;
+
 pub struct ConfigState {
 	pub show_image_info: Mutex<bool>,
 	pub transparent_color: Mutex<TransparentColor>,
 	pub theme: Mutex<Theme>,
-	pub show_toolbar: Mutex<bool>
+	pub show_toolbar: Mutex<bool>,
+	pub legacy_colors: Mutex<bool>,
+	pub palette_dither: Mutex<DitherMethod>,
+	pub color_metric: Mutex<ColorMetric>
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -108,6 +115,20 @@ pub fn load_config_file(handle: AppHandle) {
 									_ => set_toolbar_visibility(&handle, true, true)
 								};
 							}
+							"legacy_colors" => {
+								match value.trim() {
+									"true" => set_legacy_colors(&handle, true, true),
+									_ => set_legacy_colors(&handle, false, true)
+								};
+							}
+							"palette_dither" => {
+								let new_dither = DitherMethod::from_name(value.trim()).unwrap_or_default();
+								set_palette_dither(&handle, new_dither, true);
+							}
+							"color_metric" => {
+								let new_metric = ColorMetric::from_name(value.trim()).unwrap_or_default();
+								set_color_metric(&handle, new_metric, true);
+							}
 							_ => {}
 						}
 					}
@@ -123,11 +144,14 @@ pub fn save_config_file(handle: &AppHandle) {
 		let config_file_path = config_dir.join("spritist.conf");
 		if let Ok(()) = fs::create_dir_all(config_dir) {
 			fs::write(config_file_path, format!(
-				"show_image_info: {}\ntransparent_color: {}\ntheme: {}\nshow_toolbar: {}",
+				"show_image_info: {}\ntransparent_color: {}\ntheme: {}\nshow_toolbar: {}\nlegacy_colors: {}\npalette_dither: {}\ncolor_metric: {}",
 				config_state.show_image_info.lock().unwrap(),
 				config_state.transparent_color.lock().unwrap(),
 				config_state.theme.lock().unwrap(),
-				config_state.show_toolbar.lock().unwrap()
+				config_state.show_toolbar.lock().unwrap(),
+				config_state.legacy_colors.lock().unwrap(),
+				config_state.palette_dither.lock().unwrap().name(),
+				config_state.color_metric.lock().unwrap().name()
 			)).unwrap();
 		}
 	}
@@ -222,3 +246,69 @@ pub fn set_toolbar_visibility(handle: &AppHandle, show_toolbar: bool, init: bool
 	*config_state.show_toolbar.lock().unwrap() = show_toolbar;
 	if !init { save_config_file(handle); }
 }
+
+pub fn set_legacy_colors(handle: &AppHandle, legacy_colors: bool, init: bool) {
+	if let Some(menu) = handle.menu() {
+		if let Some(MenuItemKind::Submenu(view_menu)) = menu.get("view") {
+			if let Some(MenuItemKind::Check(menu_item)) = view_menu.get("legacy_colors") {
+				menu_item.set_checked(legacy_colors).unwrap();
+			};
+		}
+	}
+
+	let config_state: State<ConfigState> = handle.state();
+	*config_state.legacy_colors.lock().unwrap() = legacy_colors;
+	if !init {
+		save_config_file(handle);
+		redraw(handle);
+	}
+}
+
+pub fn set_palette_dither(handle: &AppHandle, new_dither: DitherMethod, init: bool) {
+	if let Some(menu) = handle.menu() {
+		if let Some(MenuItemKind::Submenu(view_menu)) = menu.get("view") {
+			if let Some(MenuItemKind::Submenu(spr_palette_menu)) = view_menu.get("spr_palette") {
+				if let Some(MenuItemKind::Submenu(dither_menu)) = spr_palette_menu.get("palette_dither") {
+					for dither in DitherMethod::ALL {
+						if let Some(MenuItemKind::Check(menu_item)) = dither_menu.get(&format!("palette_dither_{}", dither.name())) {
+							menu_item.set_checked(new_dither == dither).unwrap();
+						};
+					}
+				}
+			}
+		}
+	}
+
+	let config_state: State<ConfigState> = handle.state();
+	*config_state.palette_dither.lock().unwrap() = new_dither;
+	if !init { save_config_file(handle); }
+}
+
+pub fn set_color_metric(handle: &AppHandle, new_metric: ColorMetric, init: bool) {
+	if let Some(menu) = handle.menu() {
+		if let Some(MenuItemKind::Submenu(view_menu)) = menu.get("view") {
+			if let Some(MenuItemKind::Submenu(spr_palette_menu)) = view_menu.get("spr_palette") {
+				if let Some(MenuItemKind::Submenu(color_metric_menu)) = spr_palette_menu.get("color_metric") {
+					for metric in ColorMetric::ALL {
+						if let Some(MenuItemKind::Check(menu_item)) = color_metric_menu.get(&format!("color_metric_{}", metric.name())) {
+							menu_item.set_checked(new_metric == metric).unwrap();
+						};
+					}
+				}
+			}
+		}
+	}
+
+	let config_state: State<ConfigState> = handle.state();
+	*config_state.color_metric.lock().unwrap() = new_metric;
+	if !init { save_config_file(handle); }
+}
+
+/// How frames from 16-bit formats should be shown and exported.
+pub fn channel_expansion(config_state: &ConfigState) -> ChannelExpansion {
+	if *config_state.legacy_colors.lock().unwrap() {
+		ChannelExpansion::Legacy
+	} else {
+		ChannelExpansion::BitReplication
+	}
+}
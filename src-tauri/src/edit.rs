@@ -1,11 +1,22 @@
 use image::RgbaImage;
 
-use tauri::{ AppHandle, State, Emitter };
+use tauri::{ AppHandle, Manager, State, Emitter };
+
+use rfd::{ MessageDialog, MessageButtons, MessageDialogResult };
+
+use spritist_core::{
+	Frame,
+	PixelFormat,
+	dither::DitherMethod,
+	pixel_format::{ convert_frames, conversion_loss, guess_pixel_format, reinterpret_frames }
+};
 
 use crate::{
-	file::{ FileState, Frame },
+	error_dialog,
+	file::{ FileState, set_pixel_format, update_pixel_format_menu_items },
 	selection::SelectionState,
-	history::add_state_to_history
+	history::add_state_to_history,
+	state::redraw
 };
 
 #[tauri::command]
@@ -56,6 +67,159 @@ fn shift_pixels(frame: &Frame, x_shift:i32, y_shift:i32) -> Frame {
 
 	Frame {
 		image: new_img,
-		color_indexes: new_indexes
+		color_indexes: new_indexes,
+		metadata: frame.metadata.clone(),
+		native_pixels: None
+	}
+}
+
+#[derive(Clone, serde::Serialize)]
+struct FrameDetailsPayload {
+	frame_index: usize,
+	timestamp: String,
+	comment: String
+}
+
+pub fn activate_frame_details(handle: AppHandle, file_state: State<FileState>, selection_state: State<SelectionState>) {
+	let frame_index = selection_state.selected_frames.lock().unwrap().iter().min().copied();
+	let frame = frame_index.and_then(|i| file_state.frames.lock().unwrap().get(i).cloned());
+	match (frame_index, frame) {
+		(Some(frame_index), Some(frame)) => {
+			handle.emit("frame_details", FrameDetailsPayload {
+				frame_index,
+				timestamp: frame.metadata.timestamp,
+				comment: frame.metadata.comment
+			}).unwrap();
+		}
+		_ => error_dialog("Select a frame to edit its details.".to_string())
+	}
+}
+
+#[tauri::command]
+pub fn set_frame_details(handle: AppHandle, file_state: State<FileState>, frame_index: usize, timestamp: String, comment: String) {
+	if frame_index >= file_state.frames.lock().unwrap().len() {
+		return
+	}
+	add_state_to_history(&handle);
+	let mut frames = file_state.frames.lock().unwrap();
+	frames[frame_index].metadata.timestamp = timestamp;
+	frames[frame_index].metadata.comment = comment;
+	handle.emit("successful_frame_details", ()).unwrap();
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct PixelFormatConversionPayload {
+	pixel_format: String,
+	changed_pixels: u64,
+	total_pixels: u64,
+	max_difference: u8
+}
+
+pub fn pixel_format_name(pixel_format: PixelFormat) -> String {
+	match pixel_format {
+		PixelFormat::Format555 => "555".to_string(),
+		PixelFormat::Format565 => "565".to_string()
+	}
+}
+
+fn parse_pixel_format_name(name: &str) -> Option<PixelFormat> {
+	match name {
+		"555" => Some(PixelFormat::Format555),
+		"565" => Some(PixelFormat::Format565),
+		_ => None
 	}
 }
+
+fn preview_conversion(file_state: &State<FileState>, pixel_format: PixelFormat, dither: DitherMethod) -> PixelFormatConversionPayload {
+	let frames = file_state.frames.lock().unwrap();
+	let loss = conversion_loss(&frames, &convert_frames(&frames, pixel_format, dither));
+	PixelFormatConversionPayload {
+		pixel_format: pixel_format_name(pixel_format),
+		changed_pixels: loss.changed_pixels,
+		total_pixels: loss.total_pixels,
+		max_difference: loss.max_difference
+	}
+}
+
+pub fn activate_convert_pixel_format(handle: &AppHandle, pixel_format: PixelFormat) {
+	// the menu item checks itself when clicked, so put it back until the conversion is confirmed
+	update_pixel_format_menu_items(handle);
+	let file_state: State<FileState> = handle.state();
+	if *file_state.pixel_format.lock().unwrap() == pixel_format {
+		return
+	}
+	if file_state.frames.lock().unwrap().is_empty() {
+		set_pixel_format(handle, pixel_format);
+		return
+	}
+	handle.emit("convert_pixel_format", preview_conversion(&file_state, pixel_format, DitherMethod::None)).unwrap();
+}
+
+#[tauri::command]
+pub fn preview_pixel_format_conversion(file_state: State<FileState>, pixel_format: String, dither: String) -> Option<PixelFormatConversionPayload> {
+	let dither = DitherMethod::from_name(&dither).unwrap_or_default();
+	parse_pixel_format_name(&pixel_format).map(|pixel_format| preview_conversion(&file_state, pixel_format, dither))
+}
+
+#[tauri::command]
+pub fn convert_pixel_format(handle: AppHandle, file_state: State<FileState>, pixel_format: String, dither: String) {
+	let dither = DitherMethod::from_name(&dither).unwrap_or_default();
+	let pixel_format = match parse_pixel_format_name(&pixel_format) {
+		Some(pixel_format) => pixel_format,
+		None => return
+	};
+	add_state_to_history(&handle);
+	let converted_frames = convert_frames(&file_state.frames.lock().unwrap(), pixel_format, dither);
+	*file_state.frames.lock().unwrap() = converted_frames;
+	set_pixel_format(&handle, pixel_format);
+	redraw(&handle);
+	handle.emit("successful_pixel_format_conversion", ()).unwrap();
+}
+
+/// Checks whether the open file's colors fit the pixel format in its header,
+/// and offers to read the file as the other format if they clearly don't.
+/// With `report`, also says so when there's nothing to fix.
+pub fn check_pixel_format(handle: &AppHandle, report: bool) {
+	let file_state: State<FileState> = handle.state();
+	let pixel_format = *file_state.pixel_format.lock().unwrap();
+	let guess = guess_pixel_format(&file_state.frames.lock().unwrap());
+	match guess {
+		Some(guess) if guess.confident && guess.pixel_format != pixel_format => {
+			let confirm_reinterpret = MessageDialog::new()
+				.set_title("Pixel Format")
+				.set_description(format!("This file's header says its pixel format is {}, but its colors look like {}. Files with the wrong pixel format have a green or magenta cast.\n\nDo you want to read it as {} instead?",
+					pixel_format_name(pixel_format),
+					pixel_format_name(guess.pixel_format),
+					pixel_format_name(guess.pixel_format)))
+				.set_buttons(MessageButtons::YesNo)
+				.show();
+			if let MessageDialogResult::Yes = confirm_reinterpret {
+				reinterpret_pixel_format(handle, guess.pixel_format);
+			}
+		}
+		Some(guess) if report => {
+			let description = if guess.confident {
+				format!("This file's colors look right for its pixel format ({}).", pixel_format_name(pixel_format))
+			} else {
+				format!("This file's colors don't clearly fit either pixel format, so it's been left as {}.", pixel_format_name(pixel_format))
+			};
+			MessageDialog::new()
+				.set_title("Pixel Format")
+				.set_description(description)
+				.set_buttons(MessageButtons::Ok)
+				.show();
+		}
+		None if report => error_dialog("Only unedited frames from C16, S16 and BLK files can be checked.".to_string()),
+		_ => {}
+	}
+}
+
+fn reinterpret_pixel_format(handle: &AppHandle, pixel_format: PixelFormat) {
+	let file_state: State<FileState> = handle.state();
+	add_state_to_history(handle);
+	let reinterpreted_frames = reinterpret_frames(&file_state.frames.lock().unwrap(), pixel_format);
+	*file_state.frames.lock().unwrap() = reinterpreted_frames;
+	set_pixel_format(handle, pixel_format);
+	redraw(handle);
+	handle.emit("notify", format!("Reading file as pixel format {}", pixel_format_name(pixel_format))).unwrap();
+}
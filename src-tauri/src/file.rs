@@ -1,9 +1,7 @@
 use std::{
-	fs,
 	error::Error,
 	path::{ Path, PathBuf },
-	sync::Mutex,
-	io::Cursor
+	sync::Mutex
 };
 
 use tauri::{ AppHandle, State, Manager, Emitter };
@@ -12,16 +10,20 @@ use tauri::menu::MenuItemKind;
 
 use rfd::{ FileDialog, MessageDialog, MessageButtons, MessageDialogResult };
 
-use image::{
-	RgbaImage,
-	AnimationDecoder,
-	ImageReader,
-	ImageFormat,
-	codecs::gif::GifDecoder
+use spritist_core::{
+	Frame,
+	SpriteInfo,
+	palette,
+	format::{ PixelFormat, SpriteFormat, spr::SprVariant, dta::DtaVariant },
+	SpriteError,
+	codec::dialog_extensions,
+	validate::{ ValidationIssue, validate_sprite },
+	file::{ read_sprite_file, read_sprite_file_with_format, read_sprite_file_salvaged, write_sprite_file, write_sprite_file_as }
 };
 
 use crate::{
 	error_dialog,
+	file_error_dialog,
 	state::{
 		RedrawPayload,
 		reset_state,
@@ -30,22 +32,12 @@ use crate::{
 	},
 	selection::SelectionState,
 	history::add_state_to_history,
-	format::{
-		PixelFormat,
-		spr,
-		s16,
-		m16,
-		c16,
-		blk,
-		dta,
-		photo_album,
-		black_to_transparent
-	},
+	edit::check_pixel_format,
+	palette::matching_palette,
 	view::{
 		view_as_sprite,
 		view_as_bg
-	},
-	palette
+	}
 };
 
 pub struct FileState {
@@ -58,7 +50,10 @@ pub struct FileState {
 	pub pixel_format: Mutex<PixelFormat>,
 	pub cols: Mutex<usize>,
 	pub rows: Mutex<usize>,
-	pub read_only: Mutex<bool>
+	pub read_only: Mutex<bool>,
+	pub spr_variant: Mutex<Option<SprVariant>>,
+	pub dta_variant: Mutex<Option<DtaVariant>>,
+	pub file_format: Mutex<Option<SpriteFormat>>
 }
 
 pub struct FileModifiedCallback {
@@ -77,30 +72,20 @@ impl FileState {
 			pixel_format: Mutex::new(PixelFormat::Format565),
 			cols: Mutex::new(0),
 			rows: Mutex::new(0),
-			read_only: Mutex::new(false)
+			read_only: Mutex::new(false),
+			spr_variant: Mutex::new(None),
+			dta_variant: Mutex::new(None),
+			file_format: Mutex::new(None)
 		}
 	}
 }
 
-pub struct SpriteInfo {
-	pub frames: Vec<Frame>,
-	pub pixel_format: PixelFormat,
-	pub cols: u16,
-	pub rows: u16,
-	pub read_only: bool
-}
-
-#[derive(Clone)]
-pub struct Frame {
-	pub image: RgbaImage,
-	pub color_indexes: Vec<u8>
-}
-
 pub fn create_open_dialog(handle: &AppHandle, use_default_filter: bool) -> FileDialog {
 	let mut file_dialog = FileDialog::new();
 
 	if use_default_filter {
-		file_dialog = file_dialog.add_filter("Sprites", &["spr", "SPR", "s16", "S16", "c16", "C16", "m16", "M16", "n16", "N16", "blk", "BLK", "dta", "DTA", "photo album", "Photo Album", "png", "PNG", "gif", "GIF", "bmp", "BMP"]);
+		file_dialog = file_dialog.add_filter("Sprites", &dialog_extensions(|_| true));
+		file_dialog = file_dialog.add_filter("All Files", &["*"]);
 	}
 
 	let file_state: State<FileState> = handle.state();
@@ -198,8 +183,8 @@ pub fn open_file_dialog(handle: AppHandle) {
 	if let Some(file_handle) = file_handle {
 		handle.emit("show_spinner", ()).unwrap();
 		spawn(async move {
-			if let Err(why) = open_file_from_path(&handle, file_handle.as_path()) {
-				error_dialog(why.to_string());
+			if let Err(why) = open_or_salvage_file_from_path(&handle, file_handle.as_path()) {
+				file_error_dialog(&handle, why);
 			};
 			handle.emit("hide_spinner", ()).unwrap();
 		});
@@ -207,8 +192,60 @@ pub fn open_file_dialog(handle: AppHandle) {
 }
 
 pub fn open_file_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
-	let sprite_info = get_sprite_info(handle, file_path)?;
+	let (sprite_info, file_format) = get_sprite_info_with_format(handle, file_path)?;
+	load_sprite(handle, file_path, sprite_info, file_format, Vec::new());
+	check_pixel_format(handle, false);
+	Ok(())
+}
+
+// offers to salvage files that can only be read in part
+pub fn open_or_salvage_file_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
+	let why = match open_file_from_path(handle, file_path) {
+		Ok(()) => return Ok(()),
+		Err(why) => why
+	};
+	if !matches!(why.downcast_ref::<SpriteError>(), Some(SpriteError { frame: Some(_), .. })) {
+		return Err(why);
+	}
+	let confirm_salvage = MessageDialog::new()
+		.set_title("Damaged File")
+		.set_description(format!("{}\n\nDo you want to open the frames that can still be read? The rest will be replaced with empty frames.", why))
+		.set_buttons(MessageButtons::YesNo)
+		.show();
+	if let MessageDialogResult::Yes = confirm_salvage {
+		salvage_file_from_path(handle, file_path)?;
+	}
+	Ok(())
+}
+
+pub fn salvage_file_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
+	let file_state: State<FileState> = handle.state();
+	let palette = file_state.palette.lock().unwrap().clone();
+	let (sprite_info, file_format, lost_frames) = read_sprite_file_salvaged(file_path, &palette)?;
+	let frame_count = sprite_info.frames.len();
+	let lost_frame_indexes: Vec<usize> = lost_frames.iter().filter_map(|why| why.frame).collect();
+	load_sprite(handle, file_path, sprite_info, file_format, lost_frame_indexes);
+
+	if !lost_frames.is_empty() {
+		// the placeholder frames haven't been saved yet
+		*file_state.file_is_modified.lock().unwrap() = true;
+		update_window_title(handle);
 
+		handle.emit("notify", format!("Recovered {} of {} frames", frame_count - lost_frames.len(), frame_count)).unwrap();
+		let mut lost_frame_list: Vec<String> = lost_frames.iter().take(20).map(|why| why.to_string()).collect();
+		if lost_frames.len() > 20 {
+			lost_frame_list.push(format!("...and {} more", lost_frames.len() - 20));
+		}
+		MessageDialog::new()
+			.set_title("Damaged File")
+			.set_description(format!("{} frame(s) couldn't be read and were replaced with empty frames, which are now selected.\n\n{}", lost_frames.len(), lost_frame_list.join("\n")))
+			.set_buttons(MessageButtons::Ok)
+			.show();
+	}
+	Ok(())
+}
+
+fn load_sprite(handle: &AppHandle, file_path: &Path, sprite_info: SpriteInfo, file_format: SpriteFormat, selected_frames: Vec<usize>) {
 	reset_state(handle);
 	let file_state: State<FileState> = handle.state();
 	if let Some(file_title) = file_path.file_name() {
@@ -219,17 +256,15 @@ pub fn open_file_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), B
 	*file_state.file_path.lock().unwrap() = Some(file_path.to_owned());
 	*file_state.file_is_open.lock().unwrap() = true;
 	*file_state.read_only.lock().unwrap() = sprite_info.read_only;
+	*file_state.spr_variant.lock().unwrap() = sprite_info.spr_variant;
+	*file_state.dta_variant.lock().unwrap() = sprite_info.dta_variant;
+	*file_state.file_format.lock().unwrap() = Some(file_format);
 	*file_state.frames.lock().unwrap() = sprite_info.frames;
 	*file_state.pixel_format.lock().unwrap() = sprite_info.pixel_format;
 	*file_state.cols.lock().unwrap() = sprite_info.cols.into();
 	*file_state.rows.lock().unwrap() = sprite_info.rows.into();
 
-	let mut is_background = false;
-	if let Some(extension) = file_path.extension() {
-		if extension.to_string_lossy().to_lowercase() == "blk" {
-			is_background = true;
-		}
-	}
+	let mut is_background = file_format == SpriteFormat::Blk;
 	if let Some(file_name) = file_path.file_name() {
 		match file_name.to_string_lossy().to_lowercase().as_str() {
 			"back.spr" => { // C1 background
@@ -254,14 +289,21 @@ pub fn open_file_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), B
 	update_window_title(handle);
 	update_pixel_format_menu_items(handle);
 
+	// let the user know when the file isn't what its extension suggests
+	let extension = file_path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+	if extension.as_deref() != Some(file_format.extension()) || (file_format.is_spr() && file_format != SpriteFormat::Spr) {
+		handle.emit("notify", format!("Opened as {}", file_format.name())).unwrap();
+	}
+
+	let selection_state: State<SelectionState> = handle.state();
+	*selection_state.selected_frames.lock().unwrap() = selected_frames.clone();
+
 	handle.emit("redraw", RedrawPayload{
 		frame_count: file_state.frames.lock().unwrap().len(),
-		selected_frames: Vec::new(),
+		selected_frames,
 		cols: *file_state.cols.lock().unwrap(),
 		rows: *file_state.rows.lock().unwrap(),
 	}).unwrap();
-
-	Ok(())
 }
 
 pub fn drop_files(handle: &AppHandle, file_paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
@@ -278,7 +320,7 @@ pub fn drop_files(handle: &AppHandle, file_paths: &[PathBuf]) -> Result<(), Box<
 			insert_image_from_path(handle, file_path)?;
 		}
 	} else if file_paths.len() == 1 {
-		open_file_from_path(handle, &file_paths[0])?;
+		open_or_salvage_file_from_path(handle, &file_paths[0])?;
 	}
 	Ok(())
 }
@@ -293,7 +335,7 @@ pub fn activate_insert_image(handle: AppHandle) {
 		spawn(async move {
 			for file_handle in file_handles {
 				if let Err(why) =  insert_image_from_path(&handle, file_handle.as_path()) {
-					error_dialog(why.to_string());
+					file_error_dialog(&handle, why);
 				};
 			}
 			handle.emit("hide_spinner", ()).unwrap();
@@ -350,7 +392,7 @@ pub fn activate_replace_frame(handle: AppHandle, selection_state: State<Selectio
 			spawn(async move {
 				let path = file_handle.as_path().to_path_buf();
 				if let Err(why) =  replace_frame_from_path(&handle, &path) {
-					error_dialog(why.to_string());
+					file_error_dialog(&handle, why);
 				};
 				handle.emit("hide_spinner", ()).unwrap();
 			});
@@ -394,93 +436,15 @@ pub fn replace_frame_from_path(handle: &AppHandle, file_path: &Path) -> Result<(
 }
 
 pub fn get_sprite_info(handle: &AppHandle, file_path: &Path) -> Result<SpriteInfo, Box<dyn Error>> {
-	let bytes = fs::read(file_path)?;
-
 	let file_state: State<FileState> = handle.state();
 	let palette = file_state.palette.lock().unwrap();
+	read_sprite_file(file_path, &palette)
+}
 
-	let extension_err = "File does not have a valid file extension (\".spr\", \".s16\", \".c16\", \".blk\", etc.)";
-	let extension = file_path.extension().ok_or(extension_err)?;
-	let extension_str = extension.to_str().ok_or(extension_err)?;
-	match extension_str.to_lowercase().as_str() {
-		"spr" => {
-			// try regular SPR
-			match spr::decode(&bytes, &palette) {
-				Ok(result) => Ok(result),
-				Err(_) => {
-					// try single-width SPR
-					match spr::decode_single_width(&bytes, &palette) {
-						Ok(result) => Ok(result),
-						Err(_) => {
-							// try double-width SPR
-							match spr::decode_double_width(&bytes, &palette) {
-								Ok(result) => Ok(result),
-								Err(_) => {
-									// try multi-sprite SPR
-									match spr::decode_multi_sprite(&bytes, &palette) {
-										Ok(result) => Ok(result),
-										Err(_) => {
-											// try prototype SPR
-											spr::decode_prototype(&bytes, &palette)
-										}
-									}
-								}
-							}
-						}
-					}
-				}
-			}
-		},
-		"s16" => s16::decode(&bytes),
-		"c16" => c16::decode(&bytes),
-		"m16" => m16::decode(&bytes),
-		"n16" => m16::decode(&bytes),
-		"blk" => blk::decode(&bytes),
-		"dta" => dta::decode(&bytes),
-		"photo album" => {
-			photo_album::decode(&bytes, &palette)
-		},
-		"png" => {
-			let image = ImageReader::with_format(Cursor::new(bytes), ImageFormat::Png).decode()?.to_rgba8();
-			let frame = Frame { image, color_indexes: Vec::new() };
-			Ok(SpriteInfo {
-				frames: vec![frame],
-				pixel_format: PixelFormat::Format565,
-				cols: 0,
-				rows: 0,
-				read_only: true
-			})
-		},
-		"bmp" => {
-			let image = black_to_transparent(
-				ImageReader::with_format(Cursor::new(bytes), ImageFormat::Bmp).decode()?.to_rgba8()
-			);
-			let frame = Frame { image, color_indexes: Vec::new() };
-			Ok(SpriteInfo {
-				frames: vec![frame],
-				pixel_format: PixelFormat::Format565,
-				cols: 0,
-				rows: 0,
-				read_only: true
-			})
-		},
-		"gif" => {
-			let gif_frames = GifDecoder::new(Cursor::new(bytes))?.into_frames();
-			let mut frames: Vec<Frame> = Vec::new();
-			for gif_frame in gif_frames {
-				let image = gif_frame?.into_buffer();
-				frames.push(Frame { image, color_indexes: Vec::new() });
-			}
-			Ok(SpriteInfo {
-				frames,
-				pixel_format: PixelFormat::Format565,
-				cols: 0,
-				rows: 0,
-				read_only: true
-			})
-		},
-		_ => Err(extension_err.into())
-	}
+pub fn get_sprite_info_with_format(handle: &AppHandle, file_path: &Path) -> Result<(SpriteInfo, SpriteFormat), Box<dyn Error>> {
+	let file_state: State<FileState> = handle.state();
+	let palette = file_state.palette.lock().unwrap();
+	read_sprite_file_with_format(file_path, &palette)
 }
 
 #[tauri::command]
@@ -491,17 +455,16 @@ pub fn activate_save_file(handle: AppHandle, file_state: State<FileState>) {
 			if !*file_state.read_only.lock().unwrap() {
 				handle.emit("show_spinner", ()).unwrap();
 				if let Err(why) = save_file_to_path(&handle, &file_path) {
-					error_dialog(why.to_string());
+					file_error_dialog(&handle, why);
 				}
 				handle.emit("hide_spinner", ()).unwrap();
-			} else if file_path.ends_with(".png") || file_path.ends_with(".PNG") {
-				error_dialog("Use Export PNG or Export Spritesheet instead.".to_string());
-			} else if file_path.ends_with(".gif") || file_path.ends_with(".GIF") {
-				error_dialog("Use Export GIF instead.".to_string());
-			} else if file_path.ends_with(".spr") || file_path.ends_with(".SPR") {
-				error_dialog("File is in a SPR format Spritist can read but not write. Use Save As to save a copy in the standard SPR format.".to_string());
 			} else {
-				error_dialog("File is read-only. Use Save As or Export instead.".to_string());
+				let extension = file_path.extension().and_then(|extension| extension.to_str());
+				match extension.and_then(SpriteFormat::from_extension) {
+					Some(SpriteFormat::Png) => error_dialog("Use Export PNG or Export Spritesheet instead.".to_string()),
+					Some(SpriteFormat::Gif) => error_dialog("Use Export GIF instead.".to_string()),
+					_ => error_dialog("File is read-only. Use Save As or Export instead.".to_string())
+				}
 			}
 		}
 		_ => {
@@ -514,45 +477,81 @@ pub fn activate_save_file(handle: AppHandle, file_state: State<FileState>) {
 pub fn activate_save_as(handle: AppHandle) {
 	let file_handle = create_save_dialog(&handle, None, None)
 		.set_title("Save As")
-		.add_filter("Sprites", &["spr", "SPR", "s16", "S16", "c16", "C16", "m16", "M16", "n16", "N16", "blk", "BLK", "dta", "DTA", "photo album", "Photo Album", "PHOTO ALBUM"])
+		.add_filter("Sprites", &dialog_extensions(|codec| !codec.capabilities().read_only))
 		.save_file();
 	if let Some(file_handle) = file_handle {
 		handle.emit("show_spinner", ()).unwrap();
 		spawn(async move {
 			if let Err(why) = save_file_to_path(&handle, file_handle.as_path()) {
-				error_dialog(why.to_string());
+				file_error_dialog(&handle, why);
 			}
 			handle.emit("hide_spinner", ()).unwrap();
 		});
 	}
 }
 
-pub fn save_file_to_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
-	let extension_err = "File does not have a valid file extension (\".spr\", \".s16\", \".c16\", \".blk\")";
-	let extension = file_path.extension().ok_or(extension_err)?;
-	let extension_str = extension.to_str().ok_or(extension_err)?;
-
-	let file_state: State<FileState> = handle.state();
-	let palette = file_state.palette.lock().unwrap().clone();
-	let sprite_info = SpriteInfo{
+fn current_sprite_info(file_state: &State<FileState>) -> SpriteInfo {
+	SpriteInfo{
 		frames: file_state.frames.lock().unwrap().clone(),
 		pixel_format: *file_state.pixel_format.lock().unwrap(),
 		cols: *file_state.cols.lock().unwrap() as u16,
 		rows: *file_state.rows.lock().unwrap() as u16,
-		read_only: false
-	};
+		read_only: false,
+		spr_variant: file_state.spr_variant.lock().unwrap().clone(),
+		dta_variant: *file_state.dta_variant.lock().unwrap()
+	}
+}
 
-	let data = match extension_str {
-		"spr" => Some(spr::encode(sprite_info, &palette)?),
-		"s16" => Some(s16::encode(sprite_info)?),
-		"c16" => Some(c16::encode(sprite_info)?),
-		"m16" => Some(m16::encode(sprite_info)?),
-		"n16" => Some(m16::encode(sprite_info)?),
-		"blk" => Some(blk::encode(sprite_info)?),
-		_ => None
-	}.ok_or(extension_err)?;
+fn issue_list(issues: &[&ValidationIssue]) -> String {
+	issues.iter().map(|issue| format!("- {}", issue)).collect::<Vec<String>>().join("\n")
+}
 
-	fs::write(file_path, &data)?;
+// shows what validation found, and returns whether to save anyway
+fn confirm_validation_issues(issues: &[ValidationIssue], format: SpriteFormat) -> bool {
+	let errors: Vec<&ValidationIssue> = issues.iter().filter(|issue| issue.is_error()).collect();
+	let warnings: Vec<&ValidationIssue> = issues.iter().filter(|issue| !issue.is_error()).collect();
+	if !errors.is_empty() {
+		error_dialog(format!("Unable to save as {}.\n\n{}", format.name(), issue_list(&errors)));
+		return false;
+	}
+	if warnings.is_empty() {
+		return true;
+	}
+	let confirm_save = MessageDialog::new()
+		.set_title("Save File")
+		.set_description(format!("{}\n\nSave anyway?", issue_list(&warnings)))
+		.set_buttons(MessageButtons::YesNo)
+		.show();
+	matches!(confirm_save, MessageDialogResult::Yes)
+}
+
+pub fn save_file_to_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
+	let file_state: State<FileState> = handle.state();
+	let palette = matching_palette(handle);
+	let sprite_info = current_sprite_info(&file_state);
+
+	// keep saving renamed files in the format they were opened as
+	let file_format = *file_state.file_format.lock().unwrap();
+	let is_current_file = file_state.file_path.lock().unwrap().as_deref() == Some(file_path);
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	let save_format = match file_format {
+		Some(file_format) if is_current_file => Some(file_format),
+		_ => extension.and_then(SpriteFormat::from_extension)
+	};
+	if let Some(save_format) = save_format {
+		let file_name = file_path.file_name().and_then(|file_name| file_name.to_str());
+		if !confirm_validation_issues(&validate_sprite(&sprite_info, save_format, &palette, file_name), save_format) {
+			return Ok(());
+		}
+	}
+
+	match file_format {
+		Some(file_format) if is_current_file => write_sprite_file_as(file_path, sprite_info, file_format, &palette)?,
+		_ => {
+			write_sprite_file(file_path, sprite_info, &palette)?;
+			*file_state.file_format.lock().unwrap() = save_format;
+		}
+	}
 
 	if let Some(file_title) = file_path.file_name() {
 		if let Some(file_title_str) = file_title.to_str() {
@@ -568,6 +567,39 @@ pub fn save_file_to_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box
 	Ok(())
 }
 
+pub fn validate_file(handle: &AppHandle) {
+	let file_state: State<FileState> = handle.state();
+	let file_path = file_state.file_path.lock().unwrap().clone();
+	let format = file_state.file_format.lock().unwrap().or_else(|| {
+		file_path.as_ref()
+			.and_then(|file_path| file_path.extension())
+			.and_then(|extension| extension.to_str())
+			.and_then(SpriteFormat::from_extension)
+	});
+	let format = match format {
+		Some(format) => format,
+		None => {
+			error_dialog("Save the file first, so Spritist knows which format to check it against.".to_string());
+			return
+		}
+	};
+	let palette = matching_palette(handle);
+	let file_name = file_path.as_ref()
+		.and_then(|file_path| file_path.file_name())
+		.map(|file_name| file_name.to_string_lossy().to_string());
+	let issues = validate_sprite(&current_sprite_info(&file_state), format, &palette, file_name.as_deref());
+	let description = if issues.is_empty() {
+		format!("No problems found for saving as {}.", format.name())
+	} else {
+		issue_list(&issues.iter().collect::<Vec<&ValidationIssue>>())
+	};
+	MessageDialog::new()
+		.set_title("Validate File")
+		.set_description(description)
+		.set_buttons(MessageButtons::Ok)
+		.show();
+}
+
 #[tauri::command]
 pub fn set_bg_size(file_state: State<FileState>, cols: usize, rows: usize) {
 	*file_state.cols.lock().unwrap() = cols;
@@ -580,7 +612,7 @@ pub fn set_pixel_format(handle: &AppHandle, new_pixel_format: PixelFormat) {
 	update_pixel_format_menu_items(handle);
 }
 
-fn update_pixel_format_menu_items(handle: &AppHandle) {
+pub fn update_pixel_format_menu_items(handle: &AppHandle) {
 	let file_state: State<FileState> = handle.state();
 	let pixel_format = *file_state.pixel_format.lock().unwrap();
 	if let Some(menu) = handle.menu() {
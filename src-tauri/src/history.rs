@@ -2,11 +2,13 @@ use std::sync::Mutex;
 
 use tauri::{ AppHandle, Manager, State };
 
+use spritist_core::{ Frame, Palette, PixelFormat };
+
 use crate::{
-	palette::Palette,
-	file::{ FileState, Frame },
+	file::{ FileState, update_pixel_format_menu_items },
 	selection::SelectionState,
 	state::{ redraw, update_window_title },
+	palette::{ emit_palette, update_palette_menu_items }
 };
 
 pub struct HistoryState {
@@ -17,6 +19,7 @@ pub struct HistoryState {
 pub struct HistoryItem {
 	pub frames: Mutex<Vec<Frame>>,
 	pub palette: Mutex<Palette>,
+	pub pixel_format: Mutex<PixelFormat>,
 	pub selected_frames: Mutex<Vec<usize>>
 }
 
@@ -42,8 +45,11 @@ pub fn undo(handle: AppHandle, file_state: State<FileState>, selection_state: St
 	}
 
 	update_window_title(&handle);
+	update_pixel_format_menu_items(&handle);
+	update_palette_menu_items(&handle);
 
 	redraw(&handle);
+	emit_palette(&handle);
 }
 
 #[tauri::command]
@@ -55,14 +61,18 @@ pub fn redo(handle: AppHandle, file_state: State<FileState>, selection_state: St
 	}
 
 	update_window_title(&handle);
+	update_pixel_format_menu_items(&handle);
+	update_palette_menu_items(&handle);
 
 	redraw(&handle);
+	emit_palette(&handle);
 }
 
 fn get_current_state(file_state: &State<FileState>, selection_state: &State<SelectionState>) -> HistoryItem {
 	HistoryItem{
 		frames: Mutex::new(file_state.frames.lock().unwrap().clone()),
 		palette: Mutex::new(file_state.palette.lock().unwrap().clone()),
+		pixel_format: Mutex::new(*file_state.pixel_format.lock().unwrap()),
 		selected_frames: Mutex::new(selection_state.selected_frames.lock().unwrap().clone())
 	}
 }
@@ -71,4 +81,5 @@ fn set_current_state(file_state: &State<FileState>, selection_state: &State<Sele
 	*file_state.frames.lock().unwrap() = new_history_item.frames.lock().unwrap().clone();
 	*selection_state.selected_frames.lock().unwrap() = new_history_item.selected_frames.lock().unwrap().clone();
 	*file_state.palette.lock().unwrap() = new_history_item.palette.lock().unwrap().clone();
+	*file_state.pixel_format.lock().unwrap() = *new_history_item.pixel_format.lock().unwrap();
 }
@@ -0,0 +1,112 @@
+use std::fs;
+
+use tauri::{ AppHandle, Manager, State, Emitter };
+
+use spritist_core::{
+	detect::detect_format,
+	inspect::{ FileReport, inspect_file as inspect_file_contents }
+};
+
+use crate::{
+	error_dialog,
+	file::FileState,
+	edit::pixel_format_name
+};
+
+#[derive(Clone, serde::Serialize)]
+struct RunStatsPayload {
+	color_runs: u32,
+	transparent_runs: u32,
+	color_pixels: u64,
+	transparent_pixels: u64
+}
+
+#[derive(Clone, serde::Serialize)]
+struct FrameReportPayload {
+	offset: u32,
+	width: u16,
+	height: u16,
+	line_offsets: Vec<u32>,
+	data_size: usize,
+	raw_size: usize,
+	runs: Option<RunStatsPayload>
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct InspectFilePayload {
+	file_name: String,
+	file_is_modified: bool,
+	format: String,
+	file_size: usize,
+	flags: u32,
+	pixel_format: String,
+	background_size: Option<(u16, u16)>,
+	header_size: usize,
+	frames: Vec<FrameReportPayload>,
+	anomalies: Vec<String>
+}
+
+fn report_payload(file_name: String, file_is_modified: bool, report: FileReport) -> InspectFilePayload {
+	InspectFilePayload {
+		file_name,
+		file_is_modified,
+		format: report.format.name().to_string(),
+		file_size: report.file_size,
+		flags: report.flags,
+		pixel_format: pixel_format_name(report.pixel_format),
+		background_size: report.background_size,
+		header_size: report.header_size,
+		frames: report.frames.into_iter()
+			.map(|frame| FrameReportPayload {
+				offset: frame.offset,
+				width: frame.width,
+				height: frame.height,
+				line_offsets: frame.line_offsets,
+				data_size: frame.data_size,
+				raw_size: frame.raw_size,
+				runs: frame.runs.map(|runs| RunStatsPayload {
+					color_runs: runs.color_runs,
+					transparent_runs: runs.transparent_runs,
+					color_pixels: runs.color_pixels,
+					transparent_pixels: runs.transparent_pixels
+				})
+			})
+			.collect(),
+		anomalies: report.anomalies.iter().map(|anomaly| anomaly.to_string()).collect()
+	}
+}
+
+// reads the file from disk, since the report is about how it's stored rather than what's being edited
+fn inspect_open_file(file_state: &State<FileState>) -> Result<InspectFilePayload, String> {
+	let file_path = file_state.file_path.lock().unwrap().clone()
+		.ok_or("Save the file before inspecting it.")?;
+	let bytes = fs::read(&file_path).map_err(|why| why.to_string())?;
+	let extension = file_path.extension().and_then(|extension| extension.to_str());
+	let format = file_state.file_format.lock().unwrap()
+		.or_else(|| detect_format(&bytes, extension))
+		.ok_or("The file isn't in a format Spritist can read.")?;
+	let report = inspect_file_contents(&bytes, format)
+		.ok_or(format!("{} files can't be inspected. Only C16, S16, BLK, M16 and N16 files can.", format.name()))?
+		.map_err(|why| why.to_string())?;
+	let file_name = file_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+	Ok(report_payload(file_name, *file_state.file_is_modified.lock().unwrap(), report))
+}
+
+pub fn activate_inspect_file(handle: &AppHandle) {
+	let file_state: State<FileState> = handle.state();
+	match inspect_open_file(&file_state) {
+		Ok(payload) => handle.emit("inspect_file", payload).unwrap(),
+		Err(why) => error_dialog(why)
+	}
+}
+
+#[tauri::command]
+pub fn inspect_file(file_state: State<FileState>) -> Option<InspectFilePayload> {
+	match inspect_open_file(&file_state) {
+		Ok(payload) => Some(payload),
+		Err(why) => {
+			error_dialog(why);
+			None
+		}
+	}
+}
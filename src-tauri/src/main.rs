@@ -4,6 +4,7 @@
 use std::io::Cursor;
 use std::sync::Mutex;
 use std::path::PathBuf;
+use std::error::Error;
 
 use tauri::{
 	Builder,
@@ -27,6 +28,14 @@ use rfd::{ MessageDialog, MessageButtons };
 
 use image::ImageFormat;
 
+use spritist_core::{
+	SpriteError,
+	format::PixelFormat,
+	color_match::ColorMetric,
+	dither::DitherMethod,
+	palette_generator::GeneratorMethod
+};
+
 mod file;
 mod state;
 mod history;
@@ -35,10 +44,11 @@ mod clipboard;
 mod view;
 mod edit;
 mod config;
-mod format;
 mod palette;
 mod export;
 mod import;
+mod batch;
+mod inspect;
 
 fn main() {
 
@@ -50,7 +60,7 @@ fn main() {
 					if !paths.is_empty() {
 						window.app_handle().emit("show_spinner", ()).unwrap();
 						if let Err(why) = file::drop_files(window.app_handle(), paths) {
-							error_dialog(why.to_string());
+							file_error_dialog(window.app_handle(), why);
 						}
 						window.app_handle().emit("hide_spinner", ()).unwrap();
 					}
@@ -82,6 +92,9 @@ fn main() {
 						&MenuItem::with_id(handle, "export_gif", "Export GIF", true, Some("CmdOrCtrl+G"))?,
 						&MenuItem::with_id(handle, "export_spritesheet", "Export Spritesheet", true, Some("CmdOrCtrl+Shift+T"))?,
 					])?,
+					&MenuItem::with_id(handle, "batch_convert", "Batch Convert...", true, None::<&str>)?,
+					&MenuItem::with_id(handle, "inspect_file", "Inspect File...", true, None::<&str>)?,
+					&MenuItem::with_id(handle, "validate_file", "Validate File", true, None::<&str>)?,
 					&PredefinedMenuItem::separator(handle)?,
 					&MenuItem::with_id(handle, "quit", "Quit", true, Some("CmdOrCtrl+Q"))?,
 				])?,
@@ -107,9 +120,11 @@ fn main() {
 					&PredefinedMenuItem::separator(handle)?,
 					&CheckMenuItem::with_id(handle, "pixel_format_555", "Pixel Format 555", true, false, None::<&str>)?,
 					&CheckMenuItem::with_id(handle, "pixel_format_565", "Pixel Format 565 (Default)", true, true, None::<&str>)?,
+					&MenuItem::with_id(handle, "check_pixel_format", "Check Pixel Format", true, None::<&str>)?,
 					&PredefinedMenuItem::separator(handle)?,
 					&MenuItem::with_id(handle, "insert_image", "Insert Image...", true, Some("CmdOrCtrl+I"))?,
 					&MenuItem::with_id(handle, "replace_frame", "Replace Frame...", true, Some("CmdOrCtrl+R"))?,
+					&MenuItem::with_id(handle, "frame_details", "Frame Details...", true, None::<&str>)?,
 				])?,
 
 				&Submenu::with_id_and_items(handle, "view", "View", true, &[
@@ -125,12 +140,30 @@ fn main() {
 						&MenuItem::with_id(handle, "convert_to_original", "Convert to Original Palette", true, None::<&str>)?,
 						&MenuItem::with_id(handle, "convert_to_reversed", "Convert to Reversed Palette", true, None::<&str>)?,
 						&MenuItem::with_id(handle, "convert_to_palette", "Convert to Palette...", true, None::<&str>)?,
+						&Submenu::with_id_and_items(handle, "generate_palette", "Convert to Generated Palette", true, &[
+							&MenuItem::with_id(handle, "generate_palette_median-cut", "Median Cut", true, None::<&str>)?,
+							&MenuItem::with_id(handle, "generate_palette_k-means", "K-Means", true, None::<&str>)?,
+						])?,
+						&Submenu::with_id_and_items(handle, "palette_dither", "Conversion Dithering", true, &[
+							&CheckMenuItem::with_id(handle, "palette_dither_none", "None", true, true, None::<&str>)?,
+							&CheckMenuItem::with_id(handle, "palette_dither_floyd-steinberg", "Floyd-Steinberg", true, false, None::<&str>)?,
+							&CheckMenuItem::with_id(handle, "palette_dither_atkinson", "Atkinson", true, false, None::<&str>)?,
+							&CheckMenuItem::with_id(handle, "palette_dither_bayer", "Ordered (Bayer)", true, false, None::<&str>)?,
+						])?,
+						&Submenu::with_id_and_items(handle, "color_metric", "Color Matching", true, &[
+							&CheckMenuItem::with_id(handle, "color_metric_rgb", "RGB", true, true, None::<&str>)?,
+							&CheckMenuItem::with_id(handle, "color_metric_weighted-rgb", "Weighted RGB", true, false, None::<&str>)?,
+							&CheckMenuItem::with_id(handle, "color_metric_lab", "Perceptual (CIELAB)", true, false, None::<&str>)?,
+						])?,
+						&PredefinedMenuItem::separator(handle)?,
+						&MenuItem::with_id(handle, "export_palette", "Export Palette...", true, None::<&str>)?,
 					])?,
 					&PredefinedMenuItem::separator(handle)?,
 					&CheckMenuItem::with_id(handle, "view_as_sprite", "View As Sprite", true, true, None::<&str>)?,
 					&CheckMenuItem::with_id(handle, "view_as_bg", "View As Background", true, false, None::<&str>)?,
 					&PredefinedMenuItem::separator(handle)?,
 					&CheckMenuItem::with_id(handle, "show_image_info", "Show Image Info", true, true, None::<&str>)?,
+					&CheckMenuItem::with_id(handle, "legacy_colors", "Legacy Colors", true, false, None::<&str>)?,
 					&Submenu::with_id_and_items(handle, "transparent_color", "Transparent Color", true, &[
 						&CheckMenuItem::with_id(handle, "transparent_black", "Black", true, true, None::<&str>)?,
 						&CheckMenuItem::with_id(handle, "transparent_white", "White", true, false, None::<&str>)?,
@@ -175,6 +208,9 @@ fn main() {
 					"export_spritesheet" => handle.emit("export_spritesheet", "").unwrap(),
 					"import_png_as_blk" => import::activate_import_png_as_blk(handle),
 					"import_spritesheet" => import::activate_import_spritesheet(handle),
+					"batch_convert" => handle.emit("batch_convert", "").unwrap(),
+					"inspect_file" => inspect::activate_inspect_file(&handle),
+					"validate_file" => file::validate_file(&handle),
 					"quit" => try_quit(handle),
 
 					// EDIT MENU
@@ -190,10 +226,12 @@ fn main() {
 					"shift_right" => edit::shift_selection(handle.clone(), file_state, selection_state, 1, 0),
 					"shift_up" => edit::shift_selection(handle.clone(), file_state, selection_state, 0, -1),
 					"shift_down" => edit::shift_selection(handle.clone(), file_state, selection_state, 0, 1),
-					"pixel_format_555" => file::set_pixel_format(&handle, format::PixelFormat::Format555),
-					"pixel_format_565" => file::set_pixel_format(&handle, format::PixelFormat::Format565),
+					"pixel_format_555" => edit::activate_convert_pixel_format(&handle, PixelFormat::Format555),
+					"pixel_format_565" => edit::activate_convert_pixel_format(&handle, PixelFormat::Format565),
+					"check_pixel_format" => edit::check_pixel_format(&handle, true),
 					"insert_image" => file::activate_insert_image(handle),
 					"replace_frame" => file::activate_replace_frame(handle.clone(), selection_state),
+					"frame_details" => edit::activate_frame_details(handle.clone(), file_state, selection_state),
 
 					// VIEW MENU
 					"reset_zoom" => view::reset_zoom(handle.clone(), view_state),
@@ -205,12 +243,26 @@ fn main() {
 					"convert_to_palette" => palette::activate_convert_to_palette(handle),
 					"convert_to_original" => palette::convert_to_original(handle),
 					"convert_to_reversed" => palette::convert_to_reversed(handle),
+					"generate_palette_median-cut" => palette::activate_generate_palette(handle, GeneratorMethod::MedianCut),
+					"generate_palette_k-means" => palette::activate_generate_palette(handle, GeneratorMethod::KMeans),
+					"export_palette" => palette::activate_export_palette(handle),
+					"palette_dither_none" => config::set_palette_dither(&handle, DitherMethod::None, false),
+					"palette_dither_floyd-steinberg" => config::set_palette_dither(&handle, DitherMethod::FloydSteinberg, false),
+					"palette_dither_atkinson" => config::set_palette_dither(&handle, DitherMethod::Atkinson, false),
+					"palette_dither_bayer" => config::set_palette_dither(&handle, DitherMethod::Bayer, false),
+					"color_metric_rgb" => config::set_color_metric(&handle, ColorMetric::Rgb, false),
+					"color_metric_weighted-rgb" => config::set_color_metric(&handle, ColorMetric::WeightedRgb, false),
+					"color_metric_lab" => config::set_color_metric(&handle, ColorMetric::Lab, false),
 					"view_as_sprite" => view::view_as_sprite(handle),
 					"view_as_bg" => view::view_as_bg(handle),
 					"show_image_info" => {
 						let current_value = config_state.show_image_info.lock().unwrap().to_owned();
 						config::set_show_image_info(&handle, !current_value, false);
 					},
+					"legacy_colors" => {
+						let current_value = config_state.legacy_colors.lock().unwrap().to_owned();
+						config::set_legacy_colors(&handle, !current_value, false);
+					},
 					"transparent_black" => config::set_transparent_color(&handle, config::TransparentColor::Black, false),
 					"transparent_white" => config::set_transparent_color(&handle, config::TransparentColor::White, false),
 					"transparent_none" => config::set_transparent_color(&handle, config::TransparentColor::None, false),
@@ -236,7 +288,10 @@ fn main() {
 			show_image_info: Mutex::new(true),
 			transparent_color: Mutex::new(config::TransparentColor::Black),
 			theme: Mutex::new(config::Theme::Dark),
-			show_toolbar: Mutex::new(true)
+			show_toolbar: Mutex::new(true),
+			legacy_colors: Mutex::new(false),
+			palette_dither: Mutex::new(DitherMethod::None),
+			color_metric: Mutex::new(ColorMetric::Rgb)
 		})
 		.manage(selection::SelectionState {
 			selected_frames: Mutex::new(Vec::new())
@@ -290,6 +345,17 @@ fn main() {
 			export::export_gif,
 			export::export_spritesheet,
 			export::export_spritebuilder_spritesheet,
+			edit::set_frame_details,
+			edit::preview_pixel_format_conversion,
+			edit::convert_pixel_format,
+			inspect::inspect_file,
+			palette::get_palette,
+			palette::set_palette_color,
+			palette::swap_palette_colors,
+			palette::move_palette_color,
+			batch::get_batch_folder,
+			batch::select_batch_folder,
+			batch::batch_convert,
 			error_dialog,
 			try_quit
 		])
@@ -315,11 +381,13 @@ fn main() {
 			};
 
 			let file_state: State<file::FileState> = handle.state();
+			let config_state: State<config::ConfigState> = handle.state();
 			let frames = file_state.frames.lock().unwrap();
 			match frames.get(frame_index) {
 				Some(frame) => {
 					let mut frame_data = Cursor::new(Vec::new());
-					if let Ok(()) = frame.image.write_to(&mut frame_data, ImageFormat::Png) {
+					let image = frame.expanded_image(config::channel_expansion(&config_state));
+					if let Ok(()) = image.write_to(&mut frame_data, ImageFormat::Png) {
 						http::Response::builder()
 							.header("Content-Type", "image/png")
 							.body(frame_data.into_inner())
@@ -354,3 +422,24 @@ fn error_dialog(error_message: String) {
 		.set_buttons(MessageButtons::Ok)
 		.show();
 }
+
+// shows where in the file a sprite error happened, if it's known
+fn file_error_dialog(handle: &AppHandle, error: Box<dyn Error>) {
+	match error.downcast_ref::<SpriteError>() {
+		Some(sprite_error) => {
+			let mut error_message = sprite_error.description();
+			if let Some(frame) = sprite_error.frame {
+				error_message.push_str(&format!("\n\nFrame: {}", frame));
+			}
+			if let Some(offset) = sprite_error.offset {
+				let separator = if sprite_error.frame.is_some() { "\n" } else { "\n\n" };
+				error_message.push_str(&format!("{}Byte offset: {} ({:#x})", separator, offset, offset));
+			}
+			if let Some(location) = sprite_error.location() {
+				handle.emit("notify", format!("Error at {}", location)).unwrap();
+			}
+			error_dialog(error_message);
+		}
+		None => error_dialog(error.to_string())
+	}
+}
@@ -1,96 +1,54 @@
 use std::{
-	fs,
 	error::Error,
 	path::Path
 };
 
-use tauri::{ AppHandle, Manager, State };
+use tauri::{ AppHandle, Manager, State, Emitter };
 use tauri::menu::MenuItemKind;
 
-use bytes::{ Bytes, Buf };
-use image::{ Rgba, RgbaImage };
+use rfd::FileDialog;
+
+use spritist_core::{
+	Frame,
+	palette::{
+		Palette,
+		PaletteType,
+		original_palette,
+		reversed_palette,
+		RESERVED_COLORS,
+		swap_palette_for_frame,
+		translate_colors,
+		remap_color_indexes,
+		recolor_frames
+	},
+	palette_file::{ PaletteFormat, read_palette_file, write_palette_file },
+	palette_generator::{ GeneratorMethod, generate_palette }
+};
 
 use crate::{
 	error_dialog,
-	file::{ FileState, Frame, create_open_dialog },
+	config::ConfigState,
+	file::{ FileState, create_open_dialog },
+	selection::SelectionState,
 	state::{ redraw, update_window_title },
 	history::add_state_to_history
 };
 
-#[derive(Clone)]
-pub struct Palette {
-	pub palette_type: PaletteType,
-	pub file_name: Option<String>,
-	pub colors: [(u8, u8, u8); 256]
-}
-
-#[derive(Clone, PartialEq)]
-pub enum PaletteType {
-	Original,
-	Reversed,
-	Custom
-}
-
-impl Palette {
-	pub fn get_color(&self, color_index: u8) -> Rgba<u8> {
-		if color_index == 0 {
-			Rgba([0, 0, 0, 0])
-		} else {
-			match self.colors.get(color_index as usize) {
-				Some(color) => Rgba([color.0, color.1, color.2, 255]),
-				None => Rgba([0, 0, 0, 255])
-			}
-		}
-	}
-
-	pub fn find_color_index(&self, color: Rgba<u8>) -> Option<u8> {
-		if color[3] == 0 { return Some(0); }
-		for (i, palette_color) in self.colors.iter().enumerate() {
-			if palette_color.0 == color[0] && palette_color.1 == color[1] && palette_color.2 == color[2] {
-				return Some(i as u8);
-			}
-		}
-		None
-	}
-
-	pub fn get_closest_color_indexes(&self, image: &RgbaImage) -> Vec<u8> {
-		let mut color_indexes: Vec<u8> = Vec::new();
-		for y in 0..image.height() {
-			for x in 0..image.width() {
-				let color = image.get_pixel(x, y);
-				let color_index = self.find_closest_color_index(color);
-				color_indexes.push(color_index);
-			}
+fn palette_dialog_extensions() -> Vec<String> {
+	let mut dialog_extensions: Vec<String> = Vec::new();
+	for format in PaletteFormat::ALL {
+		for extension in format.extensions() {
+			dialog_extensions.push(extension.to_string());
+			dialog_extensions.push(extension.to_uppercase());
 		}
-		color_indexes
-	}
-
-	pub fn find_closest_color_index(&self, color: &Rgba<u8>) -> u8 {
-		if color[3] == 0 { return 0; }
-		let mut best_fit_index: u8 = 0;
-		let mut best_fit_dist = 255*255*3;
-		for (i, palette_color) in self.colors.iter().enumerate() {
-			if palette_color.0 == color[0] && palette_color.1 == color[1] && palette_color.2 == color[2] {
-				return i as u8
-			} else {
-				let dist_to_color =
-					i32::pow(color[0] as i32 - palette_color.0 as i32, 2) +
-					i32::pow(color[1] as i32 - palette_color.1 as i32, 2) +
-					i32::pow(color[2] as i32 - palette_color.2 as i32, 2);
-				if dist_to_color < best_fit_dist {
-					best_fit_index = i as u8;
-					best_fit_dist = dist_to_color;
-				}
-			}
-		}
-		best_fit_index
 	}
+	dialog_extensions
 }
 
 pub fn activate_load_palette(handle: AppHandle) {
 	let file_handle = create_open_dialog(&handle, false)
 		.set_title("Load Palette")
-		.add_filter("SPR Palettes", &["dta", "DTA", "pal", "PAL"])
+		.add_filter("Palettes", &palette_dialog_extensions())
 		.pick_file();
 	if let Some(file_handle) = file_handle {
 		if let Err(why) = load_palette_from_path(&handle, file_handle.as_path()) {
@@ -101,11 +59,8 @@ pub fn activate_load_palette(handle: AppHandle) {
 }
 
 fn load_palette_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
-	let bytes = fs::read(file_path)?;
-	let colors = read_color_data(&bytes)?;
-	let file_name = file_path.file_name().map(|file_name| file_name.to_string_lossy().into());
+	let new_palette = read_palette_file(file_path)?;
 	let file_state: State<FileState> = handle.state();
-	let new_palette = Palette{ palette_type: PaletteType::Custom, file_name, colors };
 	load_palette(handle, file_state, new_palette)?;
 	update_palette_menu_items(handle);
 	Ok(())
@@ -135,6 +90,7 @@ fn load_palette(handle: &AppHandle, file_state: State<FileState>, palette: Palet
 			*file_state.palette.lock().unwrap() = palette;
 			update_window_title(handle);
 			redraw(handle);
+			emit_palette(handle);
 			Ok(())
 		}
 		Err(why) => Err(why)
@@ -144,7 +100,7 @@ fn load_palette(handle: &AppHandle, file_state: State<FileState>, palette: Palet
 pub fn activate_convert_to_palette(handle: AppHandle) {
 	let file_handle = create_open_dialog(&handle, false)
 		.set_title("Convert to Palette")
-		.add_filter("SPR Palettes", &["dta", "DTA", "pal", "PAL"])
+		.add_filter("Palettes", &palette_dialog_extensions())
 		.save_file();
 	if let Some(file_handle) = file_handle {
 		if let Err(why) = convert_to_palette_from_path(&handle, file_handle.as_path()) {
@@ -155,11 +111,8 @@ pub fn activate_convert_to_palette(handle: AppHandle) {
 }
 
 fn convert_to_palette_from_path(handle: &AppHandle, file_path: &Path) -> Result<(), Box<dyn Error>> {
-	let bytes = fs::read(file_path)?;
-	let colors = read_color_data(&bytes)?;
-	let file_name = file_path.file_name().map(|file_name| file_name.to_string_lossy().into());
+	let new_palette = read_palette_file(file_path)?;
 	let file_state: State<FileState> = handle.state();
-	let new_palette = Palette { palette_type: PaletteType::Custom, file_name, colors };
 	convert_to_palette(handle, file_state, new_palette)?;
 	Ok(())
 }
@@ -181,139 +134,87 @@ pub fn convert_to_reversed(handle: AppHandle) {
 }
 
 fn convert_to_palette(handle: &AppHandle, file_state: State<FileState>, palette: Palette) -> Result<(), Box<dyn Error>> {
-	match translate_colors(handle, &palette) {
+	let config_state: State<ConfigState> = handle.state();
+	let dither = *config_state.palette_dither.lock().unwrap();
+	let palette = palette.with_color_metric(*config_state.color_metric.lock().unwrap());
+	let translated_frames = translate_colors(&file_state.frames.lock().unwrap(), &palette, dither);
+	match translated_frames {
 		Ok(frames) => {
 			add_state_to_history(handle);
 			*file_state.frames.lock().unwrap() = frames;
 			*file_state.palette.lock().unwrap() = palette;
 			update_window_title(handle);
 			redraw(handle);
+			emit_palette(handle);
 			Ok(())
 		}
 		Err(why) => Err(why)
 	}
 }
 
-fn read_color_data(contents: &[u8]) -> Result<[(u8, u8, u8); 256], Box<dyn Error>> {
-	let mut buffer = Bytes::copy_from_slice(contents);
-	let mut colors: [(u8, u8, u8); 256] = [(0, 0, 0); 256];
+/// The open file's palette, matching colors the way the config says. Use it
+/// for anything that has to find the closest colors, like saving SPR files.
+pub fn matching_palette(handle: &AppHandle) -> Palette {
+	let file_state: State<FileState> = handle.state();
+	let config_state: State<ConfigState> = handle.state();
+	let color_metric = *config_state.color_metric.lock().unwrap();
+	let palette = file_state.palette.lock().unwrap().clone();
+	palette.with_color_metric(color_metric)
+}
 
-	for color in &mut colors {
-		if buffer.remaining() < 3 {
-			return Err("Invalid palette data. File is too short.".into());
+/// Makes a palette for the selected frames, or all of them if none are
+/// selected, and converts the frames to it.
+pub fn activate_generate_palette(handle: AppHandle, method: GeneratorMethod) {
+	let file_state: State<FileState> = handle.state();
+	let selection_state: State<SelectionState> = handle.state();
+	let frames: Vec<Frame> = {
+		let frames = file_state.frames.lock().unwrap();
+		let selected_frames = selection_state.selected_frames.lock().unwrap();
+		if selected_frames.is_empty() {
+			frames.clone()
+		} else {
+			selected_frames.iter().filter_map(|i| frames.get(*i)).cloned().collect()
 		}
-		let r = buffer.get_u8() * 4;
-		let g = buffer.get_u8() * 4;
-		let b = buffer.get_u8() * 4;
-		*color = (r, g, b);
+	};
+	let result = generate_palette(&frames, method)
+		.and_then(|palette| convert_to_palette(&handle, file_state, palette));
+	if let Err(why) = result {
+		error_dialog(why.to_string());
 	}
-
-	format_colors(colors);
-
-	Ok(colors)
-}
-
-fn format_colors(mut colors: [(u8, u8, u8); 256]) {
-	// replace last 10 colors, because that's what C1 does for some reason
-	colors[246] = (255, 255, 255);
-	colors[247] = (192, 192, 192);
-	colors[248] = (128, 128, 128);
-	colors[249] = (255, 0, 0);
-	colors[250] = (0, 255, 0);
-	colors[251] = (255, 255, 0);
-	colors[252] = (0, 0, 255);
-	colors[253] = (255, 0, 255);
-	colors[254] = (0, 255, 255);
-	colors[255] = (255, 255, 255);
+	update_palette_menu_items(&handle);
 }
 
-fn swap_palette(handle: &AppHandle, new_palette: &Palette) -> Result<Vec<Frame>, Box<dyn Error>> {
+pub fn activate_export_palette(handle: AppHandle) {
 	let file_state: State<FileState> = handle.state();
-	let mut new_frames: Vec<Frame> = Vec::new();
-	for frame in file_state.frames.lock().unwrap().iter() {
-		let new_frame = swap_palette_for_frame(frame, new_palette)?;
-		new_frames.push(new_frame);
+	let palette = file_state.palette.lock().unwrap().clone();
+	let mut file_dialog = FileDialog::new()
+		.set_title("Export Palette")
+		.set_file_name("palette.dta");
+	if let Some(parent_dir) = file_state.file_path.lock().unwrap().as_ref().and_then(|file_path| file_path.parent()) {
+		file_dialog = file_dialog.set_directory(parent_dir);
 	}
-	Ok(new_frames)
-}
-
-fn swap_palette_for_frame(frame: &Frame, palette: &Palette) -> Result<Frame, Box<dyn Error>> {
-	let width = frame.image.width();
-	let height = frame.image.height();
-
-	let mut new_image = RgbaImage::new(width, height);
-
-	// find color with - same index in new palette
-	for y in 0..height {
-		for x in 0..width {
-			let pixel_index = x + (y * width);
-			match frame.color_indexes.get(pixel_index as usize) {
-				Some(color_index) => {
-					let pixel = palette.get_color(*color_index);
-					new_image.put_pixel(x, y, pixel);
-				}
-				None => {
-					return Err("Unable to swap palettes because not all the frames have indexed color (SPR format). Try converting to palette instead.".into());
-				}
-			}
+	for format in PaletteFormat::ALL {
+		file_dialog = file_dialog.add_filter(format.name(), format.extensions());
+	}
+	if let Some(file_handle) = file_dialog.save_file() {
+		match write_palette_file(file_handle.as_path(), &palette) {
+			Ok(()) => handle.emit("notify", "Exported palette".to_string()).unwrap(),
+			Err(why) => error_dialog(why.to_string())
 		}
 	}
-
-	Ok(Frame{
-		image: new_image,
-		color_indexes: frame.color_indexes.clone()
-	})
 }
 
-fn translate_colors(handle: &AppHandle, new_palette: &Palette) -> Result<Vec<Frame>, Box<dyn Error>> {
+fn swap_palette(handle: &AppHandle, new_palette: &Palette) -> Result<Vec<Frame>, Box<dyn Error>> {
 	let file_state: State<FileState> = handle.state();
 	let mut new_frames: Vec<Frame> = Vec::new();
 	for frame in file_state.frames.lock().unwrap().iter() {
-		let new_frame = translate_colors_for_frame(frame, new_palette)?;
+		let new_frame = swap_palette_for_frame(frame, new_palette)?;
 		new_frames.push(new_frame);
 	}
 	Ok(new_frames)
 }
 
-fn translate_colors_for_frame(frame: &Frame, palette: &Palette) -> Result<Frame, Box<dyn Error>> {
-	let width = frame.image.width();
-	let height = frame.image.height();
-
-	let color_indexes: Vec<u8> = palette.get_closest_color_indexes(&frame.image);
-	let mut new_image = RgbaImage::new(width, height);
-
-	for y in 0..height {
-		for x in 0..width {
-			let pixel_index = x + (y * width);
-			match color_indexes.get(pixel_index as usize) {
-				Some(color_index) => {
-					let pixel = palette.get_color(*color_index);
-					new_image.put_pixel(x, y, pixel);
-				}
-				None => {
-					return Err("Unable to swap palettes because not all the frames have indexed color (SPR format). Try converting to palette instead.".into());
-				}
-			}
-		}
-	}
-
-	Ok(Frame{ image: new_image, color_indexes })
-}
-
-pub fn original_palette() -> Palette {
-	let colors = [ (0, 0, 0), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (252, 252, 252), (16, 8, 8), (20, 24, 40), (24, 40, 16), (24, 36, 48), (44, 16, 8), (40, 24, 36), (52, 40, 16), (48, 44, 48), (24, 28, 68), (20, 52, 84), (24, 60, 96), (36, 28, 68), (44, 52, 72), (44, 56, 104), (28, 64, 28), (28, 64, 40), (52, 72, 24), (52, 72, 44), (60, 96, 24), (60, 96, 40), (24, 64, 92), (28, 64, 100), (52, 68, 80), (44, 76, 104), (56, 96, 76), (60, 96, 112), (72, 24, 8), (72, 28, 36), (80, 44, 16), (72, 52, 44), (104, 24, 12), (108, 28, 36), (108, 48, 16), (104, 52, 36), (72, 56, 72), (72, 56, 104), (104, 52, 72), (116, 52, 104), (80, 72, 20), (80, 72, 48), (80, 100, 24), (76, 104, 44), (112, 72, 20), (108, 76, 44), (112, 100, 20), (116, 100, 48), (76, 76, 76), (76, 84, 108), (84, 100, 80), (84, 100, 112), (104, 84, 76), (104, 88, 104), (112, 104, 80), (108, 108, 108), (48, 60, 132), (56, 92, 144), (64, 60, 132), (76, 88, 140), (72, 88, 176), (80, 104, 140), (72, 108, 172), (100, 88, 136), (100, 92, 172), (108, 112, 140), (108, 116, 168), (76, 92, 196), (80, 116, 200), (92, 112, 236), (104, 120, 204), (100, 120, 244), (104, 140, 52), (92, 132, 76), (92, 128, 104), (108, 140, 76), (116, 136, 112), (120, 164, 76), (120, 164, 104), (88, 128, 140), (92, 128, 184), (112, 132, 148), (116, 136, 172), (124, 164, 140), (120, 164, 176), (88, 132, 204), (88, 144, 228), (88, 164, 240), (112, 136, 204), (116, 136, 252), (120, 160, 216), (112, 164, 236), (140, 24, 16), (144, 28, 36), (136, 52, 16), (140, 52, 40), (172, 24, 16), (172, 28, 32), (168, 48, 16), (172, 48, 40), (152, 52, 72), (140, 76, 20), (140, 80, 40), (144, 104, 20), (144, 104, 48), (172, 80, 20), (168, 84, 40), (176, 104, 20), (172, 108, 44), (136, 84, 72), (136, 88, 108), (140, 108, 76), (136, 116, 108), (172, 80, 72), (176, 84, 100), (168, 116, 72), (172, 116, 104), (208, 44, 28), (212, 52, 72), (200, 84, 20), (200, 84, 40), (204, 104, 20), (204, 112, 44), (232, 80, 20), (232, 80, 44), (232, 116, 20), (232, 116, 40), (204, 80, 72), (204, 84, 100), (204, 116, 72), (200, 116, 104), (232, 80, 80), (236, 88, 96), (240, 112, 72), (236, 112, 112), (144, 60, 132), (140, 80, 132), (132, 120, 144), (132, 120, 168), (168, 120, 136), (164, 124, 164), (128, 124, 196), (208, 48, 128), (216, 112, 136), (236, 116, 204), (164, 136, 44), (148, 132, 80), (144, 136, 112), (136, 172, 80), (140, 172, 108), (176, 136, 80), (172, 140, 108), (180, 164, 80), (180, 168, 112), (156, 196, 60), (164, 208, 92), (208, 136, 24), (208, 136, 48), (212, 168, 20), (208, 168, 44), (240, 140, 20), (236, 140, 44), (244, 172, 20), (244, 172, 48), (204, 140, 76), (200, 148, 104), (208, 168, 80), (208, 168, 112), (236, 144, 72), (236, 144, 100), (240, 172, 76), (236, 176, 108), (208, 196, 56), (244, 204, 12), (248, 204, 48), (252, 240, 12), (252, 236, 44), (212, 196, 80), (212, 196, 112), (200, 244, 80), (204, 244, 108), (248, 200, 76), (244, 204, 108), (248, 236, 76), (252, 232, 112), (140, 136, 144), (140, 144, 172), (144, 168, 144), (148, 168, 180), (168, 144, 140), (164, 152, 176), (176, 168, 144), (172, 168, 180), (136, 148, 204), (132, 152, 248), (148, 164, 208), (144, 168, 252), (160, 156, 196), (172, 172, 204), (164, 184, 244), (168, 200, 168), (152, 196, 196), (176, 192, 208), (168, 196, 252), (176, 232, 196), (184, 228, 232), (204, 144, 144), (200, 152, 164), (204, 176, 140), (200, 176, 176), (236, 144, 140), (236, 144, 164), (232, 180, 136), (232, 180, 168), (196, 184, 200), (196, 188, 224), (244, 172, 204), (212, 200, 144), (208, 200, 176), (204, 240, 136), (204, 228, 176), (240, 204, 144), (236, 208, 172), (248, 232, 144), (248, 236, 176), (212, 200, 204), (200, 200, 232), (212, 228, 204), (216, 232, 228), (228, 212, 208), (224, 208, 224), (240, 232, 208), (244, 244, 236), (252, 252, 252), (0, 0, 0), (0, 0, 0), (0, 0, 0), (255, 255, 255), (192, 192, 192), (128, 128, 128), (255, 0, 0), (0, 255, 0), (255, 255, 0), (0, 0, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255) ];
-	Palette { palette_type: PaletteType::Original, file_name: None, colors }
-}
-
-pub fn reversed_palette() -> Palette {
-	let file_name = Some("reversed palette".to_string());
-	let mut colors = original_palette().colors;
-	colors.reverse();
-	format_colors(colors);
-	Palette { palette_type: PaletteType::Reversed, file_name, colors }
-}
-
-fn update_palette_menu_items(handle: &AppHandle) {
+pub fn update_palette_menu_items(handle: &AppHandle) {
 	let file_state: State<FileState> = handle.state();
 	let palette_type = file_state.palette.lock().unwrap().palette_type.clone();
 	if let Some(menu) = handle.menu() {
@@ -332,3 +233,80 @@ fn update_palette_menu_items(handle: &AppHandle) {
 		}
 	}
 }
+
+#[derive(Clone, serde::Serialize)]
+pub struct PalettePayload {
+	name: Option<String>,
+	colors: Vec<(u8, u8, u8)>,
+	reserved_colors: Vec<u8>
+}
+
+fn palette_payload(palette: &Palette) -> PalettePayload {
+	PalettePayload {
+		name: palette.file_name.clone(),
+		colors: palette.colors.to_vec(),
+		reserved_colors: RESERVED_COLORS.collect()
+	}
+}
+
+// lets the palette editor know when the palette changes, including by undo and redo
+pub fn emit_palette(handle: &AppHandle) {
+	let file_state: State<FileState> = handle.state();
+	let payload = palette_payload(&file_state.palette.lock().unwrap());
+	handle.emit("update_palette", payload).unwrap();
+}
+
+#[tauri::command]
+pub fn get_palette(file_state: State<FileState>) -> PalettePayload {
+	palette_payload(&file_state.palette.lock().unwrap())
+}
+
+// applies an edit to a copy of the palette, so nothing changes if it fails. If
+// the edit returns a mapping and `remap` is true, the frames' color indexes
+// follow their colors to their new places, otherwise the frames are redrawn
+// with whatever colors their indexes now point to
+fn edit_palette(handle: &AppHandle, remap: bool, edit: impl FnOnce(&mut Palette) -> Result<Option<[u8; 256]>, Box<dyn Error>>) {
+	let file_state: State<FileState> = handle.state();
+	let mut palette = file_state.palette.lock().unwrap().clone();
+	let mapping = match edit(&mut palette) {
+		Ok(mapping) => mapping,
+		Err(why) => {
+			error_dialog(why.to_string());
+			return
+		}
+	};
+	add_state_to_history(handle);
+	palette.palette_type = PaletteType::Custom;
+	if palette.file_name.is_none() {
+		palette.file_name = Some("edited palette".to_string());
+	}
+	let frames = file_state.frames.lock().unwrap().clone();
+	let new_frames = match mapping {
+		Some(mapping) if remap => remap_color_indexes(&frames, &mapping),
+		_ => recolor_frames(&frames, &palette)
+	};
+	*file_state.frames.lock().unwrap() = new_frames;
+	*file_state.palette.lock().unwrap() = palette;
+	update_window_title(handle);
+	update_palette_menu_items(handle);
+	redraw(handle);
+	emit_palette(handle);
+}
+
+#[tauri::command]
+pub fn set_palette_color(handle: AppHandle, color_index: u8, red: u8, green: u8, blue: u8) {
+	edit_palette(&handle, false, |palette| {
+		palette.set_color(color_index, (red, green, blue))?;
+		Ok(None)
+	});
+}
+
+#[tauri::command]
+pub fn swap_palette_colors(handle: AppHandle, first: u8, second: u8, remap: bool) {
+	edit_palette(&handle, remap, |palette| Ok(Some(palette.swap_colors(first, second)?)));
+}
+
+#[tauri::command]
+pub fn move_palette_color(handle: AppHandle, from: u8, to: u8, remap: bool) {
+	edit_palette(&handle, remap, |palette| Ok(Some(palette.move_color(from, to)?)));
+}
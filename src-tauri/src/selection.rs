@@ -2,9 +2,11 @@ use std::sync::Mutex;
 
 use tauri::{ AppHandle, Manager, State, Emitter };
 
+use spritist_core::Frame;
+
 use crate::{
 	state::RedrawPayload,
-	file::{ FileState, Frame },
+	file::FileState,
 	history::add_state_to_history
 };
 
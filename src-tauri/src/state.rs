@@ -1,12 +1,12 @@
 use tauri::{ AppHandle, Manager, State, Emitter };
 
+use spritist_core::{ PixelFormat, palette };
+
 use crate::{
-	palette,
 	file::FileState,
 	view::ViewState,
 	selection::SelectionState,
-	history::HistoryState,
-	format::PixelFormat
+	history::HistoryState
 };
 
 #[derive(Clone, serde::Serialize)]
@@ -29,6 +29,9 @@ pub fn reset_state(handle: &AppHandle) {
 	*file_state.cols.lock().unwrap() = 0;
 	*file_state.rows.lock().unwrap() = 0;
 	*file_state.read_only.lock().unwrap() = false;
+	*file_state.spr_variant.lock().unwrap() = None;
+	*file_state.dta_variant.lock().unwrap() = None;
+	*file_state.file_format.lock().unwrap() = None;
 
 	let selection_state: State<SelectionState> = handle.state();
 	*selection_state.selected_frames.lock().unwrap() = Vec::new();
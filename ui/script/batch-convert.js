@@ -0,0 +1,77 @@
+class BatchConvert {
+	static isOpen() {
+		return document.getElementById('batch-convert-dialog').classList.contains('open')
+	}
+
+	static open() {
+		document.getElementById('batch-convert-dialog').classList.add('open')
+	}
+
+	static close() {
+		document.getElementById('batch-convert-dialog').classList.remove('open')
+	}
+
+	static focusConfirmButton() {
+		document.getElementById('batch-convert-confirm-button').focus()
+	}
+
+	static setup() {
+		document.getElementById('batch-convert-close-button').addEventListener('click', () => {
+			BatchConvert.close()
+		})
+
+		document.getElementById('batch-convert-cancel-button').addEventListener('click', () => {
+			BatchConvert.close()
+		})
+
+		document.getElementById('batch-convert-select-input-button').addEventListener('click', () => {
+			const folderPath = document.getElementById('batch-convert-input').value
+			tauri_invoke('select_batch_folder', { field: 'input', folderPath })
+		})
+
+		document.getElementById('batch-convert-select-output-button').addEventListener('click', () => {
+			const folderPath = document.getElementById('batch-convert-output').value
+			tauri_invoke('select_batch_folder', { field: 'output', folderPath })
+		})
+
+		let onKeydown = (event) => {
+			if (event.key === 'Enter') {
+				event.preventDefault()
+				BatchConvert.focusConfirmButton()
+			}
+		}
+		document.getElementById('batch-convert-input').addEventListener('keydown', onKeydown)
+		document.getElementById('batch-convert-output').addEventListener('keydown', onKeydown)
+
+		document.getElementById('batch-convert-confirm-button').addEventListener('click', () => {
+			const inputDir = document.getElementById('batch-convert-input').value
+			const outputDir = document.getElementById('batch-convert-output').value
+			const fromExtension = document.getElementById('batch-convert-from').value
+			const toTarget = document.getElementById('batch-convert-to').value
+			if (!inputDir) {
+				tauri_invoke('error_dialog', { why: "No input folder selected." })
+			} else {
+				tauri_invoke('batch_convert', { inputDir, outputDir, fromExtension, toTarget })
+			}
+		})
+
+		tauri_listen('batch_convert', () => {
+			if (!document.getElementById('batch-convert-input').value) {
+				tauri_invoke('get_batch_folder').then((folderPath) => {
+					document.getElementById('batch-convert-input').value = folderPath
+				})
+			}
+
+			BatchConvert.open()
+			BatchConvert.focusConfirmButton()
+		})
+
+		tauri_listen('update_batch_folder', (event) => {
+			document.getElementById(`batch-convert-${event.payload.field}`).value = event.payload.folder_path
+		})
+
+		tauri_listen('successful_batch_convert', (event) => {
+			BatchConvert.close()
+		})
+	}
+}
@@ -0,0 +1,73 @@
+class ConvertPixelFormat {
+	static pixelFormat = '565'
+
+	static isOpen() {
+		return document.getElementById('convert-pixel-format-dialog').classList.contains('open')
+	}
+
+	static open() {
+		document.getElementById('convert-pixel-format-dialog').classList.add('open')
+	}
+
+	static close() {
+		document.getElementById('convert-pixel-format-dialog').classList.remove('open')
+	}
+
+	static focusConfirmButton() {
+		document.getElementById('convert-pixel-format-confirm-button').focus()
+	}
+
+	static dither() {
+		return document.getElementById('convert-pixel-format-dither').value
+	}
+
+	static showPreview(preview) {
+		let text = ''
+		if (preview.changed_pixels === 0) {
+			text = 'No colors will change.'
+		} else {
+			const percent = (preview.changed_pixels / preview.total_pixels * 100).toFixed(1)
+			text = `${preview.changed_pixels} of ${preview.total_pixels} pixels (${percent}%) will change color, by up to ${preview.max_difference} out of 255 per channel.`
+		}
+		document.getElementById('convert-pixel-format-preview').innerText = text
+	}
+
+	static setup() {
+		document.getElementById('convert-pixel-format-close-button').addEventListener('click', () => {
+			ConvertPixelFormat.close()
+		})
+
+		document.getElementById('convert-pixel-format-cancel-button').addEventListener('click', () => {
+			ConvertPixelFormat.close()
+		})
+
+		document.getElementById('convert-pixel-format-dither').addEventListener('change', () => {
+			const pixelFormat = ConvertPixelFormat.pixelFormat
+			const dither = ConvertPixelFormat.dither()
+			tauri_invoke('preview_pixel_format_conversion', { pixelFormat, dither }).then((preview) => {
+				if (preview) {
+					ConvertPixelFormat.showPreview(preview)
+				}
+			})
+		})
+
+		document.getElementById('convert-pixel-format-confirm-button').addEventListener('click', () => {
+			const pixelFormat = ConvertPixelFormat.pixelFormat
+			const dither = ConvertPixelFormat.dither()
+			tauri_invoke('convert_pixel_format', { pixelFormat, dither })
+		})
+
+		tauri_listen('convert_pixel_format', (event) => {
+			ConvertPixelFormat.pixelFormat = event.payload.pixel_format
+			document.getElementById('convert-pixel-format-title').innerText = `Convert to Pixel Format ${event.payload.pixel_format}`
+			document.getElementById('convert-pixel-format-dither').value = 'none'
+			ConvertPixelFormat.showPreview(event.payload)
+			ConvertPixelFormat.open()
+			ConvertPixelFormat.focusConfirmButton()
+		})
+
+		tauri_listen('successful_pixel_format_conversion', (event) => {
+			ConvertPixelFormat.close()
+		})
+	}
+}
@@ -0,0 +1,58 @@
+class FrameDetails {
+	static frameIndex = 0
+
+	static isOpen() {
+		return document.getElementById('frame-details-dialog').classList.contains('open')
+	}
+
+	static open() {
+		document.getElementById('frame-details-dialog').classList.add('open')
+	}
+
+	static close() {
+		document.getElementById('frame-details-dialog').classList.remove('open')
+	}
+
+	static focusConfirmButton() {
+		document.getElementById('frame-details-confirm-button').focus()
+	}
+
+	static setup() {
+		document.getElementById('frame-details-close-button').addEventListener('click', () => {
+			FrameDetails.close()
+		})
+
+		document.getElementById('frame-details-cancel-button').addEventListener('click', () => {
+			FrameDetails.close()
+		})
+
+		let onKeydown = (event) => {
+			if (event.key === 'Enter') {
+				event.preventDefault()
+				FrameDetails.focusConfirmButton()
+			}
+		}
+		document.getElementById('frame-details-timestamp').addEventListener('keydown', onKeydown)
+		document.getElementById('frame-details-comment').addEventListener('keydown', onKeydown)
+
+		document.getElementById('frame-details-confirm-button').addEventListener('click', () => {
+			const frameIndex = FrameDetails.frameIndex
+			const timestamp = document.getElementById('frame-details-timestamp').value
+			const comment = document.getElementById('frame-details-comment').value
+			tauri_invoke('set_frame_details', { frameIndex, timestamp, comment })
+		})
+
+		tauri_listen('frame_details', (event) => {
+			FrameDetails.frameIndex = event.payload.frame_index
+			document.getElementById('frame-details-title').innerText = `Frame ${event.payload.frame_index} Details`
+			document.getElementById('frame-details-timestamp').value = event.payload.timestamp
+			document.getElementById('frame-details-comment').value = event.payload.comment
+			FrameDetails.open()
+			document.getElementById('frame-details-timestamp').focus()
+		})
+
+		tauri_listen('successful_frame_details', (event) => {
+			FrameDetails.close()
+		})
+	}
+}
@@ -0,0 +1,99 @@
+class InspectFile {
+	static isOpen() {
+		return document.getElementById('inspect-file-dialog').classList.contains('open')
+	}
+
+	static open() {
+		document.getElementById('inspect-file-dialog').classList.add('open')
+	}
+
+	static close() {
+		document.getElementById('inspect-file-dialog').classList.remove('open')
+	}
+
+	static hex(number) {
+		return '0x' + number.toString(16).padStart(8, '0')
+	}
+
+	static cell(row, text) {
+		const cell = document.createElement('td')
+		cell.innerText = text
+		row.appendChild(cell)
+		return cell
+	}
+
+	static showReport(report) {
+		document.getElementById('inspect-file-title').innerText = `Inspect ${report.file_name}`
+
+		let summary = `${report.format}, ${report.file_size} bytes. Flags ${InspectFile.hex(report.flags)}, pixel format ${report.pixel_format}. `
+		if (report.background_size) {
+			summary += `${report.background_size[0]} x ${report.background_size[1]} tiles. `
+		}
+		summary += `${report.frames.length} frame(s) after ${report.header_size} bytes of headers.`
+		if (report.file_is_modified) {
+			summary += ' This is the saved file, so unsaved changes aren\'t included.'
+		}
+		document.getElementById('inspect-file-summary').innerText = summary
+
+		const frames = document.getElementById('inspect-file-frames')
+		frames.innerHTML = ''
+		report.frames.forEach((frame, i) => {
+			const row = document.createElement('tr')
+			InspectFile.cell(row, i)
+			InspectFile.cell(row, `${frame.width} x ${frame.height}`)
+			InspectFile.cell(row, InspectFile.hex(frame.offset))
+			if (frame.runs) {
+				const percent = (frame.data_size / Math.max(frame.raw_size, 1) * 100).toFixed(0)
+				InspectFile.cell(row, `${frame.data_size} of ${frame.raw_size} (${percent}%)`)
+				InspectFile.cell(row, `${frame.runs.color_runs} color (${frame.runs.color_pixels} px), ${frame.runs.transparent_runs} transparent (${frame.runs.transparent_pixels} px)`)
+			} else {
+				InspectFile.cell(row, `${frame.data_size} of ${frame.raw_size}`)
+				InspectFile.cell(row, '-')
+			}
+			if (frame.line_offsets.length > 0) {
+				const details = document.createElement('details')
+				const detailsSummary = document.createElement('summary')
+				detailsSummary.innerText = `${frame.line_offsets.length} line(s)`
+				details.appendChild(detailsSummary)
+				details.appendChild(document.createTextNode(frame.line_offsets.map((offset) => '0x' + offset.toString(16)).join(' ')))
+				InspectFile.cell(row, '').appendChild(details)
+			} else {
+				InspectFile.cell(row, '-')
+			}
+			frames.appendChild(row)
+		})
+
+		const anomalies = document.getElementById('inspect-file-anomalies')
+		anomalies.innerHTML = ''
+		const messages = report.anomalies.length > 0 ? report.anomalies : ['No anomalies']
+		messages.forEach((message) => {
+			const item = document.createElement('li')
+			item.innerText = message
+			anomalies.appendChild(item)
+		})
+	}
+
+	static setup() {
+		document.getElementById('inspect-file-close-button').addEventListener('click', () => {
+			InspectFile.close()
+		})
+
+		document.getElementById('inspect-file-ok-button').addEventListener('click', () => {
+			InspectFile.close()
+		})
+
+		document.getElementById('inspect-file-refresh-button').addEventListener('click', () => {
+			tauri_invoke('inspect_file').then((report) => {
+				if (report) {
+					InspectFile.showReport(report)
+				}
+			})
+		})
+
+		tauri_listen('inspect_file', (event) => {
+			InspectFile.showReport(event.payload)
+			InspectFile.open()
+			document.getElementById('inspect-file-ok-button').focus()
+		})
+	}
+}
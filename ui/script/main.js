@@ -112,7 +112,7 @@ window.addEventListener('load', () => {
 			event.preventDefault()
 			tauri_invoke('try_quit')
 
-		} else if (AboutDialog.isOpen() || ExportPng.isOpen() || ExportGif.isOpen() || ExportSpritesheet.isOpen() || ImportSpritesheet.isOpen()) {
+		} else if (AboutDialog.isOpen() || ExportPng.isOpen() || ExportGif.isOpen() || ExportSpritesheet.isOpen() || ImportSpritesheet.isOpen() || BatchConvert.isOpen() || FrameDetails.isOpen() || ConvertPixelFormat.isOpen() || InspectFile.isOpen()) {
 			if (ONLY && KEY === 'ESCAPE') {
 				event.preventDefault()
 				AboutDialog.close()
@@ -120,6 +120,10 @@ window.addEventListener('load', () => {
 				ExportGif.close()
 				ExportSpritesheet.close()
 				ImportSpritesheet.close()
+				BatchConvert.close()
+				FrameDetails.close()
+				ConvertPixelFormat.close()
+				InspectFile.close()
 			}
 
 		} else if (CTRL && KEY === 'N') {
@@ -225,6 +229,10 @@ window.addEventListener('load', () => {
 	ExportGif.setup()
 	ExportSpritesheet.setup()
 	ImportSpritesheet.setup()
+	BatchConvert.setup()
+	FrameDetails.setup()
+	ConvertPixelFormat.setup()
+	InspectFile.setup()
 })
 
 const viewAsSprite = () => {